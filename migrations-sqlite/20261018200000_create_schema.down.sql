@@ -0,0 +1,8 @@
+DROP TABLE exchange_rate;
+DROP TABLE debt;
+DROP TABLE cost;
+DROP TABLE payment;
+DROP TABLE account;
+DROP TABLE identity;
+DROP TABLE "user";
+DROP TABLE ledger;
@@ -0,0 +1,126 @@
+-- schema of all postgres migrations up to now, without the tables of the logins
+-- uuids are stored as 16 byte blobs, dates as ISO 8601 text and tags as a json array
+CREATE TABLE ledger (
+  id            BLOB NOT NULL PRIMARY KEY,
+  name          TEXT NOT NULL,
+  base_currency TEXT NOT NULL
+);
+
+CREATE TABLE "user" (
+  id            BLOB NOT NULL PRIMARY KEY,
+  role          TEXT NOT NULL CHECK (role IN ('admin', 'member', 'read_only')),
+  creation_date TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE identity (
+  provider      TEXT NOT NULL,
+  subject       TEXT NOT NULL,
+  user_id       BLOB NOT NULL,
+  username      TEXT,
+  avatar        TEXT,
+  creation_date TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  PRIMARY KEY (provider, subject),
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE account (
+  id        BLOB NOT NULL PRIMARY KEY,
+  ledger_id BLOB NOT NULL,
+  name      TEXT NOT NULL,
+  user_id   BLOB,
+
+  CONSTRAINT account_of_user_in_ledger
+    UNIQUE (ledger_id, user_id),
+
+  CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE SET NULL
+);
+
+CREATE TABLE payment (
+  id                BLOB    NOT NULL PRIMARY KEY,
+  ledger_id         BLOB    NOT NULL,
+  payer_account_id  BLOB    NOT NULL,
+  lender_account_id BLOB    NOT NULL,
+  amount            INTEGER NOT NULL,
+  currency          TEXT    NOT NULL,
+  event_date        TEXT    NOT NULL,
+  description       TEXT,
+
+  CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT payer_account_id
+    FOREIGN KEY(payer_account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT lender_account_id
+    FOREIGN KEY(lender_account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE cost (
+  id          BLOB    NOT NULL PRIMARY KEY,
+  ledger_id   BLOB    NOT NULL,
+  account_id  BLOB    NOT NULL,
+  amount      INTEGER NOT NULL,
+  currency    TEXT    NOT NULL,
+  event_date  TEXT    NOT NULL,
+  description TEXT,
+  tags        TEXT,
+
+  CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT account_id
+    FOREIGN KEY(account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE debt (
+  id                BLOB    NOT NULL PRIMARY KEY,
+  debtor_account_id BLOB    NOT NULL,
+  cost_id           BLOB    NOT NULL,
+  amount            INTEGER NOT NULL,
+
+  CONSTRAINT debtor_account_id
+    FOREIGN KEY(debtor_account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT cost_id
+    FOREIGN KEY(cost_id)
+      REFERENCES cost(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE exchange_rate (
+  id            BLOB NOT NULL PRIMARY KEY,
+  -- rate is valid from this date until the next rate of the same currencies
+  date          TEXT NOT NULL,
+  from_currency TEXT NOT NULL,
+  to_currency   TEXT NOT NULL,
+  -- 1 unit of from_currency equals rate units of to_currency
+  rate          REAL NOT NULL,
+
+  CONSTRAINT exchange_rate_per_day
+    UNIQUE (date, from_currency, to_currency)
+);
@@ -0,0 +1,4 @@
+-- incremented by every write of the balances of the ledger, writes pass the version they were
+-- calculated from and are rejected if another write came first
+ALTER TABLE ledger
+  ADD COLUMN balance_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+DROP TABLE local_credential;
+DROP TABLE personal_access_token;
+DROP TABLE login_code;
+DROP TABLE oauth_state;
+DROP TABLE session;
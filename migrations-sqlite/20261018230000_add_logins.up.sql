@@ -0,0 +1,83 @@
+-- logins of the users, like the tables of the postgres migrations
+-- tokens and codes are only stored as their sha256 hash, timestamps as text like CURRENT_TIMESTAMP
+CREATE TABLE session (
+  id                          BLOB NOT NULL PRIMARY KEY,
+  user_id                     BLOB NOT NULL,
+  username                    TEXT NOT NULL,
+  user_agent                  TEXT,
+  ip                          TEXT,
+  token_hash                  TEXT NOT NULL UNIQUE,
+  expires_at                  TEXT NOT NULL,
+  refresh_token_hash          TEXT NOT NULL UNIQUE,
+  -- the last refresh token, using it again means it was stolen
+  previous_refresh_token_hash TEXT UNIQUE,
+  refresh_expires_at          TEXT NOT NULL,
+  creation_date               TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE oauth_state (
+  csrf_token    TEXT NOT NULL PRIMARY KEY,
+  provider      TEXT NOT NULL,
+  origin_uri    TEXT NOT NULL,
+  pkce_verifier TEXT NOT NULL,
+  nonce         TEXT NOT NULL,
+  link_user_id  BLOB,
+  expires_at    TEXT NOT NULL,
+
+  CONSTRAINT link_user_id
+    FOREIGN KEY(link_user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE login_code (
+  code_hash  TEXT NOT NULL PRIMARY KEY,
+  user_id    BLOB NOT NULL,
+  username   TEXT NOT NULL,
+  user_agent TEXT,
+  ip         TEXT,
+  expires_at TEXT NOT NULL,
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE personal_access_token (
+  id            BLOB NOT NULL PRIMARY KEY,
+  user_id       BLOB NOT NULL,
+  name          TEXT NOT NULL,
+  token_hash    TEXT NOT NULL UNIQUE,
+  -- json array of the scopes
+  scopes        TEXT NOT NULL,
+  -- never expires if not set
+  expires_at    TEXT,
+  last_used_at  TEXT,
+  creation_date TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE local_credential (
+  username                TEXT    NOT NULL PRIMARY KEY,
+  user_id                 BLOB    NOT NULL,
+  password_hash           TEXT,
+  registration_code_hash  TEXT    UNIQUE,
+  registration_expires_at TEXT,
+  failed_attempts         INTEGER NOT NULL DEFAULT 0,
+  locked_until            TEXT,
+
+  CONSTRAINT user_id
+    FOREIGN KEY(user_id)
+      REFERENCES "user"(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,7 @@
+DROP TABLE exchange_rate;
+
+ALTER TABLE payment
+  DROP COLUMN currency;
+
+ALTER TABLE cost
+  DROP COLUMN currency;
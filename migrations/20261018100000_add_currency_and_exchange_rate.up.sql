@@ -0,0 +1,25 @@
+-- all existing entries were stored in euro
+ALTER TABLE cost
+  ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+
+ALTER TABLE cost
+  ALTER COLUMN currency DROP DEFAULT;
+
+ALTER TABLE payment
+  ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'EUR';
+
+ALTER TABLE payment
+  ALTER COLUMN currency DROP DEFAULT;
+
+CREATE TABLE exchange_rate (
+  id            UUID             NOT NULL PRIMARY KEY,
+  -- rate is valid from this date until the next rate of the same currencies
+  date          DATE             NOT NULL,
+  from_currency VARCHAR(3)       NOT NULL,
+  to_currency   VARCHAR(3)       NOT NULL,
+  -- 1 unit of from_currency equals rate units of to_currency
+  rate          DOUBLE PRECISION NOT NULL,
+
+  CONSTRAINT exchange_rate_per_day
+    UNIQUE (date, from_currency, to_currency)
+);
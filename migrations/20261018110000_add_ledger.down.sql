@@ -0,0 +1,10 @@
+ALTER TABLE payment
+  DROP COLUMN ledger_id;
+
+ALTER TABLE cost
+  DROP COLUMN ledger_id;
+
+ALTER TABLE account
+  DROP COLUMN ledger_id;
+
+DROP TABLE ledger;
@@ -0,0 +1,51 @@
+CREATE TABLE ledger (
+  id            UUID       NOT NULL PRIMARY KEY,
+  name          VARCHAR    NOT NULL,
+  base_currency VARCHAR(3) NOT NULL
+);
+
+-- all existing entries are moved into one ledger
+INSERT
+  INTO ledger
+    (id, name, base_currency)
+  SELECT gen_random_uuid(), 'default', 'EUR'
+    WHERE EXISTS (SELECT FROM account);
+
+ALTER TABLE account
+  ADD COLUMN ledger_id UUID;
+
+ALTER TABLE cost
+  ADD COLUMN ledger_id UUID;
+
+ALTER TABLE payment
+  ADD COLUMN ledger_id UUID;
+
+UPDATE account
+  SET ledger_id = (SELECT id FROM ledger);
+
+UPDATE cost
+  SET ledger_id = (SELECT id FROM ledger);
+
+UPDATE payment
+  SET ledger_id = (SELECT id FROM ledger);
+
+ALTER TABLE account
+  ALTER COLUMN ledger_id SET NOT NULL,
+  ADD CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE;
+
+ALTER TABLE cost
+  ALTER COLUMN ledger_id SET NOT NULL,
+  ADD CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE;
+
+ALTER TABLE payment
+  ALTER COLUMN ledger_id SET NOT NULL,
+  ADD CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+DROP TABLE "user";
+DROP TYPE user_role;
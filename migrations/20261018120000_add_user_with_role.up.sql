@@ -0,0 +1,15 @@
+CREATE TYPE user_role AS ENUM ('admin', 'member', 'read_only');
+
+CREATE TABLE "user" (
+  id            UUID      NOT NULL PRIMARY KEY,
+  -- id of the discord user, only invited users are allowed to login
+  discord_id    VARCHAR   NOT NULL UNIQUE,
+  role          user_role NOT NULL DEFAULT 'member',
+  creation_date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- users that were allowed before roles existed
+INSERT INTO "user" (id, discord_id, role)
+  VALUES
+    (gen_random_uuid(), '138371651942219777', 'admin'),
+    (gen_random_uuid(), '207268277305606144', 'admin');
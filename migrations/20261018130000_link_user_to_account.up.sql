@@ -0,0 +1,9 @@
+ALTER TABLE account
+  ADD COLUMN user_id UUID;
+
+ALTER TABLE account
+  ADD CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE SET NULL;
+
+-- every user has at most one account per ledger
+ALTER TABLE account
+  ADD CONSTRAINT account_of_user_in_ledger UNIQUE (ledger_id, user_id);
@@ -0,0 +1,5 @@
+ALTER TABLE auth_user
+  DROP COLUMN expires_at;
+
+ALTER TABLE auth_user
+  RENAME COLUMN token_hash TO access_token;
@@ -0,0 +1,8 @@
+-- old tokens were guessable, so every user has to login again
+DELETE FROM auth_user;
+
+ALTER TABLE auth_user
+  RENAME COLUMN access_token TO token_hash;
+
+ALTER TABLE auth_user
+  ADD COLUMN expires_at TIMESTAMP NOT NULL;
@@ -0,0 +1,19 @@
+DELETE FROM session;
+
+ALTER TABLE session
+  DROP CONSTRAINT token_hash,
+  DROP COLUMN ip,
+  DROP COLUMN user_agent,
+  DROP COLUMN refresh_expires_at,
+  DROP COLUMN previous_refresh_token_hash,
+  DROP COLUMN refresh_token_hash,
+  DROP COLUMN id;
+
+ALTER TABLE session
+  ADD PRIMARY KEY (token_hash);
+
+ALTER TABLE session
+  RENAME COLUMN discord_id TO id;
+
+ALTER TABLE session
+  RENAME TO auth_user;
@@ -0,0 +1,21 @@
+-- sessions without refresh token can not be continued, so every user has to login again
+DELETE FROM auth_user;
+
+ALTER TABLE auth_user
+  RENAME TO session;
+
+ALTER TABLE session
+  RENAME COLUMN id TO discord_id;
+
+ALTER TABLE session
+  DROP CONSTRAINT auth_user_pkey;
+
+ALTER TABLE session
+  ADD COLUMN id                          UUID      NOT NULL PRIMARY KEY,
+  ADD COLUMN refresh_token_hash          VARCHAR   NOT NULL UNIQUE,
+  -- the last refresh token, using it again means it was stolen
+  ADD COLUMN previous_refresh_token_hash VARCHAR   UNIQUE,
+  ADD COLUMN refresh_expires_at          TIMESTAMP NOT NULL,
+  ADD COLUMN user_agent                  VARCHAR,
+  ADD COLUMN ip                          VARCHAR,
+  ADD CONSTRAINT token_hash UNIQUE (token_hash);
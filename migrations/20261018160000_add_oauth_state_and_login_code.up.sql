@@ -0,0 +1,19 @@
+-- started logins, the random csrf token is sent as oauth state to the provider
+CREATE TABLE oauth_state (
+  csrf_token    VARCHAR   NOT NULL PRIMARY KEY,
+  origin_uri    VARCHAR   NOT NULL,
+  pkce_verifier VARCHAR   NOT NULL,
+  expires_at    TIMESTAMP NOT NULL
+);
+
+-- finished logins, which the caller exchanges once for a session
+CREATE TABLE login_code (
+  code_hash     VARCHAR   NOT NULL PRIMARY KEY,
+  discord_id    VARCHAR   NOT NULL,
+  avatar        VARCHAR,
+  username      VARCHAR   NOT NULL,
+  discriminator VARCHAR   NOT NULL,
+  user_agent    VARCHAR,
+  ip            VARCHAR,
+  expires_at    TIMESTAMP NOT NULL
+);
@@ -0,0 +1,39 @@
+DELETE FROM oauth_state;
+
+ALTER TABLE oauth_state
+  DROP COLUMN link_user_id,
+  DROP COLUMN nonce,
+  DROP COLUMN provider;
+
+DELETE FROM login_code;
+
+ALTER TABLE login_code
+  DROP COLUMN user_id,
+  ADD COLUMN discord_id    VARCHAR NOT NULL,
+  ADD COLUMN avatar        VARCHAR,
+  ADD COLUMN discriminator VARCHAR NOT NULL;
+
+DELETE FROM session;
+
+ALTER TABLE session
+  DROP COLUMN user_id,
+  ADD COLUMN discord_id    VARCHAR NOT NULL,
+  ADD COLUMN avatar        VARCHAR,
+  ADD COLUMN discriminator VARCHAR NOT NULL;
+
+ALTER TABLE "user"
+  ADD COLUMN discord_id VARCHAR UNIQUE;
+
+UPDATE "user" u
+  SET discord_id = i.subject
+  FROM identity i
+    WHERE i.provider = 'discord'
+      AND i.user_id = u.id;
+
+DELETE FROM "user"
+  WHERE discord_id IS NULL;
+
+ALTER TABLE "user"
+  ALTER COLUMN discord_id SET NOT NULL;
+
+DROP TABLE identity;
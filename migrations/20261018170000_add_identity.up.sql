@@ -0,0 +1,60 @@
+-- login of a user at a provider (e.g. discord), a user can have several
+CREATE TABLE identity (
+  provider      VARCHAR   NOT NULL,
+  -- id of the user at the provider
+  subject       VARCHAR   NOT NULL,
+  user_id       UUID      NOT NULL,
+  -- updated on every login
+  username      VARCHAR,
+  avatar        VARCHAR,
+  creation_date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT identity_at_provider PRIMARY KEY (provider, subject),
+  CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE CASCADE
+);
+
+INSERT
+  INTO identity
+    (provider, subject, user_id)
+  SELECT 'discord', discord_id, id
+    FROM "user";
+
+ALTER TABLE "user"
+  DROP COLUMN discord_id;
+
+-- sessions belong to the user instead of the discord id
+ALTER TABLE session
+  ADD COLUMN user_id UUID;
+
+UPDATE session s
+  SET user_id = i.user_id
+  FROM identity i
+    WHERE i.provider = 'discord'
+      AND i.subject = s.discord_id;
+
+DELETE FROM session
+  WHERE user_id IS NULL;
+
+ALTER TABLE session
+  ALTER COLUMN user_id SET NOT NULL,
+  ADD CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE CASCADE,
+  DROP COLUMN discord_id,
+  DROP COLUMN avatar,
+  DROP COLUMN discriminator;
+
+DELETE FROM login_code;
+
+ALTER TABLE login_code
+  DROP COLUMN discord_id,
+  DROP COLUMN avatar,
+  DROP COLUMN discriminator,
+  ADD COLUMN user_id UUID NOT NULL,
+  ADD CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE CASCADE;
+
+DELETE FROM oauth_state;
+
+ALTER TABLE oauth_state
+  ADD COLUMN provider     VARCHAR NOT NULL,
+  ADD COLUMN nonce        VARCHAR NOT NULL,
+  -- set if the identity is linked to this already logged in user
+  ADD COLUMN link_user_id UUID,
+  ADD CONSTRAINT link_user_id FOREIGN KEY (link_user_id) REFERENCES "user" (id) ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+DROP TABLE personal_access_token;
+DROP TYPE token_scope;
@@ -0,0 +1,22 @@
+CREATE TYPE token_scope AS ENUM (
+  'ledgers:read', 'ledgers:write',
+  'costs:read', 'costs:write',
+  'payments:read', 'payments:write',
+  'exchange_rates:read', 'exchange_rates:write',
+  'snapshot:read'
+);
+
+-- long lived token of a user for scripts, only allowed to call routes of its scopes
+CREATE TABLE personal_access_token (
+  id            UUID          NOT NULL PRIMARY KEY,
+  user_id       UUID          NOT NULL,
+  name          VARCHAR       NOT NULL,
+  token_hash    VARCHAR       NOT NULL,
+  scopes        token_scope[] NOT NULL,
+  -- never expires if not set
+  expires_at    TIMESTAMP,
+  last_used_at  TIMESTAMP,
+  creation_date TIMESTAMP     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT personal_access_token_hash UNIQUE (token_hash),
+  CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE CASCADE
+);
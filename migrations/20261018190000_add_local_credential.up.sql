@@ -0,0 +1,14 @@
+-- password login of users with the `local` identity, invited by an admin
+CREATE TABLE local_credential (
+  -- subject of the `local` identity
+  username                VARCHAR   NOT NULL PRIMARY KEY,
+  user_id                 UUID      NOT NULL,
+  -- argon2id hash, not set until the user registered
+  password_hash           VARCHAR,
+  -- one time code of the invite, which is needed to register
+  registration_code_hash  VARCHAR   UNIQUE,
+  registration_expires_at TIMESTAMP,
+  failed_attempts         INT       NOT NULL DEFAULT 0,
+  locked_until            TIMESTAMP,
+  CONSTRAINT user_id FOREIGN KEY (user_id) REFERENCES "user" (id) ON DELETE CASCADE
+);
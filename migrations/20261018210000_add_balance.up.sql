@@ -0,0 +1,65 @@
+-- debt between two accounts in the base currency of their ledger, updated on every write of costs
+-- and payments, every pair is stored in both directions
+CREATE TABLE balance (
+  ledger_id         UUID   NOT NULL,
+  payer_account_id  UUID   NOT NULL,
+  lender_account_id UUID   NOT NULL,
+  -- positive if the lender owes the payer
+  amount            BIGINT NOT NULL,
+
+  PRIMARY KEY (payer_account_id, lender_account_id),
+
+  CONSTRAINT ledger_id
+    FOREIGN KEY(ledger_id)
+      REFERENCES ledger(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT payer_account_id
+    FOREIGN KEY(payer_account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE,
+
+  CONSTRAINT lender_account_id
+    FOREIGN KEY(lender_account_id)
+      REFERENCES account(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX balance_of_ledger ON balance (ledger_id);
+
+-- every amount is converted with the newest rate valid on its date and rounded on its own (half
+-- away from zero), amounts without a rate are left out and reported by `/admin/balances/rebuild`
+INSERT INTO balance (ledger_id, payer_account_id, lender_account_id, amount)
+WITH movement AS (
+  SELECT c.ledger_id, c.account_id AS creditor, d.debtor_account_id AS debtor, d.amount, c.currency, c.event_date
+    FROM debt d
+      JOIN cost c ON c.id = d.cost_id
+  UNION ALL
+  SELECT p.ledger_id, p.payer_account_id, p.lender_account_id, p.amount, p.currency, p.event_date
+    FROM payment p
+), converted AS (
+  SELECT m.ledger_id, m.creditor, m.debtor,
+    CASE WHEN m.currency = l.base_currency THEN m.amount::DOUBLE PRECISION ELSE m.amount * (
+      SELECT CASE WHEN r.from_currency = m.currency THEN r.rate ELSE 1.0::DOUBLE PRECISION / r.rate END
+        FROM exchange_rate r
+        WHERE r.date <= m.event_date
+          AND ((r.from_currency = m.currency AND r.to_currency = l.base_currency)
+            OR (r.from_currency = l.base_currency AND r.to_currency = m.currency))
+        ORDER BY r.date DESC
+        LIMIT 1
+    ) END AS value
+  FROM movement m
+    JOIN ledger l ON l.id = m.ledger_id
+  WHERE m.creditor <> m.debtor
+), rounded AS (
+  SELECT ledger_id, creditor, debtor,
+    (TRUNC(value) + CASE WHEN ABS(value - TRUNC(value)) >= 0.5 THEN SIGN(value) ELSE 0 END)::BIGINT AS amount
+  FROM converted
+)
+SELECT ledger_id, payer, lender, COALESCE(SUM(amount), 0)
+FROM (
+  SELECT ledger_id, creditor AS payer, debtor AS lender, amount FROM rounded
+  UNION ALL
+  SELECT ledger_id, debtor, creditor, -amount FROM rounded
+) side
+GROUP BY ledger_id, payer, lender;
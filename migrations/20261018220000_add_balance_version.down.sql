@@ -0,0 +1,2 @@
+ALTER TABLE ledger
+  DROP COLUMN balance_version;
@@ -3,9 +3,19 @@
 # get bearer token via auth endpoint
 bearer="BEARER_TOKEN"
 
+# create ledger for all accounts
+ledger=$(
+  curl "localhost:3000/ledger" \
+    -X POST \
+    -H "Content-Type: application/json" \
+    -H "Authorization: Bearer $bearer" \
+    -d '{"name":"seed"}' |
+    jq -r ".id"
+)
+
 # create account for payer
 accountPay=$(
-  curl "localhost:3000/account" \
+  curl "localhost:3000/ledger/$ledger/account" \
     -X POST \
     -H "Content-Type: application/json" \
     -H "Authorization: Bearer $bearer" \
@@ -15,7 +25,7 @@ accountPay=$(
 
 # create account for debtor
 accountDebt=$(
-  curl "localhost:3000/account" \
+  curl "localhost:3000/ledger/$ledger/account" \
     -X POST \
     -H "Content-Type: application/json" \
     -H "Authorization: Bearer $bearer" \
@@ -25,31 +35,31 @@ accountDebt=$(
 
 
 # create costs from payer
-curl "localhost:3000/account/$accountPay/cost" \
+curl "localhost:3000/ledger/$ledger/account/$accountPay/cost" \
   -X POST \
   -H "Content-Type: application/json" \
   -H "Authorization: Bearer $bearer" \
   -d \
     "{
       \"debtors\": [
-        { \"account_id\": \"$accountPay\", \"amount\": 4.01 },
-        { \"account_id\": \"$accountDebt\", \"amount\": 0.11 }
+        { \"account_id\": \"$accountPay\", \"amount\": \"4.01\" },
+        { \"account_id\": \"$accountDebt\", \"amount\": \"0.11\" }
       ],
-      \"amount\": 4.12,
+      \"amount\": \"4.12\",
       \"description\":\"i payed\",
       \"tags\": [\"f\", \"b\", \"f\"],
       \"event_date\":\"2222-01-01\"
     }" > /dev/null
 
 # create payment from debtor
-curl "localhost:3000/account/$accountDebt/payment" \
+curl "localhost:3000/ledger/$ledger/account/$accountDebt/payment" \
   -X POST \
   -H "Content-Type: application/json" \
   -H "Authorization: Bearer $bearer" \
   -d \
     "{
       \"lender_account_id\": \"$accountPay\",
-      \"amount\": 1,
+      \"amount\": \"1.00\",
       \"description\":\"i payed back\",
       \"event_date\":\"2222-01-01\"
     }" > /dev/null
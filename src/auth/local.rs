@@ -0,0 +1,108 @@
+use axum::{
+    extract::State,
+    routing::{post, put},
+    Json, Router,
+};
+
+use crate::{
+    error::AppError,
+    model::dto::auth::{
+        AuthUser, ChangePasswordDto, ClientInfo, LocalLoginDto, LocalRegisterDto, TokenDto,
+    },
+    repository::Repositories,
+    service::{self, credential},
+};
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route("/auth/local/register", post(register))
+        .route("/auth/local/login", post(login))
+        .route("/auth/local/password", put(change_password))
+}
+
+fn ensure_enabled() -> Result<(), AppError> {
+    if credential::is_enabled() {
+        Ok(())
+    } else {
+        Err(AppError::NotFound)
+    }
+}
+
+/// Set the password of a user invited via `/admin/user/local` and login
+#[utoipa::path(
+    post,
+    path = "/auth/local/register",
+    request_body = LocalRegisterDto,
+    responses((status = 200, body = TokenDto), (status = 403), (status = 404))
+)]
+async fn register(
+    State(repos): State<Repositories>,
+    client_info: ClientInfo,
+    Json(register): Json<LocalRegisterDto>,
+) -> Result<Json<TokenDto>, AppError> {
+    ensure_enabled()?;
+
+    let user_id = credential::register(
+        &repos,
+        &register.username,
+        &register.registration_code,
+        register.password,
+    )
+    .await?;
+
+    let username = credential::normalize(&register.username);
+    let tokens = service::session::create(&repos, user_id, &username, client_info).await?;
+
+    Ok(Json(tokens))
+}
+
+/// Login with username and password, which returns the same tokens as the other logins
+///
+/// After 5 failed attempts in a row the login is locked for 15 minutes
+#[utoipa::path(
+    post,
+    path = "/auth/local/login",
+    request_body = LocalLoginDto,
+    responses((status = 200, body = TokenDto), (status = 403), (status = 404))
+)]
+async fn login(
+    State(repos): State<Repositories>,
+    client_info: ClientInfo,
+    Json(login): Json<LocalLoginDto>,
+) -> Result<Json<TokenDto>, AppError> {
+    ensure_enabled()?;
+
+    let user_id = credential::login(&repos, &login.username, login.password).await?;
+
+    let username = credential::normalize(&login.username);
+    let tokens = service::session::create(&repos, user_id, &username, client_info).await?;
+
+    Ok(Json(tokens))
+}
+
+/// Change the password of the logged in user, all other sessions are ended
+#[utoipa::path(
+    put,
+    path = "/auth/local/password",
+    request_body = ChangePasswordDto,
+    responses((status = 200), (status = 400), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn change_password(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+    Json(change): Json<ChangePasswordDto>,
+) -> Result<(), AppError> {
+    ensure_enabled()?;
+
+    let session_id = user.session_id.ok_or(AppError::Forbidden)?;
+
+    credential::change_password(
+        &repos,
+        user.id,
+        session_id,
+        change.current_password,
+        change.new_password,
+    )
+    .await
+}
@@ -0,0 +1,308 @@
+pub mod local;
+pub mod provider;
+
+use axum::{
+    extract::{Path, Query, State},
+    response::Redirect,
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use oauth2::{url::Url, AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    model::dto::auth::{
+        AuthRequestParams, AuthRequestQuery, AuthUser, AuthorizeUrlDto, ClientInfo, LoginCodeDto,
+        RefreshTokenDto, SessionDto, TokenDto,
+    },
+    repository::{OAuthState, Repositories},
+    service,
+};
+
+use self::provider::Providers;
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route("/auth/authorized", get(login_authorized))
+        .route("/auth/logout", get(logout))
+        .route("/auth/token", post(exchange_login_code))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/sessions", get(get_sessions))
+        .route("/auth/sessions/:session_id", delete(delete_session))
+        .route("/auth/:provider", get(provider_auth))
+        .route("/auth/:provider/link", post(link_provider))
+        .merge(local::app())
+}
+
+/// Origins (e.g. `https://example.com`) the login is allowed to redirect to, via `ALLOWED_ORIGINS`
+fn allowed_origins() -> Vec<String> {
+    std::env::var("ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn is_allowed_origin(uri: &str, allowed_origins: &[String]) -> bool {
+    let origin_of = |uri: &str| Url::parse(uri).ok().map(|url| url.origin());
+
+    origin_of(uri).is_some_and(|origin| {
+        origin.is_tuple()
+            && allowed_origins
+                .iter()
+                .any(|allowed| origin_of(allowed).as_ref() == Some(&origin))
+    })
+}
+
+fn ensure_allowed_origin(origin_uri: &str) -> Result<(), AppError> {
+    if is_allowed_origin(origin_uri, &allowed_origins()) {
+        Ok(())
+    } else {
+        Err(AppError::Controller(format!(
+            "origin of {origin_uri} is not allowed to login"
+        )))
+    }
+}
+
+/// Store the state of the started login and return the page of the provider
+async fn start_login(
+    repos: &Repositories,
+    providers: &Providers,
+    provider: String,
+    origin_uri: String,
+    link_user_id: Option<Uuid>,
+) -> Result<Url, AppError> {
+    let client = providers.get(&provider)?;
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let csrf_token = CsrfToken::new_random();
+    let nonce = service::session::generate();
+
+    let authorize_url = client.authorize_url(csrf_token.clone(), pkce_challenge, nonce.clone());
+
+    // the location of the caller (e.g. frontend) is kept for the later redirect
+    service::oauth::store_state(
+        repos,
+        &OAuthState {
+            csrf_token: csrf_token.secret().clone(),
+            provider,
+            origin_uri,
+            pkce_verifier: pkce_verifier.secret().clone(),
+            nonce,
+            link_user_id,
+        },
+    )
+    .await?;
+
+    Ok(authorize_url)
+}
+
+/// This call will redirect the user to the login page of the provider (e.g. `discord`, `github`
+/// or `oidc`), the passed `origin_uri` will tell the backend after the login where to redirect again
+///
+/// A one time code can be found appended at the url via the `login_code` query param,
+/// which has to be exchanged for the tokens via `/auth/token` within a minute
+#[utoipa::path(
+    get,
+    path = "/auth/{provider}",
+    params(("provider" = String, Path,), AuthRequestParams),
+    responses((status = 303), (status = 404))
+)]
+async fn provider_auth(
+    Extension(providers): Extension<Providers>,
+    State(repos): State<Repositories>,
+    Path(provider): Path<String>,
+    Query(query): Query<AuthRequestParams>,
+) -> Result<Redirect, AppError> {
+    ensure_allowed_origin(&query.origin_uri)?;
+
+    let authorize_url = start_login(&repos, &providers, provider, query.origin_uri, None).await?;
+
+    Ok(Redirect::to(authorize_url.as_ref()))
+}
+
+/// Link another provider to the logged in user, so that it can be used to login as well
+///
+/// Open the returned url, afterwards the provider redirects like a login to the `origin_uri`
+#[utoipa::path(
+    post,
+    path = "/auth/{provider}/link",
+    params(("provider" = String, Path,), AuthRequestParams),
+    responses((status = 200, body = AuthorizeUrlDto), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn link_provider(
+    user: AuthUser,
+    Extension(providers): Extension<Providers>,
+    State(repos): State<Repositories>,
+    Path(provider): Path<String>,
+    Query(query): Query<AuthRequestParams>,
+) -> Result<Json<AuthorizeUrlDto>, AppError> {
+    ensure_allowed_origin(&query.origin_uri)?;
+
+    let authorize_url = start_login(
+        &repos,
+        &providers,
+        provider,
+        query.origin_uri,
+        Some(user.id),
+    )
+    .await?;
+
+    Ok(Json(AuthorizeUrlDto {
+        authorize_url: authorize_url.to_string(),
+    }))
+}
+
+/// Exchange the one time code of the login redirect for the access and refresh token
+#[utoipa::path(
+    post,
+    path = "/auth/token",
+    request_body = LoginCodeDto,
+    responses((status = 200, body = TokenDto), (status = 403))
+)]
+async fn exchange_login_code(
+    State(repos): State<Repositories>,
+    Json(login): Json<LoginCodeDto>,
+) -> Result<Json<TokenDto>, AppError> {
+    let tokens = service::oauth::exchange_login_code(&repos, &login.login_code).await?;
+
+    Ok(Json(tokens))
+}
+
+/// End the session of the given bearer token, other devices stay logged in
+#[utoipa::path(
+    get,
+    path = "/auth/logout",
+    security(("bearer_token" = []))
+)]
+async fn logout(user: AuthUser, State(repos): State<Repositories>) -> Result<(), AppError> {
+    let session_id = user.session_id.ok_or(AppError::Forbidden)?;
+
+    service::session::delete(&repos, user.id, session_id).await?;
+
+    Ok(())
+}
+
+/// Exchange the refresh token for a new access and refresh token
+///
+/// Every refresh token can only be used once, using it again ends the session
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    request_body = RefreshTokenDto,
+    responses((status = 200, body = TokenDto), (status = 403))
+)]
+async fn refresh(
+    State(repos): State<Repositories>,
+    Json(refresh): Json<RefreshTokenDto>,
+) -> Result<Json<TokenDto>, AppError> {
+    let tokens = service::session::refresh(&repos, &refresh.refresh_token).await?;
+
+    Ok(Json(tokens))
+}
+
+/// All sessions (devices) of the user
+#[utoipa::path(
+    get,
+    path = "/auth/sessions",
+    responses((status = 200, body = [SessionDto])),
+    security(("bearer_token" = []))
+)]
+async fn get_sessions(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<SessionDto>>, AppError> {
+    let sessions = service::session::get_all_of_user(&repos, user.id).await?;
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionDto::new(session, user.session_id))
+        .collect();
+
+    Ok(Json(sessions))
+}
+
+/// End a single session of the user, e.g. of a lost device
+#[utoipa::path(
+    delete,
+    path = "/auth/sessions/{session_id}",
+    params(("session_id" = Uuid, Path,)),
+    responses((status = 200), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn delete_session(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(session_id): Path<Uuid>,
+) -> Result<(), AppError> {
+    service::session::delete(&repos, user.id, session_id).await?;
+
+    Ok(())
+}
+
+async fn login_authorized(
+    Query(query): Query<AuthRequestQuery>,
+    Extension(providers): Extension<Providers>,
+    State(repos): State<Repositories>,
+    client_info: ClientInfo,
+) -> Result<Redirect, AppError> {
+    // state is kept between calling third party and return, only known states are accepted
+    let state = service::oauth::take_state(&repos, &query.state).await?;
+
+    let identity = providers
+        .get(&state.provider)?
+        .identity(
+            AuthorizationCode::new(query.code.clone()),
+            PkceCodeVerifier::new(state.pkce_verifier),
+            state.nonce,
+        )
+        .await?;
+
+    let user_id = if let Some(user_id) = state.link_user_id {
+        service::identity::link(&repos, user_id, &state.provider, &identity).await?;
+        user_id
+    } else {
+        // only invited users are allowed to login
+        service::identity::get_user_id(&repos, &state.provider, &identity.subject)
+            .await?
+            .ok_or(AppError::Forbidden)?
+    };
+
+    service::identity::update_profile(&repos, &state.provider, &identity).await?;
+
+    let login_code =
+        service::oauth::create_login_code(&repos, user_id, &identity.username, client_info).await?;
+
+    // redirect to the given url of the calling party (e.g. frontend)
+    let mut redirect_url = Url::parse(&state.origin_uri).map_err(|_| AppError::Forbidden)?;
+    redirect_url
+        .query_pairs_mut()
+        .append_pair("login_code", &login_code);
+
+    Ok(Redirect::temporary(redirect_url.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_redirect_to_allowed_origins() {
+        let allowed = vec![
+            "https://example.com".to_string(),
+            "http://localhost:5173/".to_string(),
+        ];
+
+        assert!(is_allowed_origin("https://example.com/login?x=1", &allowed));
+        assert!(is_allowed_origin("http://localhost:5173", &allowed));
+        assert!(!is_allowed_origin("http://example.com", &allowed));
+        assert!(!is_allowed_origin("https://example.com.evil.com", &allowed));
+        assert!(!is_allowed_origin("http://localhost:5174", &allowed));
+        assert!(!is_allowed_origin("javascript:alert(1)", &allowed));
+        assert!(!is_allowed_origin("/relative", &allowed));
+    }
+}
@@ -0,0 +1,333 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::async_trait;
+use oauth2::{
+    basic::BasicClient, reqwest::async_http_client, url::Url, AuthUrl, AuthorizationCode, ClientId,
+    ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    TokenResponse, TokenUrl,
+};
+use openidconnect::{
+    core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata},
+    IssuerUrl, Nonce,
+};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+/// User as known by the provider
+#[derive(Debug)]
+pub struct Identity {
+    /// id of the user at the provider, which never changes
+    pub subject: String,
+    pub username: String,
+    pub avatar: Option<String>,
+}
+
+/// Something the user can login with (e.g. discord)
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Page of the provider the user is redirected to for the login
+    fn authorize_url(
+        &self,
+        csrf_token: CsrfToken,
+        pkce_challenge: PkceCodeChallenge,
+        nonce: String,
+    ) -> Url;
+
+    /// Exchange the code of the finished login for the user
+    async fn identity(
+        &self,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        nonce: String,
+    ) -> Result<Identity, AppError>;
+}
+
+/// All configured providers by their name, which is used in the routes (e.g. `/auth/github`)
+#[derive(Clone, Default)]
+pub struct Providers(HashMap<&'static str, Arc<dyn Provider>>);
+
+impl Providers {
+    /// Every provider is only enabled if its client id is configured
+    pub async fn from_env() -> Self {
+        let mut providers = Self::default();
+
+        if let Some(provider) = discord() {
+            providers.0.insert("discord", Arc::new(provider));
+        }
+
+        if let Some(provider) = github() {
+            providers.0.insert("github", Arc::new(provider));
+        }
+
+        match OidcProvider::from_env().await {
+            Ok(Some(provider)) => {
+                providers.0.insert("oidc", Arc::new(provider));
+            }
+            Ok(None) => {}
+            Err(err) => tracing::error!("oidc provider is disabled: {err}"),
+        }
+
+        for name in providers.0.keys() {
+            tracing::info!("login via {name} is enabled");
+        }
+
+        providers
+    }
+
+    pub fn get(&self, name: &str) -> Result<Arc<dyn Provider>, AppError> {
+        self.0.get(name).cloned().ok_or(AppError::NotFound)
+    }
+}
+
+/// Configuration of the provider, e.g. `GITHUB_CLIENT_ID`
+fn env(provider: &str, name: &str) -> Option<String> {
+    std::env::var(format!("{provider}_{name}")).ok()
+}
+
+/// All providers redirect back to the same endpoint, the stored state tells which provider it was
+#[allow(clippy::expect_used)]
+fn redirect_url() -> RedirectUrl {
+    let redirect_url = std::env::var("REDIRECT_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:3000/auth/authorized".to_string());
+
+    RedirectUrl::new(redirect_url).expect("redirect url can be created")
+}
+
+/// Plain OAuth2 provider, where the user is fetched from a userinfo endpoint
+pub struct OAuthProvider {
+    client: BasicClient,
+    scopes: Vec<&'static str>,
+    extra_params: Vec<(&'static str, &'static str)>,
+    userinfo_url: String,
+    parse_identity: fn(Value) -> Option<Identity>,
+}
+
+/// Configured via `DISCORD_CLIENT_ID`, `DISCORD_CLIENT_SECRET` (or `CLIENT_ID`, `CLIENT_SECRET`)
+///
+/// `AUTH_URL`, `TOKEN_URL` and `USERINFO_URL` can point to another server, e.g. a fake one in tests
+#[allow(clippy::expect_used)]
+fn discord() -> Option<OAuthProvider> {
+    // without prefix for setups from before other providers existed
+    let env = |name: &str| env("DISCORD", name).or_else(|| std::env::var(name).ok());
+
+    let client_id = env("CLIENT_ID")?;
+    let client_secret = env("CLIENT_SECRET").expect(".env has discord CLIENT_SECRET");
+
+    let auth_url = env("AUTH_URL").unwrap_or_else(|| {
+        "https://discord.com/api/oauth2/authorize?response_type=code".to_string()
+    });
+
+    let token_url =
+        env("TOKEN_URL").unwrap_or_else(|| "https://discord.com/api/oauth2/token".to_string());
+
+    Some(OAuthProvider {
+        client: BasicClient::new(
+            ClientId::new(client_id),
+            Some(ClientSecret::new(client_secret)),
+            AuthUrl::new(auth_url).expect("auth url can be created"),
+            Some(TokenUrl::new(token_url).expect("token url can be created")),
+        )
+        .set_redirect_uri(redirect_url()),
+        scopes: vec!["identify"],
+        // do not prompt user if they already authed to app in the past
+        extra_params: vec![("prompt", "none")],
+        // https://discord.com/developers/docs/resources/user#get-current-user
+        userinfo_url: env("USERINFO_URL")
+            .unwrap_or_else(|| "https://discord.com/api/users/@me".to_string()),
+        parse_identity: |user| {
+            Some(Identity {
+                subject: user["id"].as_str()?.to_string(),
+                username: user["username"].as_str()?.to_string(),
+                avatar: user["avatar"].as_str().map(ToString::to_string),
+            })
+        },
+    })
+}
+
+/// Configured via `GITHUB_CLIENT_ID` and `GITHUB_CLIENT_SECRET`
+#[allow(clippy::expect_used)]
+fn github() -> Option<OAuthProvider> {
+    let client_id = env("GITHUB", "CLIENT_ID")?;
+    let client_secret = env("GITHUB", "CLIENT_SECRET").expect(".env has GITHUB_CLIENT_SECRET");
+
+    let auth_url = env("GITHUB", "AUTH_URL")
+        .unwrap_or_else(|| "https://github.com/login/oauth/authorize".to_string());
+
+    let token_url = env("GITHUB", "TOKEN_URL")
+        .unwrap_or_else(|| "https://github.com/login/oauth/access_token".to_string());
+
+    Some(OAuthProvider {
+        client: BasicClient::new(
+            ClientId::new(client_id),
+            Some(ClientSecret::new(client_secret)),
+            AuthUrl::new(auth_url).expect("auth url can be created"),
+            Some(TokenUrl::new(token_url).expect("token url can be created")),
+        )
+        .set_redirect_uri(redirect_url()),
+        scopes: vec!["read:user"],
+        extra_params: vec![],
+        // https://docs.github.com/en/rest/users/users#get-the-authenticated-user
+        userinfo_url: env("GITHUB", "USERINFO_URL")
+            .unwrap_or_else(|| "https://api.github.com/user".to_string()),
+        parse_identity: |user| {
+            Some(Identity {
+                subject: user["id"].as_u64()?.to_string(),
+                username: user["login"].as_str()?.to_string(),
+                avatar: user["avatar_url"].as_str().map(ToString::to_string),
+            })
+        },
+    })
+}
+
+#[async_trait]
+impl Provider for OAuthProvider {
+    fn authorize_url(
+        &self,
+        csrf_token: CsrfToken,
+        pkce_challenge: PkceCodeChallenge,
+        _nonce: String,
+    ) -> Url {
+        let mut request = self
+            .client
+            .authorize_url(|| csrf_token)
+            .set_pkce_challenge(pkce_challenge);
+
+        for scope in &self.scopes {
+            request = request.add_scope(Scope::new((*scope).to_string()));
+        }
+
+        for (name, value) in &self.extra_params {
+            request = request.add_extra_param(*name, *value);
+        }
+
+        request.url().0
+    }
+
+    async fn identity(
+        &self,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        _nonce: String,
+    ) -> Result<Identity, AppError> {
+        let token = self
+            .client
+            .exchange_code(code)
+            .set_pkce_verifier(pkce_verifier)
+            .request_async(async_http_client)
+            .await
+            .map_err(|_| AppError::Forbidden)?;
+
+        let user = reqwest::Client::new()
+            .get(&self.userinfo_url)
+            .bearer_auth(token.access_token().secret())
+            // some providers (e.g. github) reject requests without one
+            .header(reqwest::header::USER_AGENT, "money-tracker")
+            .send()
+            .await
+            .map_err(|_| AppError::Forbidden)?
+            .json::<Value>()
+            .await
+            .map_err(|_| AppError::Forbidden)?;
+
+        (self.parse_identity)(user).ok_or_else(|| {
+            AppError::InternalServer("userinfo of provider has an unknown format".to_string())
+        })
+    }
+}
+
+/// Any OpenID Connect provider, configured via `OIDC_ISSUER_URL`, `OIDC_CLIENT_ID`
+/// and `OIDC_CLIENT_SECRET`
+///
+/// Its endpoints are discovered from the issuer and the user is taken from the validated ID token
+pub struct OidcProvider {
+    client: CoreClient,
+}
+
+impl OidcProvider {
+    async fn from_env() -> Result<Option<Self>, String> {
+        let Some(client_id) = env("OIDC", "CLIENT_ID") else {
+            return Ok(None);
+        };
+
+        let issuer_url = env("OIDC", "ISSUER_URL").ok_or("OIDC_ISSUER_URL is missing")?;
+        let client_secret = env("OIDC", "CLIENT_SECRET").ok_or("OIDC_CLIENT_SECRET is missing")?;
+
+        let issuer_url = IssuerUrl::new(issuer_url).map_err(|err| err.to_string())?;
+        let metadata = CoreProviderMetadata::discover_async(
+            issuer_url,
+            openidconnect::reqwest::async_http_client,
+        )
+        .await
+        .map_err(|err| format!("discovery failed: {err}"))?;
+
+        let client = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(client_id),
+            Some(ClientSecret::new(client_secret)),
+        )
+        .set_redirect_uri(redirect_url());
+
+        Ok(Some(Self { client }))
+    }
+}
+
+#[async_trait]
+impl Provider for OidcProvider {
+    fn authorize_url(
+        &self,
+        csrf_token: CsrfToken,
+        pkce_challenge: PkceCodeChallenge,
+        nonce: String,
+    ) -> Url {
+        self.client
+            .authorize_url(
+                CoreAuthenticationFlow::AuthorizationCode,
+                || csrf_token,
+                || Nonce::new(nonce),
+            )
+            .add_scope(Scope::new("profile".to_string()))
+            .set_pkce_challenge(pkce_challenge)
+            .url()
+            .0
+    }
+
+    async fn identity(
+        &self,
+        code: AuthorizationCode,
+        pkce_verifier: PkceCodeVerifier,
+        nonce: String,
+    ) -> Result<Identity, AppError> {
+        let token = self
+            .client
+            .exchange_code(code)
+            .set_pkce_verifier(pkce_verifier)
+            .request_async(openidconnect::reqwest::async_http_client)
+            .await
+            .map_err(|_| AppError::Forbidden)?;
+
+        // signature, issuer, audience, expiry and nonce are validated
+        let claims = token
+            .extra_fields()
+            .id_token()
+            .ok_or(AppError::Forbidden)?
+            .claims(&self.client.id_token_verifier(), &Nonce::new(nonce))
+            .map_err(|_| AppError::Forbidden)?;
+
+        let subject = claims.subject().to_string();
+        let username = claims
+            .preferred_username()
+            .map(|name| name.to_string())
+            .or_else(|| claims.email().map(|email| email.to_string()))
+            .unwrap_or_else(|| subject.clone());
+
+        Ok(Identity {
+            subject,
+            username,
+            avatar: claims
+                .picture()
+                .and_then(|picture| picture.get(None))
+                .map(|url| url.to_string()),
+        })
+    }
+}
@@ -1,74 +1,115 @@
-use axum::{extract::Path, routing, Extension, Json, Router};
-use sqlx::PgPool;
+use axum::{
+    extract::{Path, State},
+    routing, Json, Router,
+};
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::model::dto::auth::AuthUser;
+use crate::model::dto::auth::{AuthUser, MemberUser};
 use crate::model::dto::{request, response};
+use crate::repository::Repositories;
 use crate::service;
 
 #[utoipa::path(
     post,
-    path = "/account",
+    path = "/ledger/{ledger_id}/account",
+    params(("ledger_id" = Uuid, Path,)),
     request_body = CreateAccountDto,
-    responses((status = 200, body = AccountDto)),
+    responses((status = 200, body = AccountDto), (status = 403)),
     security(("bearer_token" = []))
 )]
 async fn create_account(
-    _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
     Json(account): Json<request::CreateAccountDto>,
 ) -> Result<Json<response::AccountDto>, AppError> {
-    let account = service::account::create(&pool, account.name).await?;
+    service::account::ensure_may_link(&user, None, account.user_id)?;
+
+    let account =
+        service::account::create(&repos, ledger_id, account.name, account.user_id).await?;
 
     Ok(Json(account.into()))
 }
 
+#[utoipa::path(
+    put,
+    path = "/ledger/{ledger_id}/account/{account_id}",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
+    request_body = CreateAccountDto,
+    responses((status = 200, body = AccountDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn update_account(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
+    Json(account): Json<request::CreateAccountDto>,
+) -> Result<Json<response::AccountDto>, AppError> {
+    let account = service::account::update(
+        &repos,
+        ledger_id,
+        account_id,
+        account.name,
+        account.user_id,
+        &user,
+    )
+    .await?;
+
+    Ok(Json(account.into()))
+}
+
+/// The account is removed with all of its costs and payments, so only its user or an admin can
+/// delete it
 #[utoipa::path(
     delete,
-    path = "/account/{account_id}",
-    params(("account_id" = Uuid, Path,)),
-    responses((status = 200), (status = 404)),
+    path = "/ledger/{ledger_id}/account/{account_id}",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
+    responses((status = 200), (status = 403), (status = 404)),
     security(("bearer_token" = []))
 )]
 async fn delete_account(
-    _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-    Path(account_id): Path<Uuid>,
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
 ) -> Result<(), AppError> {
-    service::account::delete(&pool, account_id).await?;
+    service::account::ensure_owned_by(&repos, ledger_id, account_id, &user).await?;
+
+    service::account::delete(&repos, ledger_id, account_id).await?;
 
     Ok(())
 }
 
 #[utoipa::path(
     get,
-    path = "/account/{account_id}",
-    params(("account_id" = Uuid, Path,)),
+    path = "/ledger/{ledger_id}/account/{account_id}",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
     responses((status = 200, body = AccountDto)),
     security(("bearer_token" = []))
 )]
 async fn get_account(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-    Path(account_id): Path<Uuid>,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
 ) -> Result<Json<response::AccountDto>, AppError> {
-    let account = service::account::get(&pool, account_id).await?;
+    let account = service::account::get(&repos, ledger_id, account_id).await?;
 
     Ok(Json(account.into()))
 }
 
 #[utoipa::path(
     get,
-    path = "/account",
+    path = "/ledger/{ledger_id}/account",
+    params(("ledger_id" = Uuid, Path,)),
     responses((status = 200, body = [AccountDto])),
     security(("bearer_token" = []))
 )]
 async fn get_all_accounts(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
 ) -> Result<Json<Vec<response::AccountDto>>, AppError> {
-    let accounts = service::account::get_all(&pool).await?;
+    let accounts = service::account::get_all(&repos, ledger_id).await?;
 
     let accounts = accounts.iter().cloned().map(Into::into).collect();
 
@@ -77,30 +118,35 @@ async fn get_all_accounts(
 
 #[utoipa::path(
     get,
-    path = "/account/{account_id}/tags",
-    params(("account_id" = Uuid, Path,)),
+    path = "/ledger/{ledger_id}/account/{account_id}/tags",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
     responses((status = 200, body = [String])),
     security(("bearer_token" = []))
 )]
 async fn get_account_tags(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-    Path(account_id): Path<Uuid>,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
 ) -> Result<Json<Vec<String>>, AppError> {
-    let tags = service::account::get_tags(&pool, account_id).await?;
+    let tags = service::account::get_tags(&repos, ledger_id, account_id).await?;
 
     Ok(Json(tags))
 }
 
-pub fn app() -> Router {
+pub fn app() -> Router<Repositories> {
     Router::new()
         .route(
-            "/account",
+            "/ledger/:ledger_id/account",
             routing::post(create_account).get(get_all_accounts),
         )
         .route(
-            "/account/:account_id",
-            routing::get(get_account).delete(delete_account),
+            "/ledger/:ledger_id/account/:account_id",
+            routing::get(get_account)
+                .put(update_account)
+                .delete(delete_account),
+        )
+        .route(
+            "/ledger/:ledger_id/account/:account_id/tags",
+            routing::get(get_account_tags),
         )
-        .route("/account/:account_id/tags", routing::get(get_account_tags))
 }
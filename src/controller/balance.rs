@@ -0,0 +1,29 @@
+use axum::{extract::State, routing, Json, Router};
+
+use crate::error::AppError;
+use crate::model::dto::auth::AdminUser;
+use crate::model::dto::response;
+use crate::repository::Repositories;
+use crate::service;
+
+/// Recalculate the stored balances of all ledgers from their costs and payments
+///
+/// Returns every account pair whose stored balance was different before
+#[utoipa::path(
+    post,
+    path = "/admin/balances/rebuild",
+    responses((status = 200, body = [BalanceDriftDto]), (status = 400), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn rebuild_balances(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<response::BalanceDriftDto>>, AppError> {
+    let drifts = service::balance::rebuild(&repos).await?;
+
+    Ok(Json(drifts))
+}
+
+pub fn app() -> Router<Repositories> {
+    Router::new().route("/admin/balances/rebuild", routing::post(rebuild_balances))
+}
@@ -1,109 +1,277 @@
 use axum::{
-    extract::{Path, Query},
-    routing, Extension, Json, Router,
+    extract::{Path, Query, State},
+    routing, Json, Router,
 };
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::helper::Conversion;
-use crate::model::dto::auth::AuthUser;
+use crate::model::dto::auth::{AuthUser, MemberUser};
 use crate::model::dto::{request, response};
+use crate::model::money::Money;
+use crate::repository::Repositories;
 use crate::service;
 
+/// Only admins can record costs for accounts that are not linked to themselves
 #[utoipa::path(
     post,
-    path = "/account/{account_id}/cost",
-    params(("account_id" = Uuid, Path,)),
+    path = "/ledger/{ledger_id}/account/{account_id}/cost",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
     request_body = CreateCostDto,
-    responses((status = 200, body = CostDto)),
+    responses((status = 200, body = CostDto), (status = 403)),
     security(("bearer_token" = []))
 )]
 async fn create_cost(
-    _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-    Path(account_id): Path<Uuid>,
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
     Json(cost): Json<request::CreateCostDto>,
 ) -> Result<Json<response::CostDto>, AppError> {
-    for debt in &cost.debtors {
-        if Conversion::to_int(debt.amount) <= 0 {
-            return Err(AppError::Controller(format!(
-                "given amount {} is non existent or negative which it can not be",
-                debt.amount
-            )));
-        }
-    }
+    validate_debtors(&cost.split, &cost.debtors)?;
+    service::account::ensure_owned_by(&repos, ledger_id, account_id, &user).await?;
+
+    let cost = service::cost::create(&repos, ledger_id, account_id, cost).await?;
+
+    Ok(Json(cost))
+}
+
+/// Record the cost as payed by the account of the caller in the ledger
+#[utoipa::path(
+    post,
+    path = "/ledger/{ledger_id}/cost",
+    params(("ledger_id" = Uuid, Path,)),
+    request_body = CreateCostDto,
+    responses((status = 200, body = CostDto)),
+    security(("bearer_token" = []))
+)]
+async fn create_own_cost(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Json(cost): Json<request::CreateCostDto>,
+) -> Result<Json<response::CostDto>, AppError> {
+    validate_debtors(&cost.split, &cost.debtors)?;
+    let account = service::account::get_of_user(&repos, ledger_id, user.id).await?;
+
+    let cost = service::cost::create(&repos, ledger_id, account.id, cost).await?;
 
-    let cost = service::cost::create(
-        &pool,
-        account_id,
-        cost.debtors,
-        cost.amount,
-        cost.description,
-        cost.event_date,
-        cost.tags,
-    )
-    .await?;
-
-    Ok(Json(cost.into()))
+    Ok(Json(cost))
 }
 
+/// Only admins can change costs of accounts that are not linked to themselves
+#[utoipa::path(
+    put,
+    path = "/ledger/{ledger_id}/account/{account_id}/cost/{cost_id}",
+    params(request::UpdateCostParams),
+    request_body = CreateCostDto,
+    responses((status = 200, body = CostDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn replace_cost(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(params): Path<request::UpdateCostParams>,
+    Json(cost): Json<request::CreateCostDto>,
+) -> Result<Json<response::CostDto>, AppError> {
+    validate_debtors(&cost.split, &cost.debtors)?;
+    let current =
+        service::cost::get_of_payer(&repos, params.ledger_id, params.account_id, params.cost_id)
+            .await?;
+    service::account::ensure_owned_by(&repos, params.ledger_id, current.account_id, &user).await?;
+
+    let cost = service::cost::update(&repos, params.ledger_id, params.cost_id, cost).await?;
+
+    Ok(Json(cost))
+}
+
+/// Only the given fields are changed, missing fields keep their current value
+///
+/// If `debtors` are given, they replace the current debtors of the cost completely,
+/// otherwise a given `split` is applied to the current debtors (e.g. `equal` after changing the amount)
+#[utoipa::path(
+    patch,
+    path = "/ledger/{ledger_id}/account/{account_id}/cost/{cost_id}",
+    params(request::UpdateCostParams),
+    request_body = UpdateCostDto,
+    responses((status = 200, body = CostDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn update_cost(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(params): Path<request::UpdateCostParams>,
+    Json(update): Json<request::UpdateCostDto>,
+) -> Result<Json<response::CostDto>, AppError> {
+    let current =
+        service::cost::get_of_payer(&repos, params.ledger_id, params.account_id, params.cost_id)
+            .await?;
+    service::account::ensure_owned_by(&repos, params.ledger_id, current.account_id, &user).await?;
+
+    let debtors = match update.debtors {
+        Some(debtors) => debtors,
+        None => service::cost::get_debts(&repos, params.cost_id)
+            .await?
+            .iter()
+            .map(|debt| request::CreateDebtorDto {
+                account_id: debt.debtor_account_id,
+                amount: Some(Money::from_cents(debt.amount)),
+                percentage: None,
+                shares: None,
+            })
+            .collect(),
+    };
+
+    let split = update.split.unwrap_or_default();
+    validate_debtors(&split, &debtors)?;
+
+    let cost = request::CreateCostDto {
+        debtors,
+        split,
+        amount: update
+            .amount
+            .unwrap_or_else(|| Money::from_cents(current.amount)),
+        currency: update.currency.or(Some(current.currency)),
+        event_date: update.event_date.unwrap_or(current.event_date),
+        description: update.description.unwrap_or(current.description),
+        tags: update.tags.or(current.tags),
+    };
+
+    let cost = service::cost::update(&repos, params.ledger_id, params.cost_id, cost).await?;
+
+    Ok(Json(cost))
+}
+
+/// The cost with all of its debtors
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}/account/{account_id}/cost/{cost_id}",
+    params(request::GetCostParams),
+    responses((status = 200, body = CostDto), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn get_cost(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(params): Path<request::GetCostParams>,
+) -> Result<Json<response::CostDto>, AppError> {
+    let cost =
+        service::cost::get_of_payer(&repos, params.ledger_id, params.account_id, params.cost_id)
+            .await?;
+    let debts = service::cost::get_debts(&repos, cost.id).await?;
+
+    Ok(Json((cost, debts).into()))
+}
+
+/// Only admins can delete costs of accounts that are not linked to themselves
 #[utoipa::path(
     delete,
-    path = "/account/{account_id}/cost/{cost_id}",
+    path = "/ledger/{ledger_id}/account/{account_id}/cost/{cost_id}",
     params(request::DeleteCostParams),
-    responses((status = 200), (status = 404)),
+    responses((status = 200), (status = 403), (status = 404)),
     security(("bearer_token" = []))
 )]
 async fn delete_cost(
-    _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
     Path(params): Path<request::DeleteCostParams>,
 ) -> Result<(), AppError> {
-    service::cost::delete(&pool, params.cost_id).await?;
+    let current =
+        service::cost::get_of_payer(&repos, params.ledger_id, params.account_id, params.cost_id)
+            .await?;
+    service::account::ensure_owned_by(&repos, params.ledger_id, current.account_id, &user).await?;
+
+    service::cost::delete(&repos, params.ledger_id, params.cost_id).await?;
 
     Ok(())
 }
 
+/// One page of the costs that match all given filters, sorted by date or amount
+///
+/// `next_cursor` of the response continues the listing with the same filters and sorting
 #[utoipa::path(
     get,
-    path = "/cost",
-    responses((status = 200, body = [CostDto])),
-    params(request::CostsQuery),
+    path = "/ledger/{ledger_id}/cost",
+    responses((status = 200, body = CostPageDto), (status = 400)),
+    params(("ledger_id" = Uuid, Path,), request::CostsQuery),
     security(("bearer_token" = []))
 )]
 async fn get_all_costs(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
     Query(query): Query<request::CostsQuery>,
-) -> Result<Json<Vec<response::CostDto>>, AppError> {
-    let costs = service::cost::get_all(&pool, query.start_date, query.end_date).await?;
+) -> Result<Json<response::CostPageDto>, AppError> {
+    let page = service::cost::get_all(&repos, ledger_id, query).await?;
 
-    Ok(Json(costs))
+    Ok(Json(page))
 }
 
+/// Debts between all accounts, optionally only for costs and payments in the given range
+///
+/// `as_of` alone reproduces the snapshot at the end of that day (e.g. the end of a month)
 #[utoipa::path(
     get,
-    path = "/snapshot",
+    path = "/ledger/{ledger_id}/snapshot",
     responses((status = 200, body = [CalculatedDebtDto])),
+    params(("ledger_id" = Uuid, Path,), request::SnapshotQuery),
     security(("bearer_token" = []))
 )]
 async fn get_current_snapshot(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Query(query): Query<request::SnapshotQuery>,
 ) -> Result<Json<Vec<response::CalculatedDebtDto>>, AppError> {
-    let debt = service::cost::get_current_snapshot(&pool).await?;
+    let debt =
+        service::cost::get_current_snapshot(&repos, ledger_id, query.from, query.as_of).await?;
 
     Ok(Json(debt))
 }
 
-pub fn app() -> Router {
+fn validate_debtors(
+    split: &request::SplitMode,
+    debtors: &[request::CreateDebtorDto],
+) -> Result<(), AppError> {
+    for debt in debtors {
+        let (name, is_valid) = match split {
+            request::SplitMode::Exact => ("amount", debt.amount.is_some_and(|a| a.cents() > 0)),
+            request::SplitMode::Percentage => (
+                "percentage",
+                debt.percentage.is_some_and(|p| p.basis_points() > 0),
+            ),
+            request::SplitMode::Shares => ("shares", debt.shares.is_some_and(|s| s > 0)),
+            request::SplitMode::Equal => continue,
+        };
+
+        if !is_valid {
+            return Err(AppError::Controller(format!(
+                "given {name} of debtor {} is non existent or negative which it can not be",
+                debt.account_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn app() -> Router<Repositories> {
     Router::new()
-        .route("/account/:account_id/cost", routing::post(create_cost))
         .route(
-            "/account/:account_id/cost/:cost_id",
-            routing::delete(delete_cost),
+            "/ledger/:ledger_id/account/:account_id/cost",
+            routing::post(create_cost),
+        )
+        .route(
+            "/ledger/:ledger_id/account/:account_id/cost/:cost_id",
+            routing::get(get_cost)
+                .put(replace_cost)
+                .patch(update_cost)
+                .delete(delete_cost),
+        )
+        .route(
+            "/ledger/:ledger_id/cost",
+            routing::post(create_own_cost).get(get_all_costs),
+        )
+        .route(
+            "/ledger/:ledger_id/snapshot",
+            routing::get(get_current_snapshot),
         )
-        .route("/cost", routing::get(get_all_costs))
-        .route("/snapshot", routing::get(get_current_snapshot))
 }
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    routing, Json, Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{AdminUser, AuthUser};
+use crate::model::dto::{request, response};
+use crate::repository::Repositories;
+use crate::service;
+
+/// Rates apply to every ledger, so only admins can change them
+#[utoipa::path(
+    post,
+    path = "/exchange-rate",
+    request_body = CreateExchangeRateDto,
+    responses((status = 200, body = ExchangeRateDto), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn create_exchange_rate(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Json(rate): Json<request::CreateExchangeRateDto>,
+) -> Result<Json<response::ExchangeRateDto>, AppError> {
+    let rate = service::exchange_rate::create(
+        &repos,
+        rate.date,
+        rate.from_currency,
+        rate.to_currency,
+        rate.rate,
+    )
+    .await?;
+
+    Ok(Json(rate.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/exchange-rate/{exchange_rate_id}",
+    params(("exchange_rate_id" = Uuid, Path,)),
+    responses((status = 200), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn delete_exchange_rate(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Path(exchange_rate_id): Path<Uuid>,
+) -> Result<(), AppError> {
+    service::exchange_rate::delete(&repos, exchange_rate_id).await?;
+
+    Ok(())
+}
+
+/// All exchange rates, newest first, snapshots are calculated in the base currency
+#[utoipa::path(
+    get,
+    path = "/exchange-rate",
+    responses((status = 200, body = [ExchangeRateDto])),
+    security(("bearer_token" = []))
+)]
+async fn get_all_exchange_rates(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<response::ExchangeRateDto>>, AppError> {
+    let rates = service::exchange_rate::get_all(&repos).await?;
+
+    let rates = rates.iter().cloned().map(Into::into).collect();
+
+    Ok(Json(rates))
+}
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route(
+            "/exchange-rate",
+            routing::post(create_exchange_rate).get(get_all_exchange_rates),
+        )
+        .route(
+            "/exchange-rate/:exchange_rate_id",
+            routing::delete(delete_exchange_rate),
+        )
+}
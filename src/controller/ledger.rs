@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, State},
+    routing, Json, Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{AdminUser, AuthUser, MemberUser};
+use crate::model::dto::{request, response};
+use crate::repository::Repositories;
+use crate::service;
+
+/// The caller gets an account in the new ledger, named like them
+#[utoipa::path(
+    post,
+    path = "/ledger",
+    request_body = CreateLedgerDto,
+    responses((status = 200, body = LedgerDto)),
+    security(("bearer_token" = []))
+)]
+async fn create_ledger(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Json(ledger): Json<request::CreateLedgerDto>,
+) -> Result<Json<response::LedgerDto>, AppError> {
+    let ledger =
+        service::ledger::create_with_account(&repos, &user, ledger.name, ledger.base_currency)
+            .await?;
+
+    Ok(Json(ledger.into()))
+}
+
+/// Changing the base currency converts every following snapshot into the new currency
+///
+/// The ledger is shared by all of its members, so only admins can change it
+#[utoipa::path(
+    put,
+    path = "/ledger/{ledger_id}",
+    params(("ledger_id" = Uuid, Path,)),
+    request_body = CreateLedgerDto,
+    responses((status = 200, body = LedgerDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn update_ledger(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Json(ledger): Json<request::CreateLedgerDto>,
+) -> Result<Json<response::LedgerDto>, AppError> {
+    let ledger =
+        service::ledger::update(&repos, ledger_id, ledger.name, ledger.base_currency).await?;
+
+    Ok(Json(ledger.into()))
+}
+
+/// Deletes the ledger with all of its accounts, costs and payments, only allowed for admins
+#[utoipa::path(
+    delete,
+    path = "/ledger/{ledger_id}",
+    params(("ledger_id" = Uuid, Path,)),
+    responses((status = 200), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn delete_ledger(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+) -> Result<(), AppError> {
+    service::ledger::delete(&repos, ledger_id).await?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}",
+    params(("ledger_id" = Uuid, Path,)),
+    responses((status = 200, body = LedgerDto)),
+    security(("bearer_token" = []))
+)]
+async fn get_ledger(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+) -> Result<Json<response::LedgerDto>, AppError> {
+    let ledger = service::ledger::get(&repos, ledger_id).await?;
+
+    Ok(Json(ledger.into()))
+}
+
+/// Ledgers in which the caller has an account, admins get all of them
+#[utoipa::path(
+    get,
+    path = "/ledger",
+    responses((status = 200, body = [LedgerDto])),
+    security(("bearer_token" = []))
+)]
+async fn get_all_ledgers(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<response::LedgerDto>>, AppError> {
+    let ledgers = service::ledger::get_all_of_user(&repos, &user).await?;
+
+    let ledgers = ledgers.iter().cloned().map(Into::into).collect();
+
+    Ok(Json(ledgers))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}/tags",
+    params(("ledger_id" = Uuid, Path,)),
+    responses((status = 200, body = [String])),
+    security(("bearer_token" = []))
+)]
+async fn get_ledger_tags(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+) -> Result<Json<Vec<String>>, AppError> {
+    let tags = service::ledger::get_tags(&repos, ledger_id).await?;
+
+    Ok(Json(tags))
+}
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route("/ledger", routing::post(create_ledger).get(get_all_ledgers))
+        .route(
+            "/ledger/:ledger_id",
+            routing::get(get_ledger)
+                .put(update_ledger)
+                .delete(delete_ledger),
+        )
+        .route("/ledger/:ledger_id/tags", routing::get(get_ledger_tags))
+}
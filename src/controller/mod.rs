@@ -1,12 +1,64 @@
-use axum::Router;
+use std::collections::HashMap;
+
+use axum::{
+    extract::{FromRequestParts, Path, State},
+    http::Request,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::AuthUser;
+use crate::repository::Repositories;
+use crate::service;
 
 pub mod account;
+pub mod balance;
 pub mod cost;
+pub mod exchange_rate;
+pub mod ledger;
 pub mod payment;
+pub mod personal_access_token;
+pub mod settlement;
+pub mod user;
 
-pub fn app() -> Router {
+pub fn app(repos: Repositories) -> Router {
     Router::new()
         .merge(account::app())
+        .merge(balance::app())
         .merge(cost::app())
+        .merge(exchange_rate::app())
+        .merge(ledger::app())
         .merge(payment::app())
+        .merge(settlement::app())
+        .merge(user::app())
+        .route_layer(middleware::from_fn_with_state(
+            repos.clone(),
+            ensure_ledger_member,
+        ))
+        .with_state(repos)
+}
+
+/// Every route with a `ledger_id` is only accessible for members of that ledger
+///
+/// The user is passed on to the handler, so it is not looked up twice
+async fn ensure_ledger_member<B: Send>(
+    State(repos): State<Repositories>,
+    params: Option<Path<HashMap<String, String>>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, AppError> {
+    let Some(ledger_id) = params.and_then(|Path(params)| params.get("ledger_id").cloned()) else {
+        return Ok(next.run(request).await);
+    };
+    let ledger_id = ledger_id.parse::<Uuid>().map_err(|_| AppError::NotFound)?;
+
+    let (mut parts, body) = request.into_parts();
+    let user = AuthUser::from_request_parts(&mut parts, &repos).await?;
+    service::ledger::ensure_member(&repos, ledger_id, &user).await?;
+    parts.extensions.insert(user);
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
 }
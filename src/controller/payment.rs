@@ -1,84 +1,206 @@
-use axum::{extract::Path, routing, Extension, Json, Router};
-use sqlx::PgPool;
+use axum::{
+    extract::{Path, Query, State},
+    routing, Json, Router,
+};
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::helper::Conversion;
-use crate::model::dto::auth::AuthUser;
+use crate::model::dto::auth::{AuthUser, MemberUser};
 use crate::model::dto::{request, response};
+use crate::repository::Repositories;
 use crate::service;
 
+/// Only admins can record payments for accounts that are not linked to themselves
 #[utoipa::path(
     post,
-    path = "/account/{account_id}/payment",
-    params(("account_id" = Uuid, Path,)),
+    path = "/ledger/{ledger_id}/account/{account_id}/payment",
+    params(("ledger_id" = Uuid, Path,), ("account_id" = Uuid, Path,)),
     request_body = CreatePaymentDto,
-    responses((status = 200, body = PaymentDto)),
+    responses((status = 200, body = PaymentDto), (status = 403)),
     security(("bearer_token" = []))
 )]
 async fn create_payment(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
+    Json(payment): Json<request::CreatePaymentDto>,
+) -> Result<Json<response::PaymentDto>, AppError> {
+    service::account::ensure_owned_by(&repos, ledger_id, account_id, &user).await?;
+
+    let payment = service::payment::create(&repos, ledger_id, account_id, payment).await?;
+
+    Ok(Json(payment.into()))
+}
+
+/// Record the payment as payed by the account of the caller in the ledger
+#[utoipa::path(
+    post,
+    path = "/ledger/{ledger_id}/payment",
+    params(("ledger_id" = Uuid, Path,)),
+    request_body = CreatePaymentDto,
+    responses((status = 200, body = PaymentDto)),
+    security(("bearer_token" = []))
+)]
+async fn create_own_payment(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Json(payment): Json<request::CreatePaymentDto>,
+) -> Result<Json<response::PaymentDto>, AppError> {
+    let account = service::account::get_of_user(&repos, ledger_id, user.id).await?;
+
+    let payment = service::payment::create(&repos, ledger_id, account.id, payment).await?;
+
+    Ok(Json(payment.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}/account/{account_id}/payment/{payment_id}",
+    params(request::GetPaymentParams),
+    responses((status = 200, body = PaymentDto), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn get_payment(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-    Path(account_id): Path<Uuid>,
+    State(repos): State<Repositories>,
+    Path(params): Path<request::GetPaymentParams>,
+) -> Result<Json<response::PaymentDto>, AppError> {
+    let payment = service::payment::get_of_party(
+        &repos,
+        params.ledger_id,
+        params.account_id,
+        params.payment_id,
+    )
+    .await?;
+
+    Ok(Json(payment.into()))
+}
+
+/// Only admins can change payments of accounts that are not linked to themselves
+#[utoipa::path(
+    put,
+    path = "/ledger/{ledger_id}/account/{account_id}/payment/{payment_id}",
+    params(request::UpdatePaymentParams),
+    request_body = CreatePaymentDto,
+    responses((status = 200, body = PaymentDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn update_payment(
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
+    Path(params): Path<request::UpdatePaymentParams>,
     Json(payment): Json<request::CreatePaymentDto>,
 ) -> Result<Json<response::PaymentDto>, AppError> {
-    let amount = Conversion::to_int(payment.amount);
-    let payment = service::payment::create(
-        &pool,
-        account_id,
-        payment.lender_account_id,
-        amount,
-        payment.description,
-        payment.event_date,
+    let current = service::payment::get_of_payer(
+        &repos,
+        params.ledger_id,
+        params.account_id,
+        params.payment_id,
     )
     .await?;
+    service::account::ensure_owned_by(&repos, params.ledger_id, current.payer_account_id, &user)
+        .await?;
+
+    let payment =
+        service::payment::update(&repos, params.ledger_id, params.payment_id, payment).await?;
 
     Ok(Json(payment.into()))
 }
 
+/// Only admins can delete payments of accounts that are not linked to themselves
 #[utoipa::path(
     delete,
-    path = "/account/{account_id}/payment/{payment_id}",
+    path = "/ledger/{ledger_id}/account/{account_id}/payment/{payment_id}",
     params(request::DeletePaymentParams),
-    responses((status = 200), (status = 404)),
+    responses((status = 200), (status = 403), (status = 404)),
     security(("bearer_token" = []))
 )]
 async fn delete_payment(
-    _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
+    MemberUser(user): MemberUser,
+    State(repos): State<Repositories>,
     Path(params): Path<request::DeletePaymentParams>,
 ) -> Result<(), AppError> {
-    service::payment::delete(&pool, params.payment_id).await?;
+    let current = service::payment::get_of_payer(
+        &repos,
+        params.ledger_id,
+        params.account_id,
+        params.payment_id,
+    )
+    .await?;
+    service::account::ensure_owned_by(&repos, params.ledger_id, current.payer_account_id, &user)
+        .await?;
+
+    service::payment::delete(&repos, params.ledger_id, params.payment_id).await?;
 
     Ok(())
 }
 
+/// One page of the payments that match all given filters, sorted by date or amount
+///
+/// `next_cursor` of the response continues the listing with the same filters and sorting
 #[utoipa::path(
     get,
-    path = "/payment",
-    responses((status = 200, body = [PaymentDto])),
+    path = "/ledger/{ledger_id}/payment",
+    params(("ledger_id" = Uuid, Path,), request::PaymentsQuery),
+    responses((status = 200, body = PaymentPageDto), (status = 400)),
     security(("bearer_token" = []))
 )]
 async fn get_all_payment(
     _user: AuthUser,
-    Extension(pool): Extension<PgPool>,
-) -> Result<Json<Vec<response::PaymentDto>>, AppError> {
-    let payments = service::payment::get_all(&pool).await?;
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Query(query): Query<request::PaymentsQuery>,
+) -> Result<Json<response::PaymentPageDto>, AppError> {
+    let page = service::payment::get_all(&repos, ledger_id, query).await?;
 
-    let payments = payments.iter().cloned().map(Into::into).collect();
+    Ok(Json(page))
+}
+
+/// Payments payed and received by the account, optionally only in the given range
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}/account/{account_id}/payment",
+    params(
+        ("ledger_id" = Uuid, Path,),
+        ("account_id" = Uuid, Path,),
+        request::AccountPaymentsQuery
+    ),
+    responses((status = 200, body = AccountPaymentsDto), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn get_account_payments(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+    Path((ledger_id, account_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<request::AccountPaymentsQuery>,
+) -> Result<Json<response::AccountPaymentsDto>, AppError> {
+    let payments = service::payment::get_of_account(
+        &repos,
+        ledger_id,
+        account_id,
+        query.start_date,
+        query.end_date,
+    )
+    .await?;
 
     Ok(Json(payments))
 }
 
-pub fn app() -> Router {
+pub fn app() -> Router<Repositories> {
     Router::new()
         .route(
-            "/account/:account_id/payment",
-            routing::post(create_payment),
+            "/ledger/:ledger_id/account/:account_id/payment",
+            routing::post(create_payment).get(get_account_payments),
+        )
+        .route(
+            "/ledger/:ledger_id/account/:account_id/payment/:payment_id",
+            routing::get(get_payment)
+                .put(update_payment)
+                .delete(delete_payment),
         )
         .route(
-            "/account/:account_id/payment/:payment_id",
-            routing::delete(delete_payment),
+            "/ledger/:ledger_id/payment",
+            routing::post(create_own_payment).get(get_all_payment),
         )
-        .route("/payment", routing::get(get_all_payment))
 }
@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Path, State},
+    routing, Json, Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::AuthUser;
+use crate::model::dto::{request, response};
+use crate::repository::Repositories;
+use crate::service;
+
+/// Create a token for scripts, which can only call the routes of its scopes
+///
+/// Tokens can only be managed with the login of a session, not with other tokens
+#[utoipa::path(
+    post,
+    path = "/tokens",
+    request_body = CreatePersonalAccessTokenDto,
+    responses((status = 200, body = CreatedPersonalAccessTokenDto), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn create_token(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+    Json(token): Json<request::CreatePersonalAccessTokenDto>,
+) -> Result<Json<response::CreatedPersonalAccessTokenDto>, AppError> {
+    let (token, personal_access_token) = service::personal_access_token::create(
+        &repos,
+        user.id,
+        token.name,
+        token.scopes,
+        token.expires_in_days,
+    )
+    .await?;
+
+    Ok(Json(response::CreatedPersonalAccessTokenDto {
+        token,
+        personal_access_token: personal_access_token.into(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    responses((status = 200, body = [PersonalAccessTokenDto]), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn get_all_tokens(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<response::PersonalAccessTokenDto>>, AppError> {
+    let tokens = service::personal_access_token::get_all_of_user(&repos, user.id).await?;
+
+    let tokens = tokens.iter().cloned().map(Into::into).collect();
+
+    Ok(Json(tokens))
+}
+
+/// Revoke the token, it can not be used afterwards
+#[utoipa::path(
+    delete,
+    path = "/tokens/{token_id}",
+    params(("token_id" = Uuid, Path,)),
+    responses((status = 200), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn delete_token(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(token_id): Path<Uuid>,
+) -> Result<(), AppError> {
+    service::personal_access_token::delete(&repos, user.id, token_id).await?;
+
+    Ok(())
+}
+
+/// Tokens are only needed with logins, single user instances have none
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route("/tokens", routing::post(create_token).get(get_all_tokens))
+        .route("/tokens/:token_id", routing::delete(delete_token))
+}
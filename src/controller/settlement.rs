@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, State},
+    routing, Json, Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{AdminUser, AuthUser};
+use crate::model::dto::{request, response};
+use crate::repository::Repositories;
+use crate::service;
+
+/// Minimal set of transfers, where the payer account pays the amount to the lender account,
+/// after which all debts are settled
+#[utoipa::path(
+    get,
+    path = "/ledger/{ledger_id}/settlement",
+    params(("ledger_id" = Uuid, Path,)),
+    responses((status = 200, body = [CalculatedDebtDto])),
+    security(("bearer_token" = []))
+)]
+async fn get_settlement(
+    _user: AuthUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+) -> Result<Json<Vec<response::CalculatedDebtDto>>, AppError> {
+    let plan = service::settlement::get_plan(&repos, ledger_id).await?;
+
+    Ok(Json(plan))
+}
+
+/// Record every transfer of the current settlement as a payment
+///
+/// The payments are made for every account of the ledger, so only admins can apply it
+#[utoipa::path(
+    post,
+    path = "/ledger/{ledger_id}/settlement/apply",
+    params(("ledger_id" = Uuid, Path,)),
+    request_body = ApplySettlementDto,
+    responses((status = 200, body = [PaymentDto]), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn apply_settlement(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Path(ledger_id): Path<Uuid>,
+    Json(settlement): Json<request::ApplySettlementDto>,
+) -> Result<Json<Vec<response::PaymentDto>>, AppError> {
+    let payments = service::settlement::apply(
+        &repos,
+        ledger_id,
+        settlement.description,
+        settlement.event_date,
+    )
+    .await?;
+
+    let payments = payments.iter().cloned().map(Into::into).collect();
+
+    Ok(Json(payments))
+}
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route(
+            "/ledger/:ledger_id/settlement",
+            routing::get(get_settlement),
+        )
+        .route(
+            "/ledger/:ledger_id/settlement/apply",
+            routing::post(apply_settlement),
+        )
+}
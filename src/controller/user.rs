@@ -0,0 +1,203 @@
+use axum::{
+    extract::{Path, State},
+    routing, Extension, Json, Router,
+};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{AdminUser, AuthUser, SingleUser};
+use crate::model::dto::{request, response};
+use crate::model::entity::Role;
+use crate::model::money::Money;
+use crate::repository::Repositories;
+use crate::service;
+
+/// Allow the user of the provider to login, only invited users have access
+#[utoipa::path(
+    post,
+    path = "/admin/user",
+    request_body = InviteUserDto,
+    responses((status = 200, body = UserDto), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn invite_user(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    Json(user): Json<request::InviteUserDto>,
+) -> Result<Json<response::UserDto>, AppError> {
+    if user
+        .provider
+        .eq_ignore_ascii_case(service::credential::PROVIDER)
+    {
+        return Err(AppError::Controller(
+            "local users are invited via /admin/user/local".to_string(),
+        ));
+    }
+
+    let user = service::user::invite(
+        &repos,
+        user.provider,
+        user.subject,
+        user.role.unwrap_or(Role::Member),
+    )
+    .await?;
+    let identities = service::user::get_identities(&repos, user.id).await?;
+
+    Ok(Json(response::UserDto::new(user, identities)))
+}
+
+/// Create a user that logs in with a password, requires `LOCAL_LOGIN=true`
+///
+/// The returned registration code is valid for 7 days and has to be passed to the user
+#[utoipa::path(
+    post,
+    path = "/admin/user/local",
+    request_body = InviteLocalUserDto,
+    responses((status = 200, body = LocalInviteDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn invite_local_user(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+    single_user: Option<Extension<SingleUser>>,
+    Json(user): Json<request::InviteLocalUserDto>,
+) -> Result<Json<response::LocalInviteDto>, AppError> {
+    // single user instances have no logins at all
+    if single_user.is_some() || !service::credential::is_enabled() {
+        return Err(AppError::NotFound);
+    }
+
+    let (user_id, registration_code) =
+        service::credential::invite(&repos, &user.username, user.role.unwrap_or(Role::Member))
+            .await?;
+    let user = service::user::get(&repos, user_id).await?;
+    let identities = service::user::get_identities(&repos, user_id).await?;
+
+    Ok(Json(response::LocalInviteDto {
+        user: response::UserDto::new(user, identities),
+        registration_code,
+    }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/user/{user_id}/role",
+    params(("user_id" = Uuid, Path,)),
+    request_body = UpdateUserRoleDto,
+    responses((status = 200, body = UserDto), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn update_user_role(
+    AdminUser(admin): AdminUser,
+    State(repos): State<Repositories>,
+    Path(user_id): Path<Uuid>,
+    Json(update): Json<request::UpdateUserRoleDto>,
+) -> Result<Json<response::UserDto>, AppError> {
+    if admin.id == user_id {
+        return Err(AppError::Controller(
+            "admins can not change their own role".to_string(),
+        ));
+    }
+
+    let user = service::user::update_role(&repos, user_id, update.role).await?;
+    let identities = service::user::get_identities(&repos, user.id).await?;
+
+    Ok(Json(response::UserDto::new(user, identities)))
+}
+
+/// Remove the user, all of its logins are invalidated
+#[utoipa::path(
+    delete,
+    path = "/admin/user/{user_id}",
+    params(("user_id" = Uuid, Path,)),
+    responses((status = 200), (status = 403), (status = 404)),
+    security(("bearer_token" = []))
+)]
+async fn delete_user(
+    AdminUser(admin): AdminUser,
+    State(repos): State<Repositories>,
+    Path(user_id): Path<Uuid>,
+) -> Result<(), AppError> {
+    if admin.id == user_id {
+        return Err(AppError::Controller(
+            "admins can not remove themselves".to_string(),
+        ));
+    }
+
+    service::user::delete(&repos, user_id).await?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/user",
+    responses((status = 200, body = [UserDto]), (status = 403)),
+    security(("bearer_token" = []))
+)]
+async fn get_all_users(
+    _admin: AdminUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<Vec<response::UserDto>>, AppError> {
+    let users = service::user::get_all(&repos).await?;
+    let identities = service::user::get_all_identities(&repos).await?;
+
+    let users = users
+        .into_iter()
+        .map(|user| {
+            let identities = identities
+                .iter()
+                .filter(|identity| identity.user_id == user.id)
+                .cloned()
+                .collect();
+
+            response::UserDto::new(user, identities)
+        })
+        .collect();
+
+    Ok(Json(users))
+}
+
+/// Profile of the caller with all linked accounts and their balance
+#[utoipa::path(
+    get,
+    path = "/me",
+    responses((status = 200, body = MeDto)),
+    security(("bearer_token" = []))
+)]
+async fn get_me(
+    user: AuthUser,
+    State(repos): State<Repositories>,
+) -> Result<Json<response::MeDto>, AppError> {
+    let profile = service::user::get(&repos, user.id).await?;
+    let identities = service::user::get_identities(&repos, user.id).await?;
+    let linked_accounts = service::account::get_all_of_user(&repos, user.id).await?;
+
+    let mut accounts = Vec::with_capacity(linked_accounts.len());
+    for account in linked_accounts {
+        let ledger = service::ledger::get(&repos, account.ledger_id).await?;
+        let balance = service::account::get_balance(&repos, ledger.id, account.id).await?;
+
+        accounts.push(response::AccountBalanceDto {
+            ledger_id: ledger.id,
+            account: account.into(),
+            balance: Money::from_cents(balance),
+            currency: ledger.base_currency,
+        });
+    }
+
+    Ok(Json(response::MeDto {
+        user: response::UserDto::new(profile, identities),
+        username: user.username,
+        accounts,
+    }))
+}
+
+pub fn app() -> Router<Repositories> {
+    Router::new()
+        .route("/admin/user", routing::post(invite_user).get(get_all_users))
+        .route("/admin/user/local", routing::post(invite_local_user))
+        .route("/admin/user/:user_id", routing::delete(delete_user))
+        .route("/admin/user/:user_id/role", routing::put(update_user_role))
+        .route("/me", routing::get(get_me))
+}
@@ -14,6 +14,7 @@ pub enum AppError {
     InternalServer(String),
     NotFound,
     Forbidden,
+    Conflict(String),
 }
 
 impl IntoResponse for AppError {
@@ -29,6 +30,7 @@ impl IntoResponse for AppError {
                 )
             }
             Self::Forbidden => (StatusCode::FORBIDDEN, "no permission".into()),
+            Self::Conflict(msg) => (StatusCode::CONFLICT, msg),
         };
 
         let body = Json(json!({
@@ -43,6 +45,26 @@ impl From<sqlx::Error> for AppError {
     fn from(value: sqlx::Error) -> Self {
         match value {
             sqlx::Error::RowNotFound => Self::NotFound,
+            // foreign key violation, e.g. an account id that does not exist
+            // postgres and sqlite (787) use different codes
+            sqlx::Error::Database(err)
+                if matches!(err.code().as_deref(), Some("23503" | "787")) =>
+            {
+                Self::Service(format!(
+                    "referenced {} does not exist",
+                    err.constraint().unwrap_or("entry")
+                ))
+            }
+            // unique violation, e.g. the same exchange rate for a day
+            // sqlite has codes for unique (2067) and primary key (1555) violations
+            sqlx::Error::Database(err)
+                if matches!(err.code().as_deref(), Some("23505" | "2067" | "1555")) =>
+            {
+                Self::Service(format!(
+                    "{} already exists",
+                    err.constraint().unwrap_or("entry")
+                ))
+            }
             _ => Self::InternalServer(value.to_string()),
         }
     }
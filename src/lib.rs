@@ -0,0 +1,93 @@
+use axum::{middleware, routing, Extension, Router};
+use sqlx::{PgPool, SqlitePool};
+use tower_http::{cors::CorsLayer, trace::TraceLayer};
+
+mod auth;
+mod controller;
+mod error;
+mod logging;
+mod model;
+mod open_api;
+mod repository;
+mod service;
+
+pub const SWAGGER_URI: &str = "swagger-ui";
+
+/// Bring the database up to date with all migrations
+pub async fn migrate(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    sqlx::migrate!("./migrations").run(pool).await
+}
+
+/// Bring a single user database up to date, it has its own migrations
+pub async fn migrate_sqlite(pool: &SqlitePool) -> Result<(), sqlx::migrate::MigrateError> {
+    sqlx::migrate!("./migrations-sqlite").run(pool).await
+}
+
+/// Tasks that run for as long as the server runs, e.g. purging expired sessions
+pub fn spawn_background_tasks(pool: PgPool) {
+    tokio::spawn(service::session::purge_expired_periodically(
+        repository::Repositories::postgres(pool),
+        std::time::Duration::from_secs(60 * 60),
+    ));
+}
+
+/// All routes of the api, the login providers are configured via the environment
+pub async fn app(pool: PgPool) -> Router {
+    let repos = repository::Repositories::postgres(pool);
+
+    // order is important, routes can only acces extensions that are added afterwards
+    Router::new()
+        .merge(open_api::app(SWAGGER_URI))
+        .merge(
+            Router::new()
+                .merge(auth::app())
+                .merge(controller::personal_access_token::app())
+                .with_state(repos.clone()),
+        )
+        .merge(controller::app(repos))
+        .layer(Extension(auth::provider::Providers::from_env().await))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(logging::print_request_response))
+        .route("/health", routing::get(|| async {}))
+}
+
+/// Routes of the api without any login, every request is made as the only user (an admin)
+///
+/// Nobody logs in, so the routes of logins, sessions and tokens do not exist
+#[allow(clippy::expect_used)]
+pub async fn single_user_app(pool: SqlitePool) -> Router {
+    let repos = repository::Repositories::sqlite(pool);
+
+    let identities = repos
+        .users
+        .get_all_identities()
+        .await
+        .expect("users can be read");
+    let user_id = match identities.iter().find(|identity| {
+        identity.provider == service::credential::PROVIDER
+            && identity.subject == model::dto::auth::SINGLE_USER
+    }) {
+        Some(identity) => identity.user_id,
+        None => repos
+            .users
+            .create(
+                service::credential::PROVIDER,
+                model::dto::auth::SINGLE_USER,
+                model::entity::Role::Admin,
+            )
+            .await
+            .expect("single user can be created"),
+    };
+
+    tracing::warn!("Single user mode, every request has full access without a login");
+
+    Router::new()
+        .merge(open_api::app(SWAGGER_URI))
+        .merge(controller::app(repos))
+        .layer(Extension(model::dto::auth::SingleUser(user_id)))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn(logging::print_request_response))
+        .route("/health", routing::get(|| async {}))
+}
@@ -1,17 +1,9 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, str::FromStr};
 
-use axum::{middleware, routing, Extension, Router};
-use sqlx::postgres::PgPoolOptions;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-
-mod auth;
-mod controller;
-mod error;
-mod helper;
-mod logging;
-mod model;
-mod open_api;
-mod service;
+use sqlx::{
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
 
 #[allow(clippy::expect_used)]
 #[tokio::main]
@@ -26,39 +18,57 @@ async fn main() {
 
     let db_connection_str = std::env::var("DATABASE_URL").expect(".env has valid DATABASE_URL");
 
-    let pool = PgPoolOptions::new()
-        .connect(&db_connection_str)
-        .await
-        .expect("pool can connect to database");
-
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("can run migration");
-
-    let swagger_uri = "swagger-ui";
-
-    // order is important, routes can only acces extensions that are added afterwards
-    let app = Router::new()
-        .merge(open_api::app(swagger_uri))
-        .merge(auth::app())
-        .merge(controller::app())
-        .layer(Extension(pool))
-        .layer(Extension(auth::oauth_client()))
-        .layer(CorsLayer::permissive())
-        .layer(TraceLayer::new_for_http())
-        .layer(middleware::from_fn(logging::print_request_response))
-        .route("/health", routing::get(|| async {}));
-
     let api_config_str = std::env::var("API_ADDR").expect(".env has valid API_ADDR");
     let api_config = parse_api_config(&api_config_str);
     let addr = SocketAddr::from(api_config);
 
+    // a sqlite file is used for single user instances without logins, every request has full
+    // access, so they have to be enabled explicitly
+    let app = if db_connection_str.starts_with("sqlite:") {
+        assert!(
+            std::env::var("SINGLE_USER").is_ok_and(|enabled| enabled == "true"),
+            "sqlite is only supported for single user instances without logins, set SINGLE_USER=true"
+        );
+        if !addr.ip().is_loopback() {
+            tracing::warn!("Single user mode is reachable from other machines via {addr}");
+        }
+
+        let options = SqliteConnectOptions::from_str(&db_connection_str)
+            .expect(".env has valid sqlite DATABASE_URL")
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .expect("pool can open database file");
+
+        money_tracker::migrate_sqlite(&pool)
+            .await
+            .expect("can run migration");
+
+        money_tracker::single_user_app(pool).await
+    } else {
+        let pool = PgPoolOptions::new()
+            .connect(&db_connection_str)
+            .await
+            .expect("pool can connect to database");
+
+        money_tracker::migrate(&pool)
+            .await
+            .expect("can run migration");
+
+        money_tracker::spawn_background_tasks(pool.clone());
+
+        money_tracker::app(pool).await
+    };
+
+    let swagger_uri = money_tracker::SWAGGER_URI;
+
     tracing::info!("Server listening on {addr}");
     tracing::info!("Swagger available under {addr}/{swagger_uri}");
 
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("server can bind to address and serve endpoints");
 }
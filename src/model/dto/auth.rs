@@ -1,19 +1,18 @@
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-    time::SystemTime,
-};
-
-use axum::extract::FromRequestParts;
-use http::request::Parts;
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, FromRef, FromRequestParts, MatchedPath};
+use http::{header, request::Parts, Method};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
-use utoipa::IntoParams;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
 use axum::{async_trait, extract::TypedHeader};
 use headers::{authorization::Bearer, Authorization};
 
 use crate::error::AppError;
+use crate::model::entity::{self, Role, Scope};
+use crate::repository::{Repositories, TokenUser};
+use crate::service;
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize)]
@@ -25,83 +24,378 @@ pub struct AuthRequestQuery {
 #[allow(clippy::module_name_repetitions)]
 #[derive(Deserialize, IntoParams)]
 pub struct AuthRequestParams {
+    /// where to redirect after the login, its origin has to be allowed via `ALLOWED_ORIGINS`
     pub origin_uri: String,
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuthUser {
-    pub id: String,
-    pub avatar: Option<String>,
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LoginCodeDto {
+    /// the `login_code` query param of the redirect after the login
+    pub login_code: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LocalLoginDto {
+    pub username: String,
+    pub password: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LocalRegisterDto {
     pub username: String,
-    // TODO: this is not used by discord anymore, can be removed later on
-    pub discriminator: String,
+    /// one time code of the invite by an admin
+    pub registration_code: String,
+    /// at least 12 characters
+    pub password: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangePasswordDto {
+    pub current_password: String,
+    /// at least 12 characters
+    pub new_password: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthorizeUrlDto {
+    /// page of the provider to open, which redirects like a login afterwards
+    pub authorize_url: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RefreshTokenDto {
+    pub refresh_token: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenDto {
+    /// short lived bearer token
+    pub access_token: String,
+    /// can be used once to get new tokens via `/auth/refresh`
+    pub refresh_token: String,
+    /// seconds until the access token expires
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionDto {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// if this is the session of the request
+    pub current: bool,
+
+    #[schema(value_type = String)]
+    pub creation_date: chrono::NaiveDateTime,
+    #[schema(value_type = String)]
+    pub refresh_expires_at: chrono::NaiveDateTime,
 }
 
-impl AuthUser {
-    pub fn account_id(&self) -> String {
-        self.id.to_string()
+impl SessionDto {
+    pub fn new(session: entity::Session, current_session_id: Option<Uuid>) -> Self {
+        Self {
+            id: session.id,
+            user_agent: session.user_agent,
+            ip: session.ip,
+            current: Some(session.id) == current_session_id,
+            creation_date: session.creation_date,
+            refresh_expires_at: session.refresh_expires_at,
+        }
     }
+}
+
+/// Device information of the caller, which is stored with their sessions
+#[derive(Debug, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: header::HeaderName| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+
+        let forwarded_for = header(header::HeaderName::from_static("x-forwarded-for"));
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+
+        Ok(Self {
+            user_agent: header(header::USER_AGENT),
+            ip: client_ip(forwarded_for.as_deref(), peer, trusts_proxy()),
+        })
+    }
+}
+
+/// `X-Forwarded-For` is only honoured behind a proxy, which is configured via `TRUSTED_PROXY=true`
+fn trusts_proxy() -> bool {
+    std::env::var("TRUSTED_PROXY").is_ok_and(|enabled| enabled == "true")
+}
+
+/// Without a trusted proxy anybody could send the header, so the address of the connection is used
+fn client_ip(
+    forwarded_for: Option<&str>,
+    peer: Option<SocketAddr>,
+    trust_proxy: bool,
+) -> Option<String> {
+    // the proxy appends the address it got the request from, the ones before are sent by the client
+    let forwarded = forwarded_for
+        .filter(|_| trust_proxy)
+        .and_then(|ips| ips.rsplit(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(ToString::to_string);
+
+    forwarded.or_else(|| peer.map(|addr| addr.ip().to_string()))
+}
+
+/// Any logged in user that was invited, only allowed to read
+///
+/// Personal access tokens are only accepted on routes their scopes allow
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    /// not set if a personal access token is used
+    pub session_id: Option<Uuid>,
+    pub username: String,
+    pub role: Role,
+}
 
-    pub fn generate_access_token(&self) -> String {
-        // TODO: use some crypt instead of hash or maybe even jwt with refresh token etc
-        let mut hasher = DefaultHasher::new();
-        self.hash(&mut hasher);
-        SystemTime::now().hash(&mut hasher);
-        hasher.finish().to_string()
+impl From<TokenUser> for AuthUser {
+    fn from(user: TokenUser) -> Self {
+        Self {
+            id: user.user_id,
+            session_id: user.session_id,
+            username: user.username,
+            role: user.role,
+        }
     }
 }
 
-impl Hash for AuthUser {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.id.hash(state);
-        self.avatar.hash(state);
-        self.username.hash(state);
-        self.discriminator.hash(state);
+/// Name of the identity of the single user, with the provider `local`
+pub const SINGLE_USER: &str = "owner";
+
+/// The only user of an instance without logins, every request is made as this admin
+#[derive(Debug, Clone, Copy)]
+pub struct SingleUser(pub Uuid);
+
+/// Scope a personal access token needs for the route, `None` if only sessions are allowed
+/// (e.g. managing users, sessions and tokens)
+///
+/// Routes are matched by their exact template, so a new route is closed to tokens until it is
+/// listed here
+fn required_scope(method: &Method, route: &str) -> Option<Scope> {
+    let by_method = |read, write| Some(if method == Method::GET { read } else { write });
+
+    match route {
+        "/ledger/:ledger_id/settlement/apply" => Some(Scope::PaymentsWrite),
+        "/ledger/:ledger_id/snapshot" | "/ledger/:ledger_id/settlement" | "/me" => {
+            (method == Method::GET).then_some(Scope::SnapshotRead)
+        }
+        "/ledger/:ledger_id/cost"
+        | "/ledger/:ledger_id/account/:account_id/cost"
+        | "/ledger/:ledger_id/account/:account_id/cost/:cost_id" => {
+            by_method(Scope::CostsRead, Scope::CostsWrite)
+        }
+        "/ledger/:ledger_id/payment"
+        | "/ledger/:ledger_id/account/:account_id/payment"
+        | "/ledger/:ledger_id/account/:account_id/payment/:payment_id" => {
+            by_method(Scope::PaymentsRead, Scope::PaymentsWrite)
+        }
+        "/exchange-rate" | "/exchange-rate/:exchange_rate_id" => {
+            by_method(Scope::ExchangeRatesRead, Scope::ExchangeRatesWrite)
+        }
+        "/ledger"
+        | "/ledger/:ledger_id"
+        | "/ledger/:ledger_id/tags"
+        | "/ledger/:ledger_id/account"
+        | "/ledger/:ledger_id/account/:account_id"
+        | "/ledger/:ledger_id/account/:account_id/tags" => {
+            by_method(Scope::LedgersRead, Scope::LedgersWrite)
+        }
+        _ => None,
     }
 }
 
+/// Logged in user that is allowed to change data (member or admin)
+pub struct MemberUser(pub AuthUser);
+
+/// Logged in user that is allowed to manage other users
+pub struct AdminUser(pub AuthUser);
+
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
+    Repositories: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // already looked up by the check of the ledger membership
+        if let Some(user) = parts.extensions.get::<Self>() {
+            return Ok(user.clone());
+        }
+
+        if let Some(SingleUser(id)) = parts.extensions.get::<SingleUser>() {
+            return Ok(Self {
+                id: *id,
+                session_id: None,
+                username: SINGLE_USER.to_string(),
+                role: Role::Admin,
+            });
+        }
+
         let TypedHeader(Authorization(bearer)) =
             TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
                 .await
                 .map_err(|_| AppError::Forbidden)?;
 
-        let pool = parts
-            .extensions
-            .get::<PgPool>()
-            .ok_or_else(|| AppError::InternalServer("pool could not be found".to_owned()))?;
-
-        let auth_user = sqlx::query_as!(
-            AuthUser,
-            r#"
-                SELECT id, avatar, username, discriminator
-                FROM auth_user
-                    WHERE access_token = $1
-            "#,
-            &bearer.token().to_string(),
-        )
-        .fetch_one(pool)
-        .await
-        .map_err(|_| AppError::Forbidden)?;
-
-        // TODO: this is a quickfix until correct user accounts are implemented via db
-        [
-            "138371651942219777", // eckon
-            "207268277305606144", // Hanawa
-        ]
-        .iter()
-        .any(|acc| *(*acc).to_string() == auth_user.account_id())
-        .then_some(0)
-        .ok_or(AppError::Forbidden)?;
-
-        Ok(auth_user)
+        let repos = Repositories::from_ref(state);
+
+        if bearer
+            .token()
+            .starts_with(service::personal_access_token::PREFIX)
+        {
+            let route = parts
+                .extensions
+                .get::<MatchedPath>()
+                .map(MatchedPath::as_str)
+                .unwrap_or_default();
+            let scope = required_scope(&parts.method, route).ok_or(AppError::Forbidden)?;
+
+            let user =
+                service::personal_access_token::get_user(&repos, bearer.token(), scope).await?;
+
+            return Ok(user.into());
+        }
+
+        let user = service::session::get_user(&repos, bearer.token()).await?;
+
+        Ok(user.into())
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for MemberUser
+where
+    Repositories: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+
+        match user.role {
+            Role::Admin | Role::Member => Ok(Self(user)),
+            Role::ReadOnly => Err(AppError::Forbidden),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminUser
+where
+    Repositories: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+
+        match user.role {
+            Role::Admin => Ok(Self(user)),
+            Role::Member | Role::ReadOnly => Err(AppError::Forbidden),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwarded_ip_only_behind_trusted_proxy() {
+        let peer = Some(SocketAddr::from(([10, 0, 0, 1], 4321)));
+
+        assert_eq!(
+            Some("10.0.0.1".to_string()),
+            client_ip(Some("1.2.3.4"), peer, false)
+        );
+        assert_eq!(
+            Some("5.6.7.8".to_string()),
+            client_ip(Some("1.2.3.4, 5.6.7.8"), peer, true)
+        );
+        assert_eq!(
+            Some("10.0.0.1".to_string()),
+            client_ip(Some(""), peer, true)
+        );
+        assert_eq!(Some("10.0.0.1".to_string()), client_ip(None, peer, true));
+        assert_eq!(None, client_ip(Some("1.2.3.4"), None, false));
+    }
+
+    #[test]
+    fn require_scope_of_route() {
+        let get = Method::GET;
+        let post = Method::POST;
+
+        assert_eq!(
+            Some(Scope::CostsRead),
+            required_scope(&get, "/ledger/:ledger_id/cost")
+        );
+        assert_eq!(
+            Some(Scope::CostsWrite),
+            required_scope(&post, "/ledger/:ledger_id/account/:account_id/cost")
+        );
+        assert_eq!(
+            Some(Scope::PaymentsWrite),
+            required_scope(&post, "/ledger/:ledger_id/settlement/apply")
+        );
+        assert_eq!(
+            Some(Scope::SnapshotRead),
+            required_scope(&get, "/ledger/:ledger_id/snapshot")
+        );
+        assert_eq!(
+            Some(Scope::LedgersWrite),
+            required_scope(&Method::DELETE, "/ledger/:ledger_id/account/:account_id")
+        );
+        assert_eq!(
+            Some(Scope::ExchangeRatesRead),
+            required_scope(&get, "/exchange-rate")
+        );
+        assert_eq!(None, required_scope(&post, "/tokens"));
+        assert_eq!(None, required_scope(&get, "/admin/user"));
+        assert_eq!(None, required_scope(&get, "/auth/sessions"));
+        assert_eq!(None, required_scope(&get, ""));
+        // only exact templates, not every route that contains a scoped part
+        assert_eq!(None, required_scope(&get, "/admin/cost"));
+        assert_eq!(
+            None,
+            required_scope(&post, "/ledger/:ledger_id/costs/import")
+        );
+        assert_eq!(None, required_scope(&post, "/admin/balances/rebuild"));
     }
 }
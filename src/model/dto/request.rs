@@ -1,34 +1,86 @@
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::model::{
+    entity::{Role, Scope},
+    money::Money,
+    percentage::Percentage,
+};
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct CreateLedgerDto {
+    pub name: String,
+    /// ISO 4217 code every snapshot is calculated in, defaults to EUR on creation and to the
+    /// current one on updates
+    pub base_currency: Option<String>,
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct CreateAccountDto {
     pub name: String,
+    /// user who owns the account, only they can record costs and payments for it
+    ///
+    /// Members can only link themselves to an account without a user, kept as is if missing
+    pub user_id: Option<Uuid>,
 }
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct CreatePaymentDto {
     pub lender_account_id: Uuid,
-    pub amount: f64,
+    pub amount: Money,
+    /// ISO 4217 code, defaults to the base currency of the ledger
+    pub currency: Option<String>,
 
     #[schema(value_type = String)]
     pub event_date: chrono::NaiveDate,
     pub description: Option<String>,
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct GetPaymentParams {
+    pub ledger_id: Uuid,
+    /// account that payed or received it
+    pub account_id: Uuid,
+    pub payment_id: Uuid,
+}
+
 #[derive(Deserialize, IntoParams)]
 pub struct DeletePaymentParams {
+    pub ledger_id: Uuid,
+    /// account that payed it
+    pub account_id: Uuid,
+    pub payment_id: Uuid,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct UpdatePaymentParams {
+    pub ledger_id: Uuid,
+    /// account that payed it
+    pub account_id: Uuid,
     pub payment_id: Uuid,
 }
 
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct ApplySettlementDto {
+    #[schema(value_type = String)]
+    pub event_date: chrono::NaiveDate,
+    pub description: Option<String>,
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct CreateCostDto {
     pub debtors: Vec<CreateDebtorDto>,
-    pub amount: f64,
+    #[serde(default)]
+    pub split: SplitMode,
+    pub amount: Money,
+    /// ISO 4217 code, defaults to the base currency of the ledger
+    pub currency: Option<String>,
 
     #[schema(value_type = String)]
     pub event_date: chrono::NaiveDate,
@@ -40,16 +92,209 @@ pub struct CreateCostDto {
 #[derive(Debug, Deserialize, Clone, ToSchema)]
 pub struct CreateDebtorDto {
     pub account_id: Uuid,
-    pub amount: f64,
+    /// Needed for the `exact` split
+    pub amount: Option<Money>,
+    /// Needed for the `percentage` split, a decimal string like `"33.33"`
+    pub percentage: Option<Percentage>,
+    /// Needed for the `shares` split
+    pub shares: Option<u32>,
+}
+
+/// How the amount of a cost is split onto its debtors
+///
+/// - `exact`: every debtor has a given amount, which need to add up to the cost amount
+/// - `equal`: every debtor pays the same part
+/// - `percentage`: every debtor pays the given percentage, which need to add up to 100
+/// - `shares`: every debtor pays the part of the given shares of all shares
+///
+/// Leftover cents go to the debtors with the largest rounding remainder, on ties in the given order
+#[derive(Debug, Deserialize, Clone, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitMode {
+    #[default]
+    Exact,
+    Equal,
+    Percentage,
+    Shares,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct UpdateCostDto {
+    pub debtors: Option<Vec<CreateDebtorDto>>,
+    pub split: Option<SplitMode>,
+    pub amount: Option<Money>,
+    pub currency: Option<String>,
+
+    #[schema(value_type = Option<String>)]
+    pub event_date: Option<chrono::NaiveDate>,
+    /// `null` removes the description, without it the current one is kept
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>, nullable)]
+    pub description: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// A missing field is `None`, an explicit `null` is `Some(None)`
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct CreateExchangeRateDto {
+    /// Rate is used for all entries from this date until the next rate
+    #[schema(value_type = String)]
+    pub date: chrono::NaiveDate,
+    pub from_currency: String,
+    pub to_currency: String,
+    /// 1 unit of `from_currency` equals `rate` units of `to_currency`
+    pub rate: f64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct GetCostParams {
+    pub ledger_id: Uuid,
+    /// account that payed it
+    pub account_id: Uuid,
+    pub cost_id: Uuid,
 }
 
 #[derive(Deserialize, IntoParams)]
 pub struct DeleteCostParams {
+    pub ledger_id: Uuid,
+    /// account that payed it
+    pub account_id: Uuid,
     pub cost_id: Uuid,
 }
 
 #[derive(Deserialize, IntoParams)]
+pub struct UpdateCostParams {
+    pub ledger_id: Uuid,
+    /// account that payed it
+    pub account_id: Uuid,
+    pub cost_id: Uuid,
+}
+
+/// Field a listing is sorted by, entries with the same value are sorted by their id
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    #[default]
+    Date,
+    Amount,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filters, sorting and page of the cost listing, every given filter has to match
+#[derive(Debug, Deserialize, Default, IntoParams)]
 pub struct CostsQuery {
     pub start_date: Option<chrono::NaiveDate>,
     pub end_date: Option<chrono::NaiveDate>,
+    pub payer_account_id: Option<Uuid>,
+    /// costs with a debt of the account
+    pub debtor_account_id: Option<Uuid>,
+    /// comma separated, costs with all of the tags
+    pub tags: Option<String>,
+    /// amount of the cost in its own currency
+    #[param(value_type = Option<String>)]
+    pub min_amount: Option<Money>,
+    #[param(value_type = Option<String>)]
+    pub max_amount: Option<Money>,
+    /// part of the description, ignoring the case
+    pub description: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: SortBy,
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrder,
+    /// number of costs per page, 50 by default and at most 200
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page, with the same sorting
+    pub cursor: Option<String>,
+}
+
+/// Filters, sorting and page of the payment listing, every given filter has to match
+#[derive(Debug, Deserialize, Default, IntoParams)]
+pub struct PaymentsQuery {
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+    pub payer_account_id: Option<Uuid>,
+    pub lender_account_id: Option<Uuid>,
+    /// amount of the payment in its own currency
+    #[param(value_type = Option<String>)]
+    pub min_amount: Option<Money>,
+    #[param(value_type = Option<String>)]
+    pub max_amount: Option<Money>,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: SortBy,
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrder,
+    /// number of payments per page, 50 by default and at most 200
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page, with the same sorting
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AccountPaymentsQuery {
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SnapshotQuery {
+    pub from: Option<chrono::NaiveDate>,
+    pub as_of: Option<chrono::NaiveDate>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct InviteUserDto {
+    /// name of the provider, e.g. `discord`, `github` or `oidc`
+    pub provider: String,
+    /// id of the user at the provider, who is allowed to login afterwards
+    pub subject: String,
+    /// defaults to member
+    pub role: Option<Role>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct InviteLocalUserDto {
+    /// name the user logs in with, case insensitive
+    pub username: String,
+    /// defaults to member
+    pub role: Option<Role>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct UpdateUserRoleDto {
+    pub role: Role,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Clone, ToSchema)]
+pub struct CreatePersonalAccessTokenDto {
+    /// what the token is used for, e.g. the name of the script
+    pub name: String,
+    /// routes the token is allowed to call, the role of the user still applies
+    pub scopes: Vec<Scope>,
+    /// never expires if not set
+    pub expires_in_days: Option<i32>,
 }
@@ -2,13 +2,32 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{helper::Conversion, model::entity};
+use crate::model::{entity, money::Money};
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct LedgerDto {
+    pub id: Uuid,
+    pub name: String,
+    pub base_currency: String,
+}
+
+impl From<entity::Ledger> for LedgerDto {
+    fn from(ledger: entity::Ledger) -> Self {
+        Self {
+            id: ledger.id,
+            name: ledger.name,
+            base_currency: ledger.base_currency,
+        }
+    }
+}
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct AccountDto {
     pub id: Uuid,
     pub name: String,
+    pub user_id: Option<Uuid>,
 }
 
 impl From<entity::Account> for AccountDto {
@@ -16,6 +35,7 @@ impl From<entity::Account> for AccountDto {
         Self {
             id: account.id,
             name: account.name,
+            user_id: account.user_id,
         }
     }
 }
@@ -26,7 +46,8 @@ pub struct PaymentDto {
     pub id: Uuid,
     pub payer_account_id: Uuid,
     pub lender_account_id: Uuid,
-    pub amount: f64,
+    pub amount: Money,
+    pub currency: String,
 
     #[schema(value_type = String)]
     pub event_date: chrono::NaiveDate,
@@ -37,7 +58,8 @@ impl From<entity::Payment> for PaymentDto {
     fn from(payment: entity::Payment) -> Self {
         Self {
             id: payment.id,
-            amount: Conversion::to_float(payment.amount),
+            amount: Money::from_cents(payment.amount),
+            currency: payment.currency,
             payer_account_id: payment.payer_account_id,
             lender_account_id: payment.lender_account_id,
             event_date: payment.event_date,
@@ -46,19 +68,55 @@ impl From<entity::Payment> for PaymentDto {
     }
 }
 
+/// One page of the payment listing
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PaymentPageDto {
+    pub payments: Vec<PaymentDto>,
+    /// `cursor` of the next page, missing on the last one
+    pub next_cursor: Option<String>,
+}
+
+/// Payments of an account, newest first
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct AccountPaymentsDto {
+    /// payed by the account
+    pub outgoing: Vec<PaymentDto>,
+    /// received by the account
+    pub incoming: Vec<PaymentDto>,
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct CalculatedDebtDto {
     pub payer_account: AccountDto,
     pub lender_account: AccountDto,
-    pub amount: f64,
+    /// Amount in the base currency of the ledger
+    pub amount: Money,
+    pub currency: String,
+}
+
+/// Stored balance of an account pair that was different from the calculated one, a missing
+/// balance is 0
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct BalanceDriftDto {
+    pub ledger_id: Uuid,
+    pub payer_account_id: Uuid,
+    pub lender_account_id: Uuid,
+    /// Amount before the rebuild
+    pub stored: Money,
+    /// Amount after the rebuild
+    pub calculated: Money,
+    /// base currency of the ledger
+    pub currency: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct DebtDto {
     pub id: Uuid,
     pub account_id: Uuid,
-    pub amount: f64,
+    pub amount: Money,
 }
 
 impl From<entity::Debt> for DebtDto {
@@ -66,7 +124,7 @@ impl From<entity::Debt> for DebtDto {
         Self {
             id: cost.id,
             account_id: cost.debtor_account_id,
-            amount: Conversion::to_float(cost.amount),
+            amount: Money::from_cents(cost.amount),
         }
     }
 }
@@ -76,7 +134,8 @@ impl From<entity::Debt> for DebtDto {
 pub struct CostDto {
     pub id: Uuid,
     pub account_id: Uuid,
-    pub amount: f64,
+    pub amount: Money,
+    pub currency: String,
     pub debtors: Vec<DebtDto>,
 
     #[schema(value_type = String)]
@@ -85,12 +144,22 @@ pub struct CostDto {
     pub tags: Option<Vec<String>>,
 }
 
+/// One page of the cost listing
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct CostPageDto {
+    pub costs: Vec<CostDto>,
+    /// `cursor` of the next page, missing on the last one
+    pub next_cursor: Option<String>,
+}
+
 impl From<entity::Cost> for CostDto {
     fn from(cost: entity::Cost) -> Self {
         Self {
             id: cost.id,
             tags: cost.tags,
-            amount: Conversion::to_float(cost.amount),
+            amount: Money::from_cents(cost.amount),
+            currency: cost.currency,
             debtors: Vec::new(),
             account_id: cost.account_id,
             event_date: cost.event_date,
@@ -98,3 +167,143 @@ impl From<entity::Cost> for CostDto {
         }
     }
 }
+
+impl From<(entity::Cost, Vec<entity::Debt>)> for CostDto {
+    fn from((cost, debts): (entity::Cost, Vec<entity::Debt>)) -> Self {
+        Self {
+            debtors: debts.into_iter().map(Into::into).collect(),
+            ..cost.into()
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ExchangeRateDto {
+    pub id: Uuid,
+
+    #[schema(value_type = String)]
+    pub date: chrono::NaiveDate,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+}
+
+impl From<entity::ExchangeRate> for ExchangeRateDto {
+    fn from(rate: entity::ExchangeRate) -> Self {
+        Self {
+            id: rate.id,
+            date: rate.date,
+            from_currency: rate.from_currency,
+            to_currency: rate.to_currency,
+            rate: rate.rate,
+        }
+    }
+}
+
+/// Own account with its balance, positive if others owe the account, negative if it owes others
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct AccountBalanceDto {
+    pub ledger_id: Uuid,
+    pub account: AccountDto,
+    pub balance: Money,
+    /// base currency of the ledger
+    pub currency: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct MeDto {
+    pub user: UserDto,
+    pub username: String,
+    pub accounts: Vec<AccountBalanceDto>,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct IdentityDto {
+    pub provider: String,
+    pub subject: String,
+    /// known after the first login
+    pub username: Option<String>,
+    pub avatar: Option<String>,
+}
+
+impl From<entity::Identity> for IdentityDto {
+    fn from(identity: entity::Identity) -> Self {
+        Self {
+            provider: identity.provider,
+            subject: identity.subject,
+            username: identity.username,
+            avatar: identity.avatar,
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserDto {
+    pub id: Uuid,
+    pub role: entity::Role,
+    /// everything the user can login with
+    pub identities: Vec<IdentityDto>,
+
+    #[schema(value_type = String)]
+    pub creation_date: chrono::NaiveDateTime,
+}
+
+impl UserDto {
+    pub fn new(user: entity::User, identities: Vec<entity::Identity>) -> Self {
+        Self {
+            id: user.id,
+            role: user.role,
+            identities: identities.into_iter().map(Into::into).collect(),
+            creation_date: user.creation_date,
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct LocalInviteDto {
+    pub user: UserDto,
+    /// has to be passed to the user, who sets their password with it via `/auth/local/register`
+    pub registration_code: String,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PersonalAccessTokenDto {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<entity::Scope>,
+
+    #[schema(value_type = Option<String>)]
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    #[schema(value_type = Option<String>)]
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    #[schema(value_type = String)]
+    pub creation_date: chrono::NaiveDateTime,
+}
+
+impl From<entity::PersonalAccessToken> for PersonalAccessTokenDto {
+    fn from(token: entity::PersonalAccessToken) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            creation_date: token.creation_date,
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct CreatedPersonalAccessTokenDto {
+    /// bearer token for the scripts, it is only shown once
+    pub token: String,
+    pub personal_access_token: PersonalAccessTokenDto,
+}
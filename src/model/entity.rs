@@ -1,20 +1,130 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// What a user is allowed to do, `admin` includes everything of `member`
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+pub enum Role {
+    /// manage users and everything a member can do
+    Admin,
+    /// create, change and delete data
+    Member,
+    /// only read data
+    ReadOnly,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct User {
+    pub id: Uuid,
+    pub role: Role,
+    pub creation_date: chrono::NaiveDateTime,
+}
+
+/// Login of a user at a provider (e.g. discord)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Identity {
+    pub provider: String,
+    /// id of the user at the provider
+    pub subject: String,
+    pub user_id: Uuid,
+    pub username: Option<String>,
+    pub avatar: Option<String>,
+    pub creation_date: chrono::NaiveDateTime,
+}
+
+/// Login of a user on one device
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub creation_date: chrono::NaiveDateTime,
+    /// the session ends if it is not refreshed until then
+    pub refresh_expires_at: chrono::NaiveDateTime,
+}
+
+/// What a personal access token is allowed to do, in addition to the role of its user
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "token_scope")]
+pub enum Scope {
+    /// ledgers and their accounts
+    #[serde(rename = "ledgers:read")]
+    #[sqlx(rename = "ledgers:read")]
+    LedgersRead,
+    #[serde(rename = "ledgers:write")]
+    #[sqlx(rename = "ledgers:write")]
+    LedgersWrite,
+    #[serde(rename = "costs:read")]
+    #[sqlx(rename = "costs:read")]
+    CostsRead,
+    #[serde(rename = "costs:write")]
+    #[sqlx(rename = "costs:write")]
+    CostsWrite,
+    /// payments, including applying a settlement
+    #[serde(rename = "payments:read")]
+    #[sqlx(rename = "payments:read")]
+    PaymentsRead,
+    #[serde(rename = "payments:write")]
+    #[sqlx(rename = "payments:write")]
+    PaymentsWrite,
+    #[serde(rename = "exchange_rates:read")]
+    #[sqlx(rename = "exchange_rates:read")]
+    ExchangeRatesRead,
+    #[serde(rename = "exchange_rates:write")]
+    #[sqlx(rename = "exchange_rates:write")]
+    ExchangeRatesWrite,
+    /// snapshots, settlements and the balances of `/me`
+    #[serde(rename = "snapshot:read")]
+    #[sqlx(rename = "snapshot:read")]
+    SnapshotRead,
+}
+
+impl sqlx::postgres::PgHasArrayType for Scope {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_token_scope")
+    }
+}
+
+/// Long lived token of a user for scripts, only its hash is stored
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PersonalAccessToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    pub creation_date: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Ledger {
+    pub id: Uuid,
+    pub name: String,
+    pub base_currency: String,
+    /// incremented by every write of the balances of the ledger
+    pub balance_version: i64,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Account {
     pub id: Uuid,
+    pub ledger_id: Uuid,
     pub name: String,
+    pub user_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Payment {
     pub id: Uuid,
+    pub ledger_id: Uuid,
     pub payer_account_id: Uuid,
     pub lender_account_id: Uuid,
     pub amount: i64,
     pub event_date: chrono::NaiveDate,
     pub description: Option<String>,
+    pub currency: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -28,9 +138,30 @@ pub struct Debt {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Cost {
     pub id: Uuid,
+    pub ledger_id: Uuid,
     pub account_id: Uuid,
     pub amount: i64,
     pub event_date: chrono::NaiveDate,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    pub currency: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExchangeRate {
+    pub id: Uuid,
+    pub date: chrono::NaiveDate,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+}
+
+/// Debt between two accounts in the base currency of their ledger, stored in both directions
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Balance {
+    pub ledger_id: Uuid,
+    pub payer_account_id: Uuid,
+    pub lender_account_id: Uuid,
+    /// positive if the lender owes the payer
+    pub amount: i64,
 }
@@ -1,2 +1,4 @@
 pub mod dto;
 pub mod entity;
+pub mod money;
+pub mod percentage;
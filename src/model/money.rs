@@ -0,0 +1,152 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::{
+    openapi::{ObjectBuilder, RefOr, Schema, SchemaType},
+    ToSchema,
+};
+
+/// Exact amount of money, stored as cents
+///
+/// It is (de)serialized as a decimal string (e.g. `"17.40"`), so that no client ever has to
+/// handle floats, which can not represent most amounts correctly (`17.4 * 100 != 1740`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const fn from_cents(cents: i64) -> Self {
+        Self(cents)
+    }
+
+    pub const fn cents(self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let cents = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", cents / 100, cents % 100)
+    }
+}
+
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_hundredths(value, "amount", "17.40").map(Self)
+    }
+}
+
+/// Decimal with at most two fraction digits, e.g. `"17.4"` is 1740 hundredths
+///
+/// The error names the kind of value and gives an example of a valid one
+pub(crate) fn parse_hundredths(value: &str, kind: &str, example: &str) -> Result<i64, String> {
+    let invalid = || format!("\"{value}\" is not a valid {kind} (e.g. \"{example}\")");
+
+    let (negative, unsigned) = value
+        .strip_prefix('-')
+        .map_or((false, value), |unsigned| (true, unsigned));
+
+    let (units, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let has_fraction = unsigned.contains('.');
+    if units.is_empty()
+        || (has_fraction && fraction.is_empty())
+        || !is_digits(units)
+        || !is_digits(fraction)
+    {
+        return Err(invalid());
+    }
+
+    if fraction.len() > 2 {
+        return Err(format!(
+            "\"{value}\" has more than two fraction digits, which can not be stored exactly"
+        ));
+    }
+
+    // "17.4" means 40 hundredths not 4
+    let fraction_hundredths = format!("{fraction:0<2}")
+        .parse::<i64>()
+        .map_err(|_| invalid())?;
+
+    let hundredths = units
+        .parse::<i64>()
+        .ok()
+        .and_then(|units| units.checked_mul(100))
+        .and_then(|hundredths| hundredths.checked_add(fraction_hundredths))
+        .ok_or_else(invalid)?;
+
+    Ok(if negative { -hundredths } else { hundredths })
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<'s> ToSchema<'s> for Money {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "Money",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .description(Some("Decimal amount with at most two fraction digits"))
+                .pattern(Some(r"^-?\d+(\.\d{1,2})?$"))
+                .example(Some("17.40".into()))
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_decimal_string() {
+        assert_eq!(Ok(Money::from_cents(1740)), "17.4".parse());
+        assert_eq!(Ok(Money::from_cents(1740)), "17.40".parse());
+        assert_eq!(Ok(Money::from_cents(1700)), "17".parse());
+        assert_eq!(Ok(Money::from_cents(5)), "0.05".parse());
+        assert_eq!(Ok(Money::from_cents(-50)), "-0.5".parse());
+    }
+
+    #[test]
+    fn reject_invalid_amounts() {
+        for value in ["17.401", "17.", ".5", "", "-", "1,5", "1e3", "+1", "abc"] {
+            assert!(
+                value.parse::<Money>().is_err(),
+                "{value} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn display_with_two_fraction_digits() {
+        assert_eq!("17.40", Money::from_cents(1740).to_string());
+        assert_eq!("0.05", Money::from_cents(5).to_string());
+        assert_eq!("-0.89", Money::from_cents(-89).to_string());
+    }
+
+    #[test]
+    fn serialize_as_string() {
+        let json = serde_json::to_string(&Money::from_cents(1740)).unwrap();
+        assert_eq!("\"17.40\"", json);
+
+        let money: Money = serde_json::from_str("\"17.40\"").unwrap();
+        assert_eq!(Money::from_cents(1740), money);
+
+        assert!(serde_json::from_str::<Money>("17.4").is_err());
+    }
+}
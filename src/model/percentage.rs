@@ -0,0 +1,97 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::{
+    openapi::{ObjectBuilder, RefOr, Schema, SchemaType},
+    ToSchema,
+};
+
+use crate::model::money::parse_hundredths;
+
+/// Exact percentage, stored as hundredths of a percent (basis points)
+///
+/// It is (de)serialized as a decimal string (e.g. `"33.33"`) like [`Money`](super::money::Money),
+/// so splits that add up to 100 never miss by a rounding error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Percentage(i64);
+
+impl Percentage {
+    /// All of it, which the percentages of a split need to add up to
+    pub const WHOLE: Self = Self(100 * 100);
+
+    pub const fn from_basis_points(basis_points: i64) -> Self {
+        Self(basis_points)
+    }
+
+    pub const fn basis_points(self) -> i64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Percentage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let basis_points = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", basis_points / 100, basis_points % 100)
+    }
+}
+
+impl FromStr for Percentage {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse_hundredths(value, "percentage", "33.33").map(Self)
+    }
+}
+
+impl Serialize for Percentage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Percentage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+impl<'s> ToSchema<'s> for Percentage {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "Percentage",
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .description(Some("Decimal percentage with at most two fraction digits"))
+                .pattern(Some(r"^-?\d+(\.\d{1,2})?$"))
+                .example(Some("33.33".into()))
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_as_basis_points() {
+        assert_eq!(Ok(Percentage::from_basis_points(3333)), "33.33".parse());
+        assert_eq!(Ok(Percentage::from_basis_points(1750)), "17.5".parse());
+        assert_eq!(Ok(Percentage::WHOLE), "100".parse());
+        assert!("33.333".parse::<Percentage>().is_err());
+        assert!("1e2".parse::<Percentage>().is_err());
+    }
+
+    #[test]
+    fn serialize_as_string() {
+        let json = serde_json::to_string(&Percentage::from_basis_points(3333)).unwrap();
+        assert_eq!("\"33.33\"", json);
+
+        let percentage: Percentage = serde_json::from_str("\"12.5\"").unwrap();
+        assert_eq!(Percentage::from_basis_points(1250), percentage);
+
+        assert!(serde_json::from_str::<Percentage>("33.33").is_err());
+    }
+}
@@ -8,21 +8,60 @@ use utoipa::{
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::auth;
-use crate::controller::{account, cost, payment};
-use crate::model::dto::{request, response};
+use crate::controller::{
+    account, balance, cost, exchange_rate, ledger, payment, personal_access_token, settlement, user,
+};
+use crate::model::dto::{auth as auth_dto, request, response};
+use crate::model::entity;
+use crate::model::money::Money;
+use crate::model::percentage::Percentage;
 
 #[derive(OpenApi)]
 #[openapi(
     components(schemas(
+        auth_dto::AuthorizeUrlDto,
+        auth_dto::ChangePasswordDto,
+        auth_dto::LocalLoginDto,
+        auth_dto::LocalRegisterDto,
+        auth_dto::LoginCodeDto,
+        auth_dto::RefreshTokenDto,
+        auth_dto::SessionDto,
+        auth_dto::TokenDto,
+        request::ApplySettlementDto,
         request::CreateAccountDto,
         request::CreateCostDto,
         request::CreateDebtorDto,
+        request::CreateExchangeRateDto,
+        request::CreateLedgerDto,
         request::CreatePaymentDto,
+        request::CreatePersonalAccessTokenDto,
+        request::InviteLocalUserDto,
+        request::InviteUserDto,
+        request::SplitMode,
+        request::UpdateCostDto,
+        request::UpdateUserRoleDto,
+        response::AccountBalanceDto,
         response::AccountDto,
+        response::AccountPaymentsDto,
+        response::BalanceDriftDto,
         response::CalculatedDebtDto,
         response::CostDto,
+        response::CostPageDto,
         response::DebtDto,
+        response::ExchangeRateDto,
+        response::MeDto,
+        response::LedgerDto,
+        response::LocalInviteDto,
         response::PaymentDto,
+        response::PaymentPageDto,
+        response::PersonalAccessTokenDto,
+        response::CreatedPersonalAccessTokenDto,
+        response::IdentityDto,
+        response::UserDto,
+        entity::Role,
+        entity::Scope,
+        Money,
+        Percentage,
     )),
     paths(
         account::create_account,
@@ -30,15 +69,53 @@ use crate::model::dto::{request, response};
         account::get_account,
         account::get_account_tags,
         account::get_all_accounts,
+        account::update_account,
+        balance::rebuild_balances,
         cost::create_cost,
+        cost::create_own_cost,
         cost::delete_cost,
         cost::get_all_costs,
+        cost::get_cost,
         cost::get_current_snapshot,
+        cost::replace_cost,
+        cost::update_cost,
+        exchange_rate::create_exchange_rate,
+        exchange_rate::delete_exchange_rate,
+        exchange_rate::get_all_exchange_rates,
+        ledger::create_ledger,
+        ledger::delete_ledger,
+        ledger::get_all_ledgers,
+        ledger::get_ledger,
+        ledger::get_ledger_tags,
+        ledger::update_ledger,
         payment::create_payment,
+        payment::create_own_payment,
         payment::delete_payment,
+        payment::get_account_payments,
         payment::get_all_payment,
-        auth::discord_auth,
+        payment::get_payment,
+        payment::update_payment,
+        personal_access_token::create_token,
+        personal_access_token::delete_token,
+        personal_access_token::get_all_tokens,
+        settlement::apply_settlement,
+        settlement::get_settlement,
+        user::delete_user,
+        user::get_all_users,
+        user::get_me,
+        user::invite_local_user,
+        user::invite_user,
+        user::update_user_role,
+        auth::delete_session,
+        auth::exchange_login_code,
+        auth::get_sessions,
+        auth::link_provider,
+        auth::local::change_password,
+        auth::local::login,
+        auth::local::register,
         auth::logout,
+        auth::provider_auth,
+        auth::refresh,
     ),
     modifiers(&SecurityAddon),
 )]
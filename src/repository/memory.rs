@@ -0,0 +1,1420 @@
+use std::sync::{Mutex, MutexGuard};
+
+use axum::async_trait;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Role, Scope};
+use crate::repository::{
+    both_directions, conflict, ensure_versions, AccountRepository, BalanceRepository, CostFilter,
+    CostRepository, Credential, CredentialRepository, DebtRepository, ExchangeRateRepository,
+    LedgerBalances, LedgerRepository, LoginCode, Movement, NewSession, OAuthRepository, OAuthState,
+    Page, PaymentFilter, PaymentRepository, PersonalAccessTokenRepository, SessionRepository,
+    TokenUser, UserRepository,
+};
+
+/// Everything in one place, so deletes can cascade like the foreign keys of the database do
+#[derive(Default)]
+pub struct Store(Mutex<Data>);
+
+#[derive(Default)]
+struct Data {
+    ledgers: Vec<entity::Ledger>,
+    accounts: Vec<entity::Account>,
+    costs: Vec<entity::Cost>,
+    debts: Vec<entity::Debt>,
+    payments: Vec<entity::Payment>,
+    balances: Vec<entity::Balance>,
+    exchange_rates: Vec<entity::ExchangeRate>,
+    users: Vec<entity::User>,
+    identities: Vec<entity::Identity>,
+    sessions: Vec<StoredSession>,
+    oauth_states: Vec<(OAuthState, chrono::NaiveDateTime)>,
+    login_codes: Vec<StoredLoginCode>,
+    personal_access_tokens: Vec<StoredToken>,
+    credentials: Vec<StoredCredential>,
+}
+
+struct StoredSession {
+    session: entity::Session,
+    user_id: Uuid,
+    username: String,
+    token_hash: String,
+    expires_at: chrono::NaiveDateTime,
+    refresh_token_hash: String,
+    previous_refresh_token_hash: Option<String>,
+}
+
+struct StoredLoginCode {
+    code_hash: String,
+    login: LoginCode,
+    expires_at: chrono::NaiveDateTime,
+}
+
+struct StoredToken {
+    token: entity::PersonalAccessToken,
+    user_id: Uuid,
+    token_hash: String,
+}
+
+struct StoredCredential {
+    username: String,
+    user_id: Uuid,
+    password_hash: Option<String>,
+    registration_code_hash: Option<String>,
+    registration_expires_at: Option<chrono::NaiveDateTime>,
+    failed_attempts: i32,
+    locked_until: Option<chrono::NaiveDateTime>,
+}
+
+impl Store {
+    fn data(&self) -> Result<MutexGuard<'_, Data>, AppError> {
+        self.0
+            .lock()
+            .map_err(|_| AppError::InternalServer("in-memory store is poisoned".to_string()))
+    }
+}
+
+fn now() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+fn in_range(
+    date: chrono::NaiveDate,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
+) -> bool {
+    start_date.is_none_or(|start| start <= date) && end_date.is_none_or(|end| date <= end)
+}
+
+/// Remove the matching entries, `NotFound` if there was none
+fn remove<T>(list: &mut Vec<T>, matches: impl Fn(&T) -> bool) -> Result<(), AppError> {
+    let len = list.len();
+    list.retain(|entry| !matches(entry));
+
+    if list.len() == len {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+impl Data {
+    /// Username of the first identity of the user
+    fn username(&self, user_id: Uuid) -> Option<String> {
+        self.identities
+            .iter()
+            .filter(|i| i.user_id == user_id)
+            .min_by_key(|i| i.creation_date)
+            .and_then(|i| i.username.clone())
+    }
+
+    fn cost(&self, cost_id: Uuid) -> Option<&entity::Cost> {
+        self.costs.iter().find(|c| c.id == cost_id)
+    }
+
+    /// Increment the version of the balances of the ledger, `Conflict` if they changed since the
+    /// given one
+    ///
+    /// Called after everything else is checked, as there is no transaction to roll it back
+    fn next_version(&mut self, ledger_id: Uuid, balance_version: i64) -> Result<(), AppError> {
+        let ledger = self
+            .ledgers
+            .iter_mut()
+            .find(|l| l.id == ledger_id)
+            .ok_or(AppError::NotFound)?;
+
+        if ledger.balance_version != balance_version {
+            return Err(conflict(ledger_id));
+        }
+        ledger.balance_version += 1;
+
+        Ok(())
+    }
+
+    /// Increment the versions of all ledgers and replace their given balances, see
+    /// `ensure_versions`
+    fn next_versions(&mut self, ledgers: &[LedgerBalances]) -> Result<(), AppError> {
+        let current = self
+            .ledgers
+            .iter()
+            .map(|l| (l.id, l.balance_version))
+            .collect::<Vec<_>>();
+        ensure_versions(&current, ledgers)?;
+
+        for ledger in &mut self.ledgers {
+            ledger.balance_version += 1;
+        }
+        for ledger in ledgers {
+            if let Some(balances) = &ledger.balances {
+                self.replace_balances(ledger.ledger_id, balances);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace all balances of the ledger, returns the replaced ones
+    fn replace_balances(
+        &mut self,
+        ledger_id: Uuid,
+        balances: &[entity::Balance],
+    ) -> Vec<entity::Balance> {
+        let (replaced, kept) = std::mem::take(&mut self.balances)
+            .into_iter()
+            .partition(|b| b.ledger_id == ledger_id);
+
+        self.balances = kept;
+        self.balances
+            .extend(balances.iter().map(|b| entity::Balance {
+                ledger_id,
+                ..b.clone()
+            }));
+
+        replaced
+    }
+
+    /// Add the changes to the balances of both directions
+    fn apply_changes(&mut self, changes: &[entity::Balance]) {
+        for side in both_directions(changes) {
+            match self.balances.iter_mut().find(|b| {
+                b.payer_account_id == side.payer_account_id
+                    && b.lender_account_id == side.lender_account_id
+            }) {
+                Some(balance) => balance.amount += side.amount,
+                None => self.balances.push(side),
+            }
+        }
+    }
+
+    /// Debts, payments and balances referencing no account or cost anymore are removed, like on
+    /// cascade
+    fn remove_orphans(&mut self) {
+        let accounts = &self.accounts;
+        let exists = |account_id: Uuid| accounts.iter().any(|a| a.id == account_id);
+
+        self.costs
+            .retain(|c| exists(c.account_id) && self.ledgers.iter().any(|l| l.id == c.ledger_id));
+        self.payments.retain(|p| {
+            exists(p.payer_account_id)
+                && exists(p.lender_account_id)
+                && self.ledgers.iter().any(|l| l.id == p.ledger_id)
+        });
+
+        self.balances.retain(|b| {
+            exists(b.payer_account_id)
+                && exists(b.lender_account_id)
+                && self.ledgers.iter().any(|l| l.id == b.ledger_id)
+        });
+
+        let costs = &self.costs;
+        self.debts
+            .retain(|d| exists(d.debtor_account_id) && costs.iter().any(|c| c.id == d.cost_id));
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for Store {
+    async fn get(&self, ledger_id: Uuid) -> Result<entity::Ledger, AppError> {
+        self.data()?
+            .ledgers
+            .iter()
+            .find(|l| l.id == ledger_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::Ledger>, AppError> {
+        Ok(self.data()?.ledgers.clone())
+    }
+
+    async fn create(&self, ledger: &entity::Ledger) -> Result<(), AppError> {
+        self.data()?.ledgers.push(ledger.clone());
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        ledger: &entity::Ledger,
+        balances: Option<&[entity::Balance]>,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        data.next_version(ledger.id, ledger.balance_version)?;
+
+        let current = data
+            .ledgers
+            .iter_mut()
+            .find(|l| l.id == ledger.id)
+            .ok_or(AppError::NotFound)?;
+        *current = entity::Ledger {
+            balance_version: current.balance_version,
+            ..ledger.clone()
+        };
+
+        if let Some(balances) = balances {
+            data.replace_balances(ledger.id, balances);
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, ledger_id: Uuid) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        remove(&mut data.ledgers, |l| l.id == ledger_id)?;
+        data.accounts.retain(|a| a.ledger_id != ledger_id);
+        data.remove_orphans();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccountRepository for Store {
+    async fn get(&self, ledger_id: Uuid, account_id: Uuid) -> Result<entity::Account, AppError> {
+        self.data()?
+            .accounts
+            .iter()
+            .find(|a| a.id == account_id && a.ledger_id == ledger_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(self
+            .data()?
+            .accounts
+            .iter()
+            .filter(|a| a.ledger_id == ledger_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(self
+            .data()?
+            .accounts
+            .iter()
+            .filter(|a| a.user_id == Some(user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn find_of_user(
+        &self,
+        ledger_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<entity::Account>, AppError> {
+        Ok(self
+            .data()?
+            .accounts
+            .iter()
+            .find(|a| a.ledger_id == ledger_id && a.user_id == Some(user_id))
+            .cloned())
+    }
+
+    async fn find_in_ledger(
+        &self,
+        ledger_id: Uuid,
+        account_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        Ok(self
+            .data()?
+            .accounts
+            .iter()
+            .filter(|a| a.ledger_id == ledger_id && account_ids.contains(&a.id))
+            .map(|a| a.id)
+            .collect())
+    }
+
+    async fn create(&self, account: &entity::Account) -> Result<(), AppError> {
+        let mut data = self.data()?;
+
+        if !data.ledgers.iter().any(|l| l.id == account.ledger_id) {
+            return Err(AppError::Service(
+                "referenced ledger_id does not exist".to_string(),
+            ));
+        }
+
+        if account.user_id.is_some()
+            && data
+                .accounts
+                .iter()
+                .any(|a| a.ledger_id == account.ledger_id && a.user_id == account.user_id)
+        {
+            return Err(AppError::Service(
+                "account_of_user_in_ledger already exists".to_string(),
+            ));
+        }
+
+        data.accounts.push(account.clone());
+
+        Ok(())
+    }
+
+    async fn update(&self, account: &entity::Account) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        let current = data
+            .accounts
+            .iter_mut()
+            .find(|a| a.id == account.id && a.ledger_id == account.ledger_id)
+            .ok_or(AppError::NotFound)?;
+
+        *current = account.clone();
+
+        Ok(())
+    }
+
+    async fn delete(&self, ledger_id: Uuid, account_id: Uuid) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        remove(&mut data.accounts, |a| {
+            a.id == account_id && a.ledger_id == ledger_id
+        })?;
+        data.remove_orphans();
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CostRepository for Store {
+    async fn get(&self, ledger_id: Uuid, cost_id: Uuid) -> Result<entity::Cost, AppError> {
+        self.data()?
+            .cost(cost_id)
+            .filter(|c| c.ledger_id == ledger_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_for_account(
+        &self,
+        ledger_id: Uuid,
+        account_id: Uuid,
+    ) -> Result<Vec<entity::Cost>, AppError> {
+        Ok(self
+            .data()?
+            .costs
+            .iter()
+            .filter(|c| c.ledger_id == ledger_id && c.account_id == account_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all_with_debts(
+        &self,
+        ledger_id: Uuid,
+        filter: &CostFilter,
+        page: &Page,
+    ) -> Result<Vec<(entity::Cost, entity::Debt)>, AppError> {
+        let data = self.data()?;
+
+        let has_debt = |cost: &entity::Cost| {
+            data.debts.iter().any(|d| {
+                d.cost_id == cost.id
+                    && filter
+                        .debtor_account_id
+                        .is_none_or(|debtor| d.debtor_account_id == debtor)
+            })
+        };
+        let has_tags = |cost: &entity::Cost| {
+            let tags = cost.tags.as_deref().unwrap_or_default();
+            filter.tags.iter().all(|tag| tags.contains(tag))
+        };
+        let has_description = |cost: &entity::Cost| {
+            filter.description.as_ref().is_none_or(|part| {
+                cost.description
+                    .as_ref()
+                    .is_some_and(|d| d.to_lowercase().contains(&part.to_lowercase()))
+            })
+        };
+
+        let mut costs = data
+            .costs
+            .iter()
+            .filter(|c| {
+                c.ledger_id == ledger_id
+                    && in_range(c.event_date, filter.start_date, filter.end_date)
+                    && filter
+                        .payer_account_id
+                        .is_none_or(|payer| c.account_id == payer)
+                    && filter.min_amount.is_none_or(|min| min <= c.amount)
+                    && filter.max_amount.is_none_or(|max| c.amount <= max)
+                    && has_debt(c)
+                    && has_tags(c)
+                    && has_description(c)
+            })
+            .map(|c| ((page.position(c.amount, c.event_date), c.id), c))
+            .filter(|(key, _)| page.after.is_none_or(|after| *key > after))
+            .collect::<Vec<_>>();
+        costs.sort_by_key(|(key, _)| *key);
+        costs.truncate(usize::try_from(page.limit).unwrap_or(0));
+
+        Ok(costs
+            .into_iter()
+            .flat_map(|(_, cost)| {
+                data.debts
+                    .iter()
+                    .filter(|d| d.cost_id == cost.id)
+                    .map(|debt| (cost.clone(), debt.clone()))
+            })
+            .collect())
+    }
+
+    async fn get_tags(&self, ledger_id: Uuid) -> Result<Vec<String>, AppError> {
+        let mut tags = self
+            .data()?
+            .costs
+            .iter()
+            .filter(|c| c.ledger_id == ledger_id)
+            .filter_map(|c| c.tags.clone())
+            .flatten()
+            .collect::<Vec<_>>();
+        tags.sort();
+        tags.dedup();
+
+        Ok(tags)
+    }
+
+    async fn create(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        data.next_version(cost.ledger_id, balance_version)?;
+        data.costs.push(cost.clone());
+        data.debts.extend_from_slice(debts);
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        if !data
+            .costs
+            .iter()
+            .any(|c| c.id == cost.id && c.ledger_id == cost.ledger_id)
+        {
+            return Err(AppError::NotFound);
+        }
+        data.next_version(cost.ledger_id, balance_version)?;
+
+        let current = data
+            .costs
+            .iter_mut()
+            .find(|c| c.id == cost.id && c.ledger_id == cost.ledger_id)
+            .ok_or(AppError::NotFound)?;
+
+        // the payer of a cost can not be changed
+        *current = entity::Cost {
+            account_id: current.account_id,
+            ..cost.clone()
+        };
+
+        data.debts.retain(|d| d.cost_id != cost.id);
+        data.debts.extend_from_slice(debts);
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        cost_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        if !data
+            .costs
+            .iter()
+            .any(|c| c.id == cost_id && c.ledger_id == ledger_id)
+        {
+            return Err(AppError::NotFound);
+        }
+        data.next_version(ledger_id, balance_version)?;
+
+        remove(&mut data.costs, |c| {
+            c.id == cost_id && c.ledger_id == ledger_id
+        })?;
+        data.remove_orphans();
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DebtRepository for Store {
+    async fn get_of_cost(&self, cost_id: Uuid) -> Result<Vec<entity::Debt>, AppError> {
+        Ok(self
+            .data()?
+            .debts
+            .iter()
+            .filter(|d| d.cost_id == cost_id)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl BalanceRepository for Store {
+    async fn get_movements(
+        &self,
+        ledger_id: Uuid,
+        _currency: &str,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Movement>, AppError> {
+        let data = self.data()?;
+
+        // nothing is summed up, every debt and payment is its own movement
+        let movement = |creditor, debtor, amount, currency: &String, event_date| Movement {
+            creditor_account_id: creditor,
+            debtor_account_id: debtor,
+            currency: currency.clone(),
+            event_date,
+            amount,
+            count: 1,
+        };
+
+        let debts = data.debts.iter().filter_map(|debt| {
+            let cost = data.cost(debt.cost_id)?;
+            (cost.ledger_id == ledger_id && in_range(cost.event_date, start_date, end_date)).then(
+                || {
+                    movement(
+                        cost.account_id,
+                        debt.debtor_account_id,
+                        debt.amount,
+                        &cost.currency,
+                        cost.event_date,
+                    )
+                },
+            )
+        });
+        let payments = data
+            .payments
+            .iter()
+            .filter(|p| p.ledger_id == ledger_id && in_range(p.event_date, start_date, end_date))
+            .map(|p| {
+                movement(
+                    p.payer_account_id,
+                    p.lender_account_id,
+                    p.amount,
+                    &p.currency,
+                    p.event_date,
+                )
+            });
+
+        Ok(debts.chain(payments).collect())
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Balance>, AppError> {
+        Ok(self
+            .data()?
+            .balances
+            .iter()
+            .filter(|b| b.ledger_id == ledger_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn replace_all(
+        &self,
+        ledger_id: Uuid,
+        balance_version: i64,
+        balances: &[entity::Balance],
+    ) -> Result<Vec<entity::Balance>, AppError> {
+        let mut data = self.data()?;
+        data.next_version(ledger_id, balance_version)?;
+
+        Ok(data.replace_balances(ledger_id, balances))
+    }
+}
+
+#[async_trait]
+impl PaymentRepository for Store {
+    async fn get(&self, ledger_id: Uuid, payment_id: Uuid) -> Result<entity::Payment, AppError> {
+        self.data()?
+            .payments
+            .iter()
+            .find(|p| p.id == payment_id && p.ledger_id == ledger_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_all(
+        &self,
+        ledger_id: Uuid,
+        filter: &PaymentFilter,
+        page: &Page,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        let mut payments = self
+            .data()?
+            .payments
+            .iter()
+            .filter(|p| {
+                p.ledger_id == ledger_id
+                    && in_range(p.event_date, filter.start_date, filter.end_date)
+                    && filter
+                        .payer_account_id
+                        .is_none_or(|payer| p.payer_account_id == payer)
+                    && filter
+                        .lender_account_id
+                        .is_none_or(|lender| p.lender_account_id == lender)
+                    && filter.min_amount.is_none_or(|min| min <= p.amount)
+                    && filter.max_amount.is_none_or(|max| p.amount <= max)
+            })
+            .map(|p| ((page.position(p.amount, p.event_date), p.id), p.clone()))
+            .filter(|(key, _)| page.after.is_none_or(|after| *key > after))
+            .collect::<Vec<_>>();
+        payments.sort_by_key(|(key, _)| *key);
+        payments.truncate(usize::try_from(page.limit).unwrap_or(0));
+
+        Ok(payments.into_iter().map(|(_, payment)| payment).collect())
+    }
+
+    async fn get_for_account(
+        &self,
+        payer_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(self
+            .data()?
+            .payments
+            .iter()
+            .filter(|p| {
+                p.payer_account_id == payer_account_id
+                    && in_range(p.event_date, start_date, end_date)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_of_account(
+        &self,
+        lender_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(self
+            .data()?
+            .payments
+            .iter()
+            .filter(|p| {
+                p.lender_account_id == lender_account_id
+                    && in_range(p.event_date, start_date, end_date)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn create(
+        &self,
+        ledger_id: Uuid,
+        payments: &[entity::Payment],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        data.next_version(ledger_id, balance_version)?;
+        data.payments.extend_from_slice(payments);
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        payment: &entity::Payment,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        if !data
+            .payments
+            .iter()
+            .any(|p| p.id == payment.id && p.ledger_id == payment.ledger_id)
+        {
+            return Err(AppError::NotFound);
+        }
+        data.next_version(payment.ledger_id, balance_version)?;
+
+        let current = data
+            .payments
+            .iter_mut()
+            .find(|p| p.id == payment.id && p.ledger_id == payment.ledger_id)
+            .ok_or(AppError::NotFound)?;
+
+        *current = entity::Payment {
+            payer_account_id: current.payer_account_id,
+            ..payment.clone()
+        };
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        payment_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        if !data
+            .payments
+            .iter()
+            .any(|p| p.id == payment_id && p.ledger_id == ledger_id)
+        {
+            return Err(AppError::NotFound);
+        }
+        data.next_version(ledger_id, balance_version)?;
+
+        remove(&mut data.payments, |p| {
+            p.id == payment_id && p.ledger_id == ledger_id
+        })?;
+        data.apply_changes(changes);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExchangeRateRepository for Store {
+    async fn get(&self, exchange_rate_id: Uuid) -> Result<entity::ExchangeRate, AppError> {
+        self.data()?
+            .exchange_rates
+            .iter()
+            .find(|r| r.id == exchange_rate_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::ExchangeRate>, AppError> {
+        let mut rates = self.data()?.exchange_rates.clone();
+        rates.sort_by_key(|rate| std::cmp::Reverse(rate.date));
+
+        Ok(rates)
+    }
+
+    async fn create(
+        &self,
+        exchange_rate: &entity::ExchangeRate,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+
+        if data.exchange_rates.iter().any(|r| {
+            r.date == exchange_rate.date
+                && r.from_currency == exchange_rate.from_currency
+                && r.to_currency == exchange_rate.to_currency
+        }) {
+            return Err(AppError::Service(
+                "exchange_rate_per_day already exists".to_string(),
+            ));
+        }
+
+        data.next_versions(ledgers)?;
+        data.exchange_rates.push(exchange_rate.clone());
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        exchange_rate_id: Uuid,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        if !data.exchange_rates.iter().any(|r| r.id == exchange_rate_id) {
+            return Err(AppError::NotFound);
+        }
+
+        data.next_versions(ledgers)?;
+        remove(&mut data.exchange_rates, |r| r.id == exchange_rate_id)
+    }
+}
+
+#[async_trait]
+impl UserRepository for Store {
+    async fn get(&self, user_id: Uuid) -> Result<entity::User, AppError> {
+        self.data()?
+            .users
+            .iter()
+            .find(|u| u.id == user_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::User>, AppError> {
+        Ok(self.data()?.users.clone())
+    }
+
+    async fn create(&self, provider: &str, subject: &str, role: Role) -> Result<Uuid, AppError> {
+        let mut data = self.data()?;
+        let provider = provider.to_lowercase();
+
+        if data
+            .identities
+            .iter()
+            .any(|i| i.provider == provider && i.subject == subject)
+        {
+            return Err(AppError::Service("identity already exists".to_string()));
+        }
+
+        let user = entity::User {
+            id: Uuid::new_v4(),
+            role,
+            creation_date: chrono::Utc::now().naive_utc(),
+        };
+
+        data.identities.push(entity::Identity {
+            provider,
+            subject: subject.to_string(),
+            user_id: user.id,
+            username: None,
+            avatar: None,
+            creation_date: user.creation_date,
+        });
+        data.users.push(user.clone());
+
+        Ok(user.id)
+    }
+
+    async fn update_role(&self, user_id: Uuid, role: Role) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        let user = data
+            .users
+            .iter_mut()
+            .find(|u| u.id == user_id)
+            .ok_or(AppError::NotFound)?;
+
+        user.role = role;
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        remove(&mut data.users, |u| u.id == user_id)?;
+        data.identities.retain(|i| i.user_id != user_id);
+        data.sessions.retain(|s| s.user_id != user_id);
+        data.oauth_states
+            .retain(|(state, _)| state.link_user_id != Some(user_id));
+        data.login_codes.retain(|c| c.login.user_id != user_id);
+        data.personal_access_tokens.retain(|t| t.user_id != user_id);
+        data.credentials.retain(|c| c.user_id != user_id);
+
+        for account in data
+            .accounts
+            .iter_mut()
+            .filter(|a| a.user_id == Some(user_id))
+        {
+            account.user_id = None;
+        }
+
+        Ok(())
+    }
+
+    async fn get_identities(&self, user_id: Uuid) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(self
+            .data()?
+            .identities
+            .iter()
+            .filter(|i| i.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_all_identities(&self) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(self.data()?.identities.clone())
+    }
+
+    async fn find_by_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        Ok(self
+            .data()?
+            .identities
+            .iter()
+            .find(|i| i.provider == provider && i.subject == subject)
+            .map(|i| i.user_id))
+    }
+
+    async fn add_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+
+        if data
+            .identities
+            .iter()
+            .any(|i| i.provider == provider && i.subject == subject)
+        {
+            return Err(AppError::Service("identity already exists".to_string()));
+        }
+        if !data.users.iter().any(|u| u.id == user_id) {
+            return Err(AppError::NotFound);
+        }
+
+        data.identities.push(entity::Identity {
+            provider: provider.to_string(),
+            subject: subject.to_string(),
+            user_id,
+            username: None,
+            avatar: None,
+            creation_date: now(),
+        });
+
+        Ok(())
+    }
+
+    async fn update_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        username: &str,
+        avatar: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+
+        if let Some(identity) = data
+            .identities
+            .iter_mut()
+            .find(|i| i.provider == provider && i.subject == subject)
+        {
+            identity.username = Some(username.to_string());
+            identity.avatar = avatar.map(ToString::to_string);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionRepository for Store {
+    async fn create(
+        &self,
+        session: &NewSession,
+        access_lifetime: chrono::Duration,
+        refresh_lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        data.sessions.push(StoredSession {
+            session: entity::Session {
+                id: Uuid::new_v4(),
+                user_agent: session.user_agent.clone(),
+                ip: session.ip.clone(),
+                creation_date: now,
+                refresh_expires_at: now + refresh_lifetime,
+            },
+            user_id: session.user_id,
+            username: session.username.clone(),
+            token_hash: session.token_hash.clone(),
+            expires_at: now + access_lifetime,
+            refresh_token_hash: session.refresh_token_hash.clone(),
+            previous_refresh_token_hash: None,
+        });
+
+        Ok(())
+    }
+
+    async fn get_user(&self, token_hash: &str) -> Result<Option<TokenUser>, AppError> {
+        let data = self.data()?;
+
+        Ok(data
+            .sessions
+            .iter()
+            .filter(|s| s.token_hash == token_hash && s.expires_at > now())
+            .find_map(|s| {
+                let user = data.users.iter().find(|u| u.id == s.user_id)?;
+
+                Some(TokenUser {
+                    user_id: user.id,
+                    session_id: Some(s.session.id),
+                    username: s.username.clone(),
+                    role: user.role,
+                })
+            }))
+    }
+
+    async fn refresh(
+        &self,
+        refresh_token_hash: &str,
+        token_hash: &str,
+        access_lifetime: chrono::Duration,
+        new_refresh_token_hash: &str,
+    ) -> Result<(), AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        let session = data
+            .sessions
+            .iter_mut()
+            .find(|s| {
+                s.refresh_token_hash == refresh_token_hash && s.session.refresh_expires_at > now
+            })
+            .ok_or(AppError::Forbidden)?;
+
+        session.token_hash = token_hash.to_string();
+        session.expires_at = now + access_lifetime;
+        session.previous_refresh_token_hash = Some(std::mem::replace(
+            &mut session.refresh_token_hash,
+            new_refresh_token_hash.to_string(),
+        ));
+
+        Ok(())
+    }
+
+    async fn delete_reused(&self, refresh_token_hash: &str) -> Result<bool, AppError> {
+        let mut data = self.data()?;
+
+        Ok(remove(&mut data.sessions, |s| {
+            s.previous_refresh_token_hash.as_deref() == Some(refresh_token_hash)
+        })
+        .is_ok())
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Session>, AppError> {
+        let mut sessions = self
+            .data()?
+            .sessions
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .map(|s| s.session.clone())
+            .collect::<Vec<_>>();
+
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.creation_date));
+        Ok(sessions)
+    }
+
+    async fn delete(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        remove(&mut self.data()?.sessions, |s| {
+            s.session.id == session_id && s.user_id == user_id
+        })
+    }
+
+    async fn delete_all_except(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        self.data()?
+            .sessions
+            .retain(|s| s.user_id != user_id || s.session.id == session_id);
+
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let mut data = self.data()?;
+        let len = data.sessions.len();
+        let now = now();
+
+        data.sessions.retain(|s| s.session.refresh_expires_at > now);
+
+        Ok((len - data.sessions.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl OAuthRepository for Store {
+    async fn store_state(
+        &self,
+        state: &OAuthState,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        self.data()?
+            .oauth_states
+            .push((state.clone(), now() + lifetime));
+
+        Ok(())
+    }
+
+    async fn take_state(&self, csrf_token: &str) -> Result<Option<OAuthState>, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        Ok(data
+            .oauth_states
+            .iter()
+            .position(|(state, expires_at)| state.csrf_token == csrf_token && *expires_at > now)
+            .map(|index| data.oauth_states.remove(index).0))
+    }
+
+    async fn store_login_code(
+        &self,
+        code_hash: &str,
+        login: &LoginCode,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        self.data()?.login_codes.push(StoredLoginCode {
+            code_hash: code_hash.to_string(),
+            login: login.clone(),
+            expires_at: now() + lifetime,
+        });
+
+        Ok(())
+    }
+
+    async fn take_login_code(&self, code_hash: &str) -> Result<Option<LoginCode>, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        Ok(data
+            .login_codes
+            .iter()
+            .position(|c| c.code_hash == code_hash && c.expires_at > now)
+            .map(|index| data.login_codes.remove(index).login))
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let mut data = self.data()?;
+        let len = data.oauth_states.len() + data.login_codes.len();
+        let now = now();
+
+        data.oauth_states
+            .retain(|(_, expires_at)| *expires_at > now);
+        data.login_codes.retain(|c| c.expires_at > now);
+
+        Ok((len - data.oauth_states.len() - data.login_codes.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl PersonalAccessTokenRepository for Store {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        lifetime: Option<chrono::Duration>,
+    ) -> Result<entity::PersonalAccessToken, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        if !data.users.iter().any(|u| u.id == user_id) {
+            return Err(AppError::NotFound);
+        }
+
+        let token = entity::PersonalAccessToken {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            scopes: scopes.to_vec(),
+            expires_at: lifetime.map(|lifetime| now + lifetime),
+            last_used_at: None,
+            creation_date: now,
+        };
+
+        data.personal_access_tokens.push(StoredToken {
+            token: token.clone(),
+            user_id,
+            token_hash: token_hash.to_string(),
+        });
+
+        Ok(token)
+    }
+
+    async fn get_all_of_user(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<entity::PersonalAccessToken>, AppError> {
+        let mut tokens = self
+            .data()?
+            .personal_access_tokens
+            .iter()
+            .filter(|t| t.user_id == user_id)
+            .map(|t| t.token.clone())
+            .collect::<Vec<_>>();
+
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.creation_date));
+        Ok(tokens)
+    }
+
+    async fn delete(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+        remove(&mut self.data()?.personal_access_tokens, |t| {
+            t.token.id == token_id && t.user_id == user_id
+        })
+    }
+
+    async fn use_token(
+        &self,
+        token_hash: &str,
+        scope: Scope,
+    ) -> Result<Option<TokenUser>, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        let Some(index) = data.personal_access_tokens.iter().position(|t| {
+            t.token_hash == token_hash
+                && t.token.scopes.contains(&scope)
+                && t.token.expires_at.is_none_or(|expires_at| expires_at > now)
+        }) else {
+            return Ok(None);
+        };
+
+        let user_id = data.personal_access_tokens[index].user_id;
+        let Some(role) = data.users.iter().find(|u| u.id == user_id).map(|u| u.role) else {
+            return Ok(None);
+        };
+        let username = data.username(user_id);
+
+        let stored = &mut data.personal_access_tokens[index];
+        stored.token.last_used_at = Some(now);
+
+        Ok(Some(TokenUser {
+            user_id,
+            session_id: None,
+            username: username.unwrap_or_else(|| stored.token.name.clone()),
+            role,
+        }))
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let mut data = self.data()?;
+        let len = data.personal_access_tokens.len();
+        let now = now();
+
+        data.personal_access_tokens
+            .retain(|t| t.token.expires_at.is_none_or(|expires_at| expires_at > now));
+
+        Ok((len - data.personal_access_tokens.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for Store {
+    async fn invite(
+        &self,
+        provider: &str,
+        username: &str,
+        role: Role,
+        registration_code_hash: &str,
+        registration_lifetime: chrono::Duration,
+    ) -> Result<Uuid, AppError> {
+        let user_id = UserRepository::create(self, provider, username, role).await?;
+
+        self.data()?.credentials.push(StoredCredential {
+            username: username.to_string(),
+            user_id,
+            password_hash: None,
+            registration_code_hash: Some(registration_code_hash.to_string()),
+            registration_expires_at: Some(now() + registration_lifetime),
+            failed_attempts: 0,
+            locked_until: None,
+        });
+
+        Ok(user_id)
+    }
+
+    async fn register(
+        &self,
+        username: &str,
+        registration_code_hash: &str,
+        password_hash: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        let Some(credential) = data.credentials.iter_mut().find(|c| {
+            c.username == username
+                && c.registration_code_hash.as_deref() == Some(registration_code_hash)
+                && c.registration_expires_at
+                    .is_some_and(|expires_at| expires_at > now)
+        }) else {
+            return Ok(None);
+        };
+
+        credential.password_hash = Some(password_hash.to_string());
+        credential.registration_code_hash = None;
+        credential.registration_expires_at = None;
+
+        Ok(Some(credential.user_id))
+    }
+
+    async fn count_attempt(
+        &self,
+        username: &str,
+        max_failed_attempts: i32,
+        lockout: chrono::Duration,
+    ) -> Result<Option<Credential>, AppError> {
+        let mut data = self.data()?;
+        let now = now();
+
+        let Some(credential) = data.credentials.iter_mut().find(|c| {
+            c.username == username
+                && c.password_hash.is_some()
+                && c.locked_until
+                    .is_none_or(|locked_until| locked_until <= now)
+        }) else {
+            return Ok(None);
+        };
+
+        credential.failed_attempts += 1;
+        if credential.failed_attempts >= max_failed_attempts {
+            credential.failed_attempts = 0;
+            credential.locked_until = Some(now + lockout);
+        }
+
+        Ok(Some(Credential {
+            user_id: credential.user_id,
+            password_hash: credential.password_hash.clone().unwrap_or_default(),
+            locked: credential
+                .locked_until
+                .is_some_and(|locked_until| locked_until > now),
+        }))
+    }
+
+    async fn reset_attempts(&self, username: &str) -> Result<(), AppError> {
+        if let Some(credential) = self
+            .data()?
+            .credentials
+            .iter_mut()
+            .find(|c| c.username == username)
+        {
+            credential.failed_attempts = 0;
+            credential.locked_until = None;
+        }
+
+        Ok(())
+    }
+
+    async fn get_username(&self, user_id: Uuid) -> Result<Option<String>, AppError> {
+        Ok(self
+            .data()?
+            .credentials
+            .iter()
+            .find(|c| c.user_id == user_id && c.password_hash.is_some())
+            .map(|c| c.username.clone()))
+    }
+
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), AppError> {
+        if let Some(credential) = self
+            .data()?
+            .credentials
+            .iter_mut()
+            .find(|c| c.user_id == user_id)
+        {
+            credential.password_hash = Some(password_hash.to_string());
+        }
+
+        Ok(())
+    }
+}
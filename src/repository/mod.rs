@@ -0,0 +1,723 @@
+//! Storage of ledgers, accounts, costs, debts, payments, exchange rates, users and their logins
+//!
+//! Services only use these traits, so the domain logic can be tested against an in-memory storage
+//! instead of a database
+
+use std::sync::Arc;
+
+use axum::async_trait;
+use sqlx::{PgPool, SqlitePool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Role, Scope};
+
+#[cfg(test)]
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+#[async_trait]
+pub trait LedgerRepository: Send + Sync {
+    async fn get(&self, ledger_id: Uuid) -> Result<entity::Ledger, AppError>;
+
+    async fn get_all(&self) -> Result<Vec<entity::Ledger>, AppError>;
+
+    async fn create(&self, ledger: &entity::Ledger) -> Result<(), AppError>;
+
+    /// `NotFound` if the ledger does not exist, `Conflict` if its balances changed since
+    /// `balance_version`
+    ///
+    /// Given balances replace the stored ones together with the ledger, e.g. in a new base currency
+    async fn update(
+        &self,
+        ledger: &entity::Ledger,
+        balances: Option<&[entity::Balance]>,
+    ) -> Result<(), AppError>;
+
+    /// Everything in the ledger is removed with it
+    async fn delete(&self, ledger_id: Uuid) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait AccountRepository: Send + Sync {
+    async fn get(&self, ledger_id: Uuid, account_id: Uuid) -> Result<entity::Account, AppError>;
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Account>, AppError>;
+
+    /// All accounts linked to the user, over all ledgers
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Account>, AppError>;
+
+    /// A user has at most one account per ledger
+    async fn find_of_user(
+        &self,
+        ledger_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<entity::Account>, AppError>;
+
+    /// The ids of the given accounts which exist in the ledger
+    async fn find_in_ledger(
+        &self,
+        ledger_id: Uuid,
+        account_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError>;
+
+    async fn create(&self, account: &entity::Account) -> Result<(), AppError>;
+
+    /// `NotFound` if the account does not exist in its ledger
+    async fn update(&self, account: &entity::Account) -> Result<(), AppError>;
+
+    /// Costs, debts and payments of the account are removed with it
+    async fn delete(&self, ledger_id: Uuid, account_id: Uuid) -> Result<(), AppError>;
+}
+
+/// Every given filter has to match
+#[derive(Debug, Default, Clone)]
+pub struct CostFilter {
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+    pub payer_account_id: Option<Uuid>,
+    /// costs with a debt of the account
+    pub debtor_account_id: Option<Uuid>,
+    /// costs with all of the tags
+    pub tags: Vec<String>,
+    /// amount of the cost in its own currency
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    /// part of the description, ignoring the case
+    pub description: Option<String>,
+}
+
+/// Part of a listing that is sorted by date or amount, entries with the same one by their id
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub by_amount: bool,
+    pub descending: bool,
+    /// position and id of the last entry of the previous page
+    pub after: Option<(i64, Uuid)>,
+    pub limit: i64,
+}
+
+impl Page {
+    /// Ascending position of an entry, dates are counted in days since 0001-01-01 (starting at 1)
+    pub fn position(&self, amount: i64, date: chrono::NaiveDate) -> i64 {
+        use chrono::Datelike;
+
+        let key = if self.by_amount {
+            amount
+        } else {
+            i64::from(date.num_days_from_ce())
+        };
+
+        if self.descending {
+            -key
+        } else {
+            key
+        }
+    }
+}
+
+#[async_trait]
+pub trait CostRepository: Send + Sync {
+    async fn get(&self, ledger_id: Uuid, cost_id: Uuid) -> Result<entity::Cost, AppError>;
+
+    /// Costs payed by the account
+    async fn get_for_account(
+        &self,
+        ledger_id: Uuid,
+        account_id: Uuid,
+    ) -> Result<Vec<entity::Cost>, AppError>;
+
+    /// Every cost of the page once per debt, in the order of the page
+    ///
+    /// Costs without debts are left out
+    async fn get_all_with_debts(
+        &self,
+        ledger_id: Uuid,
+        filter: &CostFilter,
+        page: &Page,
+    ) -> Result<Vec<(entity::Cost, entity::Debt)>, AppError>;
+
+    /// All distinct tags used by costs of the ledger
+    async fn get_tags(&self, ledger_id: Uuid) -> Result<Vec<String>, AppError>;
+
+    /// The cost is only stored together with its debts and the changes of the balances
+    async fn create(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+
+    /// Replace the cost and all of its debts together, `NotFound` if the cost does not exist
+    async fn update(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        cost_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait DebtRepository: Send + Sync {
+    async fn get_of_cost(&self, cost_id: Uuid) -> Result<Vec<entity::Debt>, AppError>;
+}
+
+/// Debts and payments from the debtor to the creditor, summed up as far as possible
+///
+/// Amounts in other currencies are converted one by one with the rate of their date, so they are
+/// only summed up if they are the same amount on the same date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movement {
+    /// payer of the cost or of the payment
+    pub creditor_account_id: Uuid,
+    /// debtor of the cost or lender of the payment
+    pub debtor_account_id: Uuid,
+    pub currency: String,
+    /// any date of the amounts, if they are in the requested currency
+    pub event_date: chrono::NaiveDate,
+    pub amount: i64,
+    /// how often the amount occurs, always 1 for the requested currency
+    pub count: i64,
+}
+
+/// Changes of balances in both directions, with the same pairs summed up
+///
+/// Writes of costs and payments pass the changes in one direction only, as the other direction
+/// always changes by the negated amount. The pairs are sorted, so concurrent writes lock their rows
+/// in the same order.
+pub fn both_directions(changes: &[entity::Balance]) -> Vec<entity::Balance> {
+    let mut sides: Vec<entity::Balance> = Vec::new();
+
+    // debts of accounts to themselves are no balance
+    for change in changes
+        .iter()
+        .filter(|c| c.payer_account_id != c.lender_account_id)
+    {
+        for (payer_account_id, lender_account_id, amount) in [
+            (
+                change.payer_account_id,
+                change.lender_account_id,
+                change.amount,
+            ),
+            (
+                change.lender_account_id,
+                change.payer_account_id,
+                -change.amount,
+            ),
+        ] {
+            match sides.iter_mut().find(|side| {
+                side.payer_account_id == payer_account_id
+                    && side.lender_account_id == lender_account_id
+            }) {
+                Some(side) => side.amount += amount,
+                None => sides.push(entity::Balance {
+                    ledger_id: change.ledger_id,
+                    payer_account_id,
+                    lender_account_id,
+                    amount,
+                }),
+            }
+        }
+    }
+
+    sides.sort_by_key(|side| (side.payer_account_id, side.lender_account_id));
+    sides
+}
+
+/// Balances of a ledger calculated with other exchange rates, from a version of the stored ones
+#[derive(Debug, Clone)]
+pub struct LedgerBalances {
+    pub ledger_id: Uuid,
+    pub balance_version: i64,
+    /// `None` keeps the stored balances, e.g. if the rates can not convert all amounts of the ledger
+    pub balances: Option<Vec<entity::Balance>>,
+}
+
+/// The write was calculated from balances that another write changed in the meantime
+pub fn conflict(ledger_id: Uuid) -> AppError {
+    AppError::Conflict(format!(
+        "balances of ledger {ledger_id} changed concurrently"
+    ))
+}
+
+/// `Conflict` unless the ledgers are exactly the current ones, with their current versions
+///
+/// Exchange rates apply to every ledger, so their writes have to recalculate all of them
+pub fn ensure_versions(
+    current: &[(Uuid, i64)],
+    ledgers: &[LedgerBalances],
+) -> Result<(), AppError> {
+    if let Some((ledger_id, _)) = current.iter().find(|(ledger_id, version)| {
+        !ledgers
+            .iter()
+            .any(|l| l.ledger_id == *ledger_id && l.balance_version == *version)
+    }) {
+        return Err(conflict(*ledger_id));
+    }
+
+    if let Some(ledger) = ledgers.iter().find(|l| {
+        !current
+            .iter()
+            .any(|(ledger_id, _)| *ledger_id == l.ledger_id)
+    }) {
+        return Err(conflict(ledger.ledger_id));
+    }
+
+    Ok(())
+}
+
+/// Writes of the balances pass the `balance_version` of the ledger their changes were calculated
+/// from. They increment it together with the balances, or fail with `Conflict` if another write
+/// came first, so the service can calculate the changes again.
+#[async_trait]
+pub trait BalanceRepository: Send + Sync {
+    /// Includes debts of accounts to themselves, e.g. their share of their own costs
+    async fn get_movements(
+        &self,
+        ledger_id: Uuid,
+        currency: &str,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Movement>, AppError>;
+
+    /// Stored balances of the ledger, every pair in both directions
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Balance>, AppError>;
+
+    /// Replace all stored balances of the ledger together, returns the replaced ones
+    async fn replace_all(
+        &self,
+        ledger_id: Uuid,
+        balance_version: i64,
+        balances: &[entity::Balance],
+    ) -> Result<Vec<entity::Balance>, AppError>;
+}
+
+/// Every given filter has to match
+#[derive(Debug, Default, Clone)]
+pub struct PaymentFilter {
+    pub start_date: Option<chrono::NaiveDate>,
+    pub end_date: Option<chrono::NaiveDate>,
+    pub payer_account_id: Option<Uuid>,
+    pub lender_account_id: Option<Uuid>,
+    /// amount of the payment in its own currency
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+}
+
+#[async_trait]
+pub trait PaymentRepository: Send + Sync {
+    async fn get(&self, ledger_id: Uuid, payment_id: Uuid) -> Result<entity::Payment, AppError>;
+
+    /// One page of the payments that match the filter
+    async fn get_all(
+        &self,
+        ledger_id: Uuid,
+        filter: &PaymentFilter,
+        page: &Page,
+    ) -> Result<Vec<entity::Payment>, AppError>;
+
+    /// Payments payed by the account
+    async fn get_for_account(
+        &self,
+        payer_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError>;
+
+    /// Payments received by the account
+    async fn get_of_account(
+        &self,
+        lender_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError>;
+
+    /// All payments of the ledger are stored together with the changes of the balances or none
+    async fn create(
+        &self,
+        ledger_id: Uuid,
+        payments: &[entity::Payment],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+
+    /// `NotFound` if the payment does not exist in its ledger, the payer can not be changed
+    async fn update(
+        &self,
+        payment: &entity::Payment,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        payment_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait ExchangeRateRepository: Send + Sync {
+    async fn get(&self, exchange_rate_id: Uuid) -> Result<entity::ExchangeRate, AppError>;
+
+    /// Sorted by date, newest first
+    async fn get_all(&self) -> Result<Vec<entity::ExchangeRate>, AppError>;
+
+    /// Only one rate per day and currency pair can exist
+    ///
+    /// Stored together with the balances of every ledger, see [`ensure_versions`]
+    async fn create(
+        &self,
+        exchange_rate: &entity::ExchangeRate,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError>;
+
+    /// Deleted together with the balances of every ledger, see [`ensure_versions`]
+    async fn delete(
+        &self,
+        exchange_rate_id: Uuid,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn get(&self, user_id: Uuid) -> Result<entity::User, AppError>;
+
+    /// Oldest first
+    async fn get_all(&self) -> Result<Vec<entity::User>, AppError>;
+
+    /// Create the user together with its first identity, returns the id of the user
+    async fn create(&self, provider: &str, subject: &str, role: Role) -> Result<Uuid, AppError>;
+
+    /// `NotFound` if the user does not exist
+    async fn update_role(&self, user_id: Uuid, role: Role) -> Result<(), AppError>;
+
+    /// Identities, sessions and tokens of the user are removed with it
+    async fn delete(&self, user_id: Uuid) -> Result<(), AppError>;
+
+    async fn get_identities(&self, user_id: Uuid) -> Result<Vec<entity::Identity>, AppError>;
+
+    async fn get_all_identities(&self) -> Result<Vec<entity::Identity>, AppError>;
+
+    /// The user that logs in with the identity, if it was invited or linked
+    async fn find_by_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, AppError>;
+
+    /// Another identity the user logs in with
+    async fn add_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+    ) -> Result<(), AppError>;
+
+    /// Profile of the identity as the provider knows it
+    async fn update_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        username: &str,
+        avatar: Option<&str>,
+    ) -> Result<(), AppError>;
+}
+
+/// User of a valid access token, either of a session or a personal access token
+#[derive(Debug, Clone)]
+pub struct TokenUser {
+    pub user_id: Uuid,
+    /// not set for personal access tokens
+    pub session_id: Option<Uuid>,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Login of a user on one device, only the hashes of its tokens are stored
+#[derive(Debug, Clone)]
+pub struct NewSession {
+    pub user_id: Uuid,
+    pub username: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub token_hash: String,
+    pub refresh_token_hash: String,
+}
+
+/// Lifetimes of sessions, logins and tokens start at the write, by the clock of the storage
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn create(
+        &self,
+        session: &NewSession,
+        access_lifetime: chrono::Duration,
+        refresh_lifetime: chrono::Duration,
+    ) -> Result<(), AppError>;
+
+    /// User of the unexpired session of the access token
+    async fn get_user(&self, token_hash: &str) -> Result<Option<TokenUser>, AppError>;
+
+    /// Replace both tokens of the session of the refresh token, `Forbidden` if there is no session
+    /// that can still be refreshed with it
+    ///
+    /// The replaced refresh token is remembered, see [`SessionRepository::delete_reused`]
+    async fn refresh(
+        &self,
+        refresh_token_hash: &str,
+        token_hash: &str,
+        access_lifetime: chrono::Duration,
+        new_refresh_token_hash: &str,
+    ) -> Result<(), AppError>;
+
+    /// Delete the session whose refresh token was already replaced by this one, returns if there
+    /// was one
+    async fn delete_reused(&self, refresh_token_hash: &str) -> Result<bool, AppError>;
+
+    /// Newest first
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Session>, AppError>;
+
+    /// `NotFound` if the user has no such session
+    async fn delete(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError>;
+
+    async fn delete_all_except(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError>;
+
+    /// Delete all sessions that can not be refreshed anymore, returns how many were deleted
+    async fn purge_expired(&self) -> Result<u64, AppError>;
+}
+
+/// Started login at a provider, the random csrf token is sent as oauth state to the provider
+#[derive(Debug, Clone)]
+pub struct OAuthState {
+    pub csrf_token: String,
+    pub provider: String,
+    pub origin_uri: String,
+    pub pkce_verifier: String,
+    /// only checked by OpenID Connect providers
+    pub nonce: String,
+    /// set if the identity is linked to this logged in user instead of logging in
+    pub link_user_id: Option<Uuid>,
+}
+
+/// Finished login, which the caller exchanges once for a session
+#[derive(Debug, Clone)]
+pub struct LoginCode {
+    pub user_id: Uuid,
+    pub username: String,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[async_trait]
+pub trait OAuthRepository: Send + Sync {
+    async fn store_state(
+        &self,
+        state: &OAuthState,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError>;
+
+    /// Remove the unexpired state and return it, so every state can only be used once
+    async fn take_state(&self, csrf_token: &str) -> Result<Option<OAuthState>, AppError>;
+
+    async fn store_login_code(
+        &self,
+        code_hash: &str,
+        login: &LoginCode,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError>;
+
+    /// Remove the unexpired login of the code and return it, so every code can only be used once
+    async fn take_login_code(&self, code_hash: &str) -> Result<Option<LoginCode>, AppError>;
+
+    /// Delete all unfinished logins and unused login codes, returns how many were deleted
+    async fn purge_expired(&self) -> Result<u64, AppError>;
+}
+
+#[async_trait]
+pub trait PersonalAccessTokenRepository: Send + Sync {
+    /// Never expires without a lifetime
+    async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        lifetime: Option<chrono::Duration>,
+    ) -> Result<entity::PersonalAccessToken, AppError>;
+
+    /// Newest first
+    async fn get_all_of_user(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<entity::PersonalAccessToken>, AppError>;
+
+    /// `NotFound` if the user has no such token
+    async fn delete(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError>;
+
+    /// User of the unexpired token if it has the scope, the use is recorded with the token
+    ///
+    /// The username is the one of the first identity of the user, or the name of the token
+    async fn use_token(
+        &self,
+        token_hash: &str,
+        scope: Scope,
+    ) -> Result<Option<TokenUser>, AppError>;
+
+    /// Delete all tokens that expired, returns how many were deleted
+    async fn purge_expired(&self) -> Result<u64, AppError>;
+}
+
+/// Password login of a registered user
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub user_id: Uuid,
+    /// argon2id hash
+    pub password_hash: String,
+    /// if the counted attempt locked the login
+    pub locked: bool,
+}
+
+#[async_trait]
+pub trait CredentialRepository: Send + Sync {
+    /// Create the user with the identity of the username at the provider, together with the
+    /// credential it registers with the code, returns the id of the user
+    async fn invite(
+        &self,
+        provider: &str,
+        username: &str,
+        role: Role,
+        registration_code_hash: &str,
+        registration_lifetime: chrono::Duration,
+    ) -> Result<Uuid, AppError>;
+
+    /// Set the first password if the code of the invite matches and did not expire, returns the
+    /// user
+    async fn register(
+        &self,
+        username: &str,
+        registration_code_hash: &str,
+        password_hash: &str,
+    ) -> Result<Option<Uuid>, AppError>;
+
+    /// Count an attempt of the registered and unlocked user before its password is checked, the
+    /// login is locked for the lockout once the maximum of attempts in a row is reached
+    ///
+    /// `None` if the user has no password or is locked
+    async fn count_attempt(
+        &self,
+        username: &str,
+        max_failed_attempts: i32,
+        lockout: chrono::Duration,
+    ) -> Result<Option<Credential>, AppError>;
+
+    /// The attempt was no failure, as the password was correct
+    async fn reset_attempts(&self, username: &str) -> Result<(), AppError>;
+
+    /// Username of the password login of the user, `None` if it has no password
+    async fn get_username(&self, user_id: Uuid) -> Result<Option<String>, AppError>;
+
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), AppError>;
+}
+
+/// All repositories, which the routes get as state
+#[derive(Clone)]
+pub struct Repositories {
+    pub ledgers: Arc<dyn LedgerRepository>,
+    pub accounts: Arc<dyn AccountRepository>,
+    pub costs: Arc<dyn CostRepository>,
+    pub debts: Arc<dyn DebtRepository>,
+    pub balances: Arc<dyn BalanceRepository>,
+    pub payments: Arc<dyn PaymentRepository>,
+    pub exchange_rates: Arc<dyn ExchangeRateRepository>,
+    pub users: Arc<dyn UserRepository>,
+    pub sessions: Arc<dyn SessionRepository>,
+    pub oauth: Arc<dyn OAuthRepository>,
+    pub personal_access_tokens: Arc<dyn PersonalAccessTokenRepository>,
+    pub credentials: Arc<dyn CredentialRepository>,
+}
+
+impl Repositories {
+    pub fn postgres(pool: PgPool) -> Self {
+        Self {
+            ledgers: Arc::new(postgres::PgLedgers(pool.clone())),
+            accounts: Arc::new(postgres::PgAccounts(pool.clone())),
+            costs: Arc::new(postgres::PgCosts(pool.clone())),
+            debts: Arc::new(postgres::PgDebts(pool.clone())),
+            balances: Arc::new(postgres::PgBalances(pool.clone())),
+            payments: Arc::new(postgres::PgPayments(pool.clone())),
+            exchange_rates: Arc::new(postgres::PgExchangeRates(pool.clone())),
+            users: Arc::new(postgres::PgUsers(pool.clone())),
+            sessions: Arc::new(postgres::PgSessions(pool.clone())),
+            oauth: Arc::new(postgres::PgOAuth(pool.clone())),
+            personal_access_tokens: Arc::new(postgres::PgPersonalAccessTokens(pool.clone())),
+            credentials: Arc::new(postgres::PgCredentials(pool)),
+        }
+    }
+
+    /// Storage in a single file, for instances with only one user
+    pub fn sqlite(pool: SqlitePool) -> Self {
+        Self {
+            ledgers: Arc::new(sqlite::SqliteLedgers(pool.clone())),
+            accounts: Arc::new(sqlite::SqliteAccounts(pool.clone())),
+            costs: Arc::new(sqlite::SqliteCosts(pool.clone())),
+            debts: Arc::new(sqlite::SqliteDebts(pool.clone())),
+            balances: Arc::new(sqlite::SqliteBalances(pool.clone())),
+            payments: Arc::new(sqlite::SqlitePayments(pool.clone())),
+            exchange_rates: Arc::new(sqlite::SqliteExchangeRates(pool.clone())),
+            users: Arc::new(sqlite::SqliteUsers(pool.clone())),
+            sessions: Arc::new(sqlite::SqliteSessions(pool.clone())),
+            oauth: Arc::new(sqlite::SqliteOAuth(pool.clone())),
+            personal_access_tokens: Arc::new(sqlite::SqlitePersonalAccessTokens(pool.clone())),
+            credentials: Arc::new(sqlite::SqliteCredentials(pool)),
+        }
+    }
+
+    /// Empty sqlite database in memory, so unit tests also run its queries
+    #[cfg(test)]
+    pub async fn sqlite_memory() -> Self {
+        // every connection to `:memory:` would get its own database
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::migrate_sqlite(&pool).await.unwrap();
+
+        Self::sqlite(pool)
+    }
+
+    /// Everything is lost with the last clone
+    #[cfg(test)]
+    pub fn memory() -> Self {
+        let store = Arc::new(memory::Store::default());
+
+        Self {
+            ledgers: store.clone(),
+            accounts: store.clone(),
+            costs: store.clone(),
+            debts: store.clone(),
+            balances: store.clone(),
+            payments: store.clone(),
+            exchange_rates: store.clone(),
+            users: store.clone(),
+            sessions: store.clone(),
+            oauth: store.clone(),
+            personal_access_tokens: store.clone(),
+            credentials: store,
+        }
+    }
+}
@@ -0,0 +1,1949 @@
+use axum::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Role, Scope};
+use crate::repository::{
+    both_directions, conflict, ensure_versions, AccountRepository, BalanceRepository, CostFilter,
+    CostRepository, Credential, CredentialRepository, DebtRepository, ExchangeRateRepository,
+    LedgerBalances, LedgerRepository, LoginCode, Movement, NewSession, OAuthRepository, OAuthState,
+    Page, PaymentFilter, PaymentRepository, PersonalAccessTokenRepository, SessionRepository,
+    TokenUser, UserRepository,
+};
+
+/// Key of the advisory lock between new ledgers and the writes of exchange rates, see
+/// `next_versions`
+const LEDGERS_LOCK: i64 = 1;
+
+/// Increment the version of the balances of the ledger, `Conflict` if they changed since the given
+/// one
+///
+/// The row of the ledger stays locked until the end of the transaction, so the writes of its
+/// balances happen one after another
+async fn next_version(
+    tx: &mut Transaction<'_, Postgres>,
+    ledger_id: Uuid,
+    balance_version: i64,
+) -> Result<(), AppError> {
+    let ledger = sqlx::query!(
+        r#"
+            UPDATE ledger
+                SET balance_version = balance_version + 1
+                WHERE id = $1
+                RETURNING balance_version
+        "#,
+        ledger_id,
+    )
+    .fetch_optional(tx)
+    .await?
+    .ok_or(AppError::NotFound)?;
+
+    if ledger.balance_version != balance_version + 1 {
+        return Err(conflict(ledger_id));
+    }
+
+    Ok(())
+}
+
+/// Increment the versions of all ledgers and replace their given balances, see `ensure_versions`
+///
+/// The update locks the rows of all existing ledgers until the end of the transaction, so their
+/// balances are not written in between. Ledgers created later would miss the recalculation, so
+/// they wait for the exclusive advisory lock, other writes are not blocked by it
+async fn next_versions(
+    tx: &mut Transaction<'_, Postgres>,
+    ledgers: &[LedgerBalances],
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+            SELECT pg_advisory_xact_lock($1)
+        "#,
+        LEDGERS_LOCK,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let current = sqlx::query!(
+        r#"
+            UPDATE ledger
+                SET balance_version = balance_version + 1
+                RETURNING id, balance_version - 1 AS "balance_version!"
+        "#
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|ledger| (ledger.id, ledger.balance_version))
+    .collect::<Vec<_>>();
+
+    ensure_versions(&current, ledgers)?;
+
+    for ledger in ledgers {
+        if let Some(balances) = &ledger.balances {
+            replace_balances(tx, ledger.ledger_id, balances).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace all stored balances of the ledger, returns the replaced ones
+async fn replace_balances(
+    tx: &mut Transaction<'_, Postgres>,
+    ledger_id: Uuid,
+    balances: &[entity::Balance],
+) -> Result<Vec<entity::Balance>, AppError> {
+    let replaced = sqlx::query_as!(
+        entity::Balance,
+        r#"
+            DELETE
+                FROM balance
+                    WHERE ledger_id = $1
+                RETURNING *
+        "#,
+        ledger_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let payer_ids = balances
+        .iter()
+        .map(|b| b.payer_account_id)
+        .collect::<Vec<_>>();
+    let lender_ids = balances
+        .iter()
+        .map(|b| b.lender_account_id)
+        .collect::<Vec<_>>();
+    let amounts = balances.iter().map(|b| b.amount).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            INSERT
+                INTO balance
+                    (ledger_id, payer_account_id, lender_account_id, amount)
+                SELECT $1, b.*
+                    FROM UNNEST($2::UUID[], $3::UUID[], $4::BIGINT[])
+                        AS b(payer_account_id, lender_account_id, amount)
+        "#,
+        ledger_id,
+        &payer_ids[..],
+        &lender_ids[..],
+        &amounts[..],
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(replaced)
+}
+
+pub struct PgLedgers(pub PgPool);
+
+#[async_trait]
+impl LedgerRepository for PgLedgers {
+    async fn get(&self, ledger_id: Uuid) -> Result<entity::Ledger, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Ledger,
+            r#"
+                SELECT *
+                FROM ledger
+                    WHERE id = $1
+            "#,
+            ledger_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::Ledger>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Ledger,
+            r#"
+                SELECT * FROM ledger
+            "#
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(&self, ledger: &entity::Ledger) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        // waits for the writes of exchange rates, which do not recalculate ledgers created later
+        sqlx::query!(
+            r#"
+                SELECT pg_advisory_xact_lock_shared($1)
+            "#,
+            LEDGERS_LOCK,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO ledger
+                        (id, name, base_currency)
+                    VALUES
+                        ($1,   $2,            $3)
+            "#,
+            ledger.id,
+            ledger.name,
+            ledger.base_currency,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        ledger: &entity::Ledger,
+        balances: Option<&[entity::Balance]>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger.id, ledger.balance_version).await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE ledger
+                    SET name = $2, base_currency = $3
+                    WHERE id = $1
+            "#,
+            ledger.id,
+            ledger.name,
+            ledger.base_currency,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if let Some(balances) = balances {
+            replace_balances(&mut tx, ledger.id, balances).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, ledger_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM ledger
+                        WHERE id = $1
+            "#,
+            ledger_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct PgAccounts(pub PgPool);
+
+#[async_trait]
+impl AccountRepository for PgAccounts {
+    async fn get(&self, ledger_id: Uuid, account_id: Uuid) -> Result<entity::Account, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Account,
+            r#"
+                SELECT *
+                FROM account
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            account_id,
+            ledger_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Account,
+            r#"
+                SELECT * FROM account
+                    WHERE ledger_id = $1
+            "#,
+            ledger_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Account,
+            r#"
+                SELECT * FROM account
+                    WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn find_of_user(
+        &self,
+        ledger_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<entity::Account>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Account,
+            r#"
+                SELECT * FROM account
+                    WHERE ledger_id = $1
+                        AND user_id = $2
+            "#,
+            ledger_id,
+            user_id
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn find_in_ledger(
+        &self,
+        ledger_id: Uuid,
+        account_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        let found = sqlx::query!(
+            r#"
+                SELECT id
+                FROM account
+                    WHERE ledger_id = $1
+                        AND id = ANY($2)
+            "#,
+            ledger_id,
+            account_ids,
+        )
+        .fetch_all(&self.0)
+        .await?;
+
+        Ok(found.into_iter().map(|f| f.id).collect())
+    }
+
+    async fn create(&self, account: &entity::Account) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO account
+                        (id, ledger_id, name, user_id)
+                    VALUES
+                        ($1,        $2,   $3,      $4)
+            "#,
+            account.id,
+            account.ledger_id,
+            account.name,
+            account.user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update(&self, account: &entity::Account) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE account
+                    SET name = $3, user_id = $4
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            account.id,
+            account.ledger_id,
+            account.name,
+            account.user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, ledger_id: Uuid, account_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM account
+                        WHERE id = $1
+                            AND ledger_id = $2
+            "#,
+            account_id,
+            ledger_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct PgCosts(pub PgPool);
+
+/// Insert all debts of a cost with a single statement
+async fn insert_debts(
+    tx: &mut Transaction<'_, Postgres>,
+    debts: &[entity::Debt],
+) -> Result<(), AppError> {
+    let ids = debts.iter().map(|d| d.id).collect::<Vec<_>>();
+    let cost_ids = debts.iter().map(|d| d.cost_id).collect::<Vec<_>>();
+    let account_ids = debts
+        .iter()
+        .map(|d| d.debtor_account_id)
+        .collect::<Vec<_>>();
+    let amounts = debts.iter().map(|d| d.amount).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            INSERT
+                INTO debt
+                    (id, debtor_account_id, cost_id, amount)
+                SELECT d.id, d.debtor_account_id, d.cost_id, d.amount
+                    FROM UNNEST($1::UUID[], $2::UUID[], $3::UUID[], $4::BIGINT[])
+                        AS d(id, cost_id, debtor_account_id, amount)
+        "#,
+        &ids[..],
+        &cost_ids[..],
+        &account_ids[..],
+        &amounts[..],
+    )
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Add the changes to the stored balances of both directions with a single statement
+async fn apply_changes(
+    tx: &mut Transaction<'_, Postgres>,
+    changes: &[entity::Balance],
+) -> Result<(), AppError> {
+    let sides = both_directions(changes);
+    let ledger_ids = sides.iter().map(|b| b.ledger_id).collect::<Vec<_>>();
+    let payer_ids = sides.iter().map(|b| b.payer_account_id).collect::<Vec<_>>();
+    let lender_ids = sides
+        .iter()
+        .map(|b| b.lender_account_id)
+        .collect::<Vec<_>>();
+    let amounts = sides.iter().map(|b| b.amount).collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+            INSERT
+                INTO balance
+                    (ledger_id, payer_account_id, lender_account_id, amount)
+                SELECT *
+                    FROM UNNEST($1::UUID[], $2::UUID[], $3::UUID[], $4::BIGINT[])
+                ON CONFLICT (payer_account_id, lender_account_id)
+                    DO UPDATE SET amount = balance.amount + EXCLUDED.amount
+        "#,
+        &ledger_ids[..],
+        &payer_ids[..],
+        &lender_ids[..],
+        &amounts[..],
+    )
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl CostRepository for PgCosts {
+    async fn get(&self, ledger_id: Uuid, cost_id: Uuid) -> Result<entity::Cost, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Cost,
+            r#"
+                SELECT *
+                FROM cost
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            cost_id,
+            ledger_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_for_account(
+        &self,
+        ledger_id: Uuid,
+        account_id: Uuid,
+    ) -> Result<Vec<entity::Cost>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Cost,
+            r#"
+                SELECT *
+                FROM cost
+                    WHERE account_id = $1
+                        AND ledger_id = $2
+            "#,
+            account_id,
+            ledger_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_with_debts(
+        &self,
+        ledger_id: Uuid,
+        filter: &CostFilter,
+        page: &Page,
+    ) -> Result<Vec<(entity::Cost, entity::Debt)>, AppError> {
+        let (after_position, after_id) = page.after.unzip();
+
+        Ok(sqlx::query!(
+            r#"
+                WITH filtered AS (
+                    SELECT
+                        c.*,
+                        CASE WHEN $11 THEN c.amount ELSE (c.event_date - DATE '0001-01-01' + 1)::BIGINT END
+                            * CASE WHEN $12 THEN -1 ELSE 1 END AS position
+                    FROM cost c
+                    WHERE
+                        c.ledger_id = $1
+                            AND c.event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+                            AND ($4::UUID IS NULL OR c.account_id = $4)
+                            AND EXISTS (
+                                SELECT 1 FROM debt d WHERE d.cost_id = c.id AND ($5::UUID IS NULL OR d.debtor_account_id = $5)
+                            )
+                            AND COALESCE(c.tags, '{}') @> $6::VARCHAR[]
+                            AND c.amount BETWEEN COALESCE($7, c.amount) AND COALESCE($8, c.amount)
+                            AND ($9::VARCHAR IS NULL OR POSITION(LOWER($9) IN LOWER(c.description)) > 0)
+                ),
+                page AS (
+                    SELECT *
+                    FROM filtered f
+                    WHERE $13::BIGINT IS NULL OR (f.position, f.id) > ($13, $14::UUID)
+                    ORDER BY f.position, f.id
+                    LIMIT $10
+                )
+                SELECT p.id AS "id!", p.ledger_id AS "ledger_id!", p.account_id AS "account_id!",
+                    p.amount AS "amount!", p.event_date AS "event_date!", p.description, p.tags,
+                    p.currency AS "currency!", d.id AS debt_id, d.debtor_account_id, d.amount AS debtor_amount
+                FROM page p
+                    JOIN debt d ON d.cost_id = p.id
+                ORDER BY p.position, p.id, d.id
+            "#,
+            ledger_id,
+            filter.start_date,
+            filter.end_date,
+            filter.payer_account_id,
+            filter.debtor_account_id,
+            &filter.tags,
+            filter.min_amount,
+            filter.max_amount,
+            filter.description,
+            page.limit,
+            page.by_amount,
+            page.descending,
+            after_position,
+            after_id,
+        )
+        .map(|row| {
+            (
+                entity::Cost {
+                    id: row.id,
+                    ledger_id: row.ledger_id,
+                    account_id: row.account_id,
+                    amount: row.amount,
+                    event_date: row.event_date,
+                    description: row.description,
+                    tags: row.tags,
+                    currency: row.currency,
+                },
+                entity::Debt {
+                    id: row.debt_id,
+                    debtor_account_id: row.debtor_account_id,
+                    cost_id: row.id,
+                    amount: row.debtor_amount,
+                },
+            )
+        })
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_tags(&self, ledger_id: Uuid) -> Result<Vec<String>, AppError> {
+        let tags = sqlx::query!(
+            r#"
+                SELECT DISTINCT UNNEST(tags) AS "tag!"
+                FROM cost
+                    WHERE ledger_id = $1
+                    ORDER BY 1
+            "#,
+            ledger_id
+        )
+        .fetch_all(&self.0)
+        .await?;
+
+        Ok(tags.into_iter().map(|t| t.tag).collect())
+    }
+
+    async fn create(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        // cost and debts are only stored together, a failing debt will roll back the whole cost
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, cost.ledger_id, balance_version).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO cost
+                        (id, ledger_id, account_id, amount, currency, description, event_date, tags)
+                    VALUES
+                        ($1,        $2,         $3,     $4,       $5,          $6,         $7,   $8)
+            "#,
+            cost.id,
+            cost.ledger_id,
+            cost.account_id,
+            cost.amount,
+            cost.currency,
+            cost.description,
+            cost.event_date,
+            cost.tags.as_deref(),
+        )
+        .execute(&mut tx)
+        .await?;
+
+        insert_debts(&mut tx, debts).await?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        // the cost and its debts are replaced together, so the split can never be half updated
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, cost.ledger_id, balance_version).await?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE cost
+                    SET amount = $3, currency = $4, description = $5, event_date = $6, tags = $7
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            cost.id,
+            cost.ledger_id,
+            cost.amount,
+            cost.currency,
+            cost.description,
+            cost.event_date,
+            cost.tags.as_deref(),
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        sqlx::query!(
+            r#"
+                DELETE
+                    FROM debt
+                        WHERE cost_id = $1
+            "#,
+            cost.id,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        insert_debts(&mut tx, debts).await?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        cost_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM cost
+                        WHERE id = $1
+                            AND ledger_id = $2
+            "#,
+            cost_id,
+            ledger_id,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct PgDebts(pub PgPool);
+
+#[async_trait]
+impl DebtRepository for PgDebts {
+    async fn get_of_cost(&self, cost_id: Uuid) -> Result<Vec<entity::Debt>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Debt,
+            r#"
+                SELECT id, debtor_account_id, cost_id, amount
+                FROM debt
+                    WHERE cost_id = $1
+            "#,
+            cost_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+}
+
+pub struct PgBalances(pub PgPool);
+
+#[async_trait]
+impl BalanceRepository for PgBalances {
+    async fn get_movements(
+        &self,
+        ledger_id: Uuid,
+        currency: &str,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Movement>, AppError> {
+        Ok(sqlx::query_as!(
+            Movement,
+            r#"
+                WITH movement AS (
+                    -- the debtor owes the payer of the cost
+                    SELECT c.account_id AS creditor, d.debtor_account_id AS debtor, d.amount, c.currency, c.event_date
+                        FROM debt d
+                            JOIN cost c ON c.id = d.cost_id
+                        WHERE c.ledger_id = $1
+                            AND c.event_date BETWEEN COALESCE($3, '-infinity'::DATE) AND COALESCE($4, 'infinity'::DATE)
+                    UNION ALL
+                    -- the lender owes the payment back to its payer
+                    SELECT p.payer_account_id, p.lender_account_id, p.amount, p.currency, p.event_date
+                        FROM payment p
+                        WHERE p.ledger_id = $1
+                            AND p.event_date BETWEEN COALESCE($3, '-infinity'::DATE) AND COALESCE($4, 'infinity'::DATE)
+                )
+                SELECT creditor AS "creditor_account_id!", debtor AS "debtor_account_id!",
+                    currency AS "currency!", MIN(event_date) AS "event_date!",
+                    (CASE WHEN currency = $2 THEN SUM(amount) ELSE MIN(amount) END)::BIGINT AS "amount!",
+                    CASE WHEN currency = $2 THEN 1 ELSE COUNT(*) END AS "count!"
+                FROM movement
+                GROUP BY creditor, debtor, currency,
+                    CASE WHEN currency = $2 THEN NULL ELSE event_date END,
+                    CASE WHEN currency = $2 THEN NULL ELSE amount END
+            "#,
+            ledger_id,
+            currency,
+            start_date,
+            end_date,
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Balance>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Balance,
+            r#"
+                SELECT *
+                FROM balance
+                    WHERE ledger_id = $1
+            "#,
+            ledger_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn replace_all(
+        &self,
+        ledger_id: Uuid,
+        balance_version: i64,
+        balances: &[entity::Balance],
+    ) -> Result<Vec<entity::Balance>, AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+        let replaced = replace_balances(&mut tx, ledger_id, balances).await?;
+
+        tx.commit().await?;
+
+        Ok(replaced)
+    }
+}
+
+pub struct PgPayments(pub PgPool);
+
+#[async_trait]
+impl PaymentRepository for PgPayments {
+    async fn get(&self, ledger_id: Uuid, payment_id: Uuid) -> Result<entity::Payment, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Payment,
+            r#"
+                SELECT *
+                FROM payment
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            payment_id,
+            ledger_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(
+        &self,
+        ledger_id: Uuid,
+        filter: &PaymentFilter,
+        page: &Page,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        let (after_position, after_id) = page.after.unzip();
+
+        Ok(sqlx::query_as!(
+            entity::Payment,
+            r#"
+                WITH filtered AS (
+                    SELECT
+                        p.*,
+                        CASE WHEN $9 THEN p.amount ELSE (p.event_date - DATE '0001-01-01' + 1)::BIGINT END
+                            * CASE WHEN $10 THEN -1 ELSE 1 END AS position
+                    FROM payment p
+                    WHERE
+                        p.ledger_id = $1
+                            AND p.event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+                            AND ($4::UUID IS NULL OR p.payer_account_id = $4)
+                            AND ($5::UUID IS NULL OR p.lender_account_id = $5)
+                            AND p.amount BETWEEN COALESCE($6, p.amount) AND COALESCE($7, p.amount)
+                )
+                SELECT f.id AS "id!", f.ledger_id AS "ledger_id!", f.payer_account_id AS "payer_account_id!",
+                    f.lender_account_id AS "lender_account_id!", f.amount AS "amount!",
+                    f.event_date AS "event_date!", f.description, f.currency AS "currency!"
+                FROM filtered f
+                WHERE $11::BIGINT IS NULL OR (f.position, f.id) > ($11, $12::UUID)
+                ORDER BY f.position, f.id
+                LIMIT $8
+            "#,
+            ledger_id,
+            filter.start_date,
+            filter.end_date,
+            filter.payer_account_id,
+            filter.lender_account_id,
+            filter.min_amount,
+            filter.max_amount,
+            page.limit,
+            page.by_amount,
+            page.descending,
+            after_position,
+            after_id,
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_for_account(
+        &self,
+        payer_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Payment,
+            r#"
+                SELECT *
+                FROM payment
+                    WHERE payer_account_id = $1
+                        AND event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+            "#,
+            payer_account_id,
+            start_date,
+            end_date,
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_of_account(
+        &self,
+        lender_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Payment,
+            r#"
+                SELECT *
+                FROM payment
+                    WHERE lender_account_id = $1
+                        AND event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+            "#,
+            lender_account_id,
+            start_date,
+            end_date,
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(
+        &self,
+        ledger_id: Uuid,
+        payments: &[entity::Payment],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let ids = payments.iter().map(|p| p.id).collect::<Vec<_>>();
+        let ledger_ids = payments.iter().map(|p| p.ledger_id).collect::<Vec<_>>();
+        let payer_ids = payments
+            .iter()
+            .map(|p| p.payer_account_id)
+            .collect::<Vec<_>>();
+        let lender_ids = payments
+            .iter()
+            .map(|p| p.lender_account_id)
+            .collect::<Vec<_>>();
+        let amounts = payments.iter().map(|p| p.amount).collect::<Vec<_>>();
+        let currencies = payments
+            .iter()
+            .map(|p| p.currency.clone())
+            .collect::<Vec<_>>();
+        let descriptions = payments
+            .iter()
+            .map(|p| p.description.clone())
+            .collect::<Vec<_>>();
+        let event_dates = payments.iter().map(|p| p.event_date).collect::<Vec<_>>();
+
+        // either all payments are stored together with their balances or none
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO payment
+                        (id, ledger_id, payer_account_id, lender_account_id, amount, currency, description, event_date)
+                    SELECT *
+                        FROM UNNEST($1::UUID[], $2::UUID[], $3::UUID[], $4::UUID[], $5::BIGINT[], $6::VARCHAR[], $7::TEXT[], $8::DATE[])
+            "#,
+            &ids[..],
+            &ledger_ids[..],
+            &payer_ids[..],
+            &lender_ids[..],
+            &amounts[..],
+            &currencies[..],
+            &descriptions[..] as &[Option<String>],
+            &event_dates[..],
+        )
+        .execute(&mut tx)
+        .await?;
+
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        payment: &entity::Payment,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, payment.ledger_id, balance_version).await?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE payment
+                    SET lender_account_id = $3, amount = $4, currency = $5, description = $6, event_date = $7
+                    WHERE id = $1
+                        AND ledger_id = $2
+            "#,
+            payment.id,
+            payment.ledger_id,
+            payment.lender_account_id,
+            payment.amount,
+            payment.currency,
+            payment.description,
+            payment.event_date
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        payment_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM payment
+                        WHERE id = $1
+                            AND ledger_id = $2
+            "#,
+            payment_id,
+            ledger_id,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct PgExchangeRates(pub PgPool);
+
+#[async_trait]
+impl ExchangeRateRepository for PgExchangeRates {
+    async fn get(&self, exchange_rate_id: Uuid) -> Result<entity::ExchangeRate, AppError> {
+        Ok(sqlx::query_as!(
+            entity::ExchangeRate,
+            r#"
+                SELECT *
+                FROM exchange_rate
+                    WHERE id = $1
+            "#,
+            exchange_rate_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::ExchangeRate>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::ExchangeRate,
+            r#"
+                SELECT *
+                FROM exchange_rate
+                    ORDER BY date DESC
+            "#
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(
+        &self,
+        exchange_rate: &entity::ExchangeRate,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_versions(&mut tx, ledgers).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO exchange_rate
+                        (id, date, from_currency, to_currency, rate)
+                    VALUES
+                        ($1,   $2,            $3,          $4,   $5)
+            "#,
+            exchange_rate.id,
+            exchange_rate.date,
+            exchange_rate.from_currency,
+            exchange_rate.to_currency,
+            exchange_rate.rate,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        exchange_rate_id: Uuid,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_versions(&mut tx, ledgers).await?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM exchange_rate
+                        WHERE id = $1
+            "#,
+            exchange_rate_id,
+        )
+        .execute(&mut tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct PgUsers(pub PgPool);
+
+/// Seconds of the lifetime, as `make_interval` takes them
+#[allow(clippy::cast_precision_loss)]
+fn seconds(lifetime: chrono::Duration) -> f64 {
+    lifetime.num_seconds() as f64
+}
+
+/// Insert the user together with its first identity, returns the id of the user
+///
+/// Part of the given transaction, so other data of the user can be stored together with it
+async fn insert_user(
+    tx: &mut Transaction<'_, Postgres>,
+    provider: &str,
+    subject: &str,
+    role: Role,
+) -> Result<Uuid, AppError> {
+    let uuid = Uuid::new_v4();
+
+    sqlx::query!(
+        r#"
+            INSERT
+                INTO "user"
+                    (id, role)
+                VALUES
+                    ($1,   $2)
+        "#,
+        &uuid,
+        role as Role,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT
+                INTO identity
+                    (provider, subject, user_id)
+                VALUES
+                    ($1,            $2,      $3)
+        "#,
+        provider.to_lowercase(),
+        subject,
+        &uuid,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    Ok(uuid)
+}
+
+#[async_trait]
+impl UserRepository for PgUsers {
+    async fn get(&self, user_id: Uuid) -> Result<entity::User, AppError> {
+        Ok(sqlx::query_as!(
+            entity::User,
+            r#"
+                SELECT id, role AS "role: Role", creation_date
+                FROM "user"
+                    WHERE id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::User>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::User,
+            r#"
+                SELECT id, role AS "role: Role", creation_date
+                FROM "user"
+                    ORDER BY creation_date
+            "#
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(&self, provider: &str, subject: &str, role: Role) -> Result<Uuid, AppError> {
+        let mut tx = self.0.begin().await?;
+
+        let uuid = insert_user(&mut tx, provider, subject, role).await?;
+
+        tx.commit().await?;
+
+        Ok(uuid)
+    }
+
+    async fn update_role(&self, user_id: Uuid, role: Role) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE "user"
+                    SET role = $2
+                    WHERE id = $1
+            "#,
+            user_id,
+            role as Role,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, user_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM "user"
+                        WHERE id = $1
+            "#,
+            user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn get_identities(&self, user_id: Uuid) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Identity,
+            r#"
+                SELECT *
+                FROM identity
+                    WHERE user_id = $1
+                    ORDER BY creation_date
+            "#,
+            user_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_identities(&self) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Identity,
+            r#"
+                SELECT *
+                FROM identity
+                    ORDER BY creation_date
+            "#
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn find_by_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        let identity = sqlx::query!(
+            r#"
+                SELECT user_id
+                FROM identity
+                    WHERE provider = $1
+                        AND subject = $2
+            "#,
+            provider,
+            subject,
+        )
+        .fetch_optional(&self.0)
+        .await?;
+
+        Ok(identity.map(|i| i.user_id))
+    }
+
+    async fn add_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO identity
+                        (provider, subject, user_id)
+                    VALUES
+                        ($1,            $2,      $3)
+            "#,
+            provider,
+            subject,
+            user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        username: &str,
+        avatar: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE identity
+                    SET username = $3, avatar = $4
+                    WHERE provider = $1
+                        AND subject = $2
+            "#,
+            provider,
+            subject,
+            username,
+            avatar,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+}
+
+pub struct PgSessions(pub PgPool);
+
+#[async_trait]
+impl SessionRepository for PgSessions {
+    async fn create(
+        &self,
+        session: &NewSession,
+        access_lifetime: chrono::Duration,
+        refresh_lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO session
+                        (id, user_id, username, user_agent, ip,
+                         token_hash, expires_at,
+                         refresh_token_hash, refresh_expires_at)
+                    VALUES
+                        ($1, $2, $3, $4, $5,
+                         $6, CURRENT_TIMESTAMP + make_interval(secs => $7),
+                         $8, CURRENT_TIMESTAMP + make_interval(secs => $9))
+            "#,
+            Uuid::new_v4(),
+            session.user_id,
+            session.username,
+            session.user_agent,
+            session.ip,
+            session.token_hash,
+            seconds(access_lifetime),
+            session.refresh_token_hash,
+            seconds(refresh_lifetime),
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user(&self, token_hash: &str) -> Result<Option<TokenUser>, AppError> {
+        // only unexpired sessions of users that are (still) invited are valid
+        Ok(sqlx::query_as!(
+            TokenUser,
+            r#"
+                SELECT u.id AS user_id, s.id AS "session_id?", s.username, u.role AS "role: Role"
+                FROM session s
+                    JOIN "user" u ON u.id = s.user_id
+                WHERE s.token_hash = $1
+                    AND s.expires_at > CURRENT_TIMESTAMP
+            "#,
+            token_hash,
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn refresh(
+        &self,
+        refresh_token_hash: &str,
+        token_hash: &str,
+        access_lifetime: chrono::Duration,
+        new_refresh_token_hash: &str,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                UPDATE session
+                    SET token_hash = $2,
+                        expires_at = CURRENT_TIMESTAMP + make_interval(secs => $3),
+                        refresh_token_hash = $4,
+                        previous_refresh_token_hash = refresh_token_hash
+                    WHERE refresh_token_hash = $1
+                        AND refresh_expires_at > CURRENT_TIMESTAMP
+            "#,
+            refresh_token_hash,
+            token_hash,
+            seconds(access_lifetime),
+            new_refresh_token_hash,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_reused(&self, refresh_token_hash: &str) -> Result<bool, AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM session
+                        WHERE previous_refresh_token_hash = $1
+            "#,
+            refresh_token_hash,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Session>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::Session,
+            r#"
+                SELECT id, user_agent, ip, creation_date, refresh_expires_at
+                FROM session
+                    WHERE user_id = $1
+                    ORDER BY creation_date DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn delete(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM session
+                        WHERE id = $1
+                            AND user_id = $2
+            "#,
+            session_id,
+            user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_all_except(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                DELETE
+                    FROM session
+                        WHERE user_id = $1
+                            AND id != $2
+            "#,
+            user_id,
+            session_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM session
+                        WHERE refresh_expires_at <= CURRENT_TIMESTAMP
+            "#
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct PgOAuth(pub PgPool);
+
+#[async_trait]
+impl OAuthRepository for PgOAuth {
+    async fn store_state(
+        &self,
+        state: &OAuthState,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO oauth_state
+                        (csrf_token, provider, origin_uri, pkce_verifier, nonce, link_user_id, expires_at)
+                    VALUES
+                        ($1, $2, $3, $4, $5, $6, CURRENT_TIMESTAMP + make_interval(secs => $7))
+            "#,
+            state.csrf_token,
+            state.provider,
+            state.origin_uri,
+            state.pkce_verifier,
+            state.nonce,
+            state.link_user_id,
+            seconds(lifetime),
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_state(&self, csrf_token: &str) -> Result<Option<OAuthState>, AppError> {
+        Ok(sqlx::query_as!(
+            OAuthState,
+            r#"
+                DELETE
+                    FROM oauth_state
+                        WHERE csrf_token = $1
+                            AND expires_at > CURRENT_TIMESTAMP
+                    RETURNING csrf_token, provider, origin_uri, pkce_verifier, nonce, link_user_id
+            "#,
+            csrf_token,
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn store_login_code(
+        &self,
+        code_hash: &str,
+        login: &LoginCode,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO login_code
+                        (code_hash, user_id, username, user_agent, ip, expires_at)
+                    VALUES
+                        ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(secs => $6))
+            "#,
+            code_hash,
+            login.user_id,
+            login.username,
+            login.user_agent,
+            login.ip,
+            seconds(lifetime),
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_login_code(&self, code_hash: &str) -> Result<Option<LoginCode>, AppError> {
+        Ok(sqlx::query_as!(
+            LoginCode,
+            r#"
+                DELETE
+                    FROM login_code
+                        WHERE code_hash = $1
+                            AND expires_at > CURRENT_TIMESTAMP
+                    RETURNING user_id, username, user_agent, ip
+            "#,
+            code_hash,
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let states = sqlx::query!(
+            r#"
+                DELETE
+                    FROM oauth_state
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            "#
+        )
+        .execute(&self.0)
+        .await?;
+
+        let codes = sqlx::query!(
+            r#"
+                DELETE
+                    FROM login_code
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            "#
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(states.rows_affected() + codes.rows_affected())
+    }
+}
+
+pub struct PgPersonalAccessTokens(pub PgPool);
+
+#[async_trait]
+impl PersonalAccessTokenRepository for PgPersonalAccessTokens {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        lifetime: Option<chrono::Duration>,
+    ) -> Result<entity::PersonalAccessToken, AppError> {
+        Ok(sqlx::query_as!(
+            entity::PersonalAccessToken,
+            r#"
+                INSERT
+                    INTO personal_access_token
+                        (id, user_id, name, token_hash, scopes, expires_at)
+                    VALUES
+                        ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP + make_interval(secs => $6))
+                    RETURNING id, name, scopes AS "scopes: Vec<Scope>", expires_at, last_used_at, creation_date
+            "#,
+            Uuid::new_v4(),
+            user_id,
+            name,
+            token_hash,
+            scopes as &[Scope],
+            lifetime.map(seconds),
+        )
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all_of_user(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<entity::PersonalAccessToken>, AppError> {
+        Ok(sqlx::query_as!(
+            entity::PersonalAccessToken,
+            r#"
+                SELECT id, name, scopes AS "scopes: Vec<Scope>", expires_at, last_used_at, creation_date
+                FROM personal_access_token
+                    WHERE user_id = $1
+                    ORDER BY creation_date DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn delete(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM personal_access_token
+                        WHERE id = $1
+                            AND user_id = $2
+            "#,
+            token_id,
+            user_id,
+        )
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn use_token(
+        &self,
+        token_hash: &str,
+        scope: Scope,
+    ) -> Result<Option<TokenUser>, AppError> {
+        Ok(sqlx::query_as!(
+            TokenUser,
+            r#"
+                UPDATE personal_access_token t
+                    SET last_used_at = CURRENT_TIMESTAMP
+                    FROM "user" u
+                    WHERE u.id = t.user_id
+                        AND t.token_hash = $1
+                        AND $2 = ANY(t.scopes)
+                        AND (t.expires_at IS NULL OR t.expires_at > CURRENT_TIMESTAMP)
+                    RETURNING u.id AS user_id, NULL::UUID AS "session_id?", u.role AS "role: Role",
+                        COALESCE(
+                            (SELECT i.username
+                                FROM identity i
+                                    WHERE i.user_id = u.id
+                                    ORDER BY i.creation_date
+                                    LIMIT 1),
+                            t.name) AS "username!"
+            "#,
+            token_hash,
+            scope as Scope,
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let result = sqlx::query!(
+            r#"
+                DELETE
+                    FROM personal_access_token
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            "#
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct PgCredentials(pub PgPool);
+
+#[async_trait]
+impl CredentialRepository for PgCredentials {
+    async fn invite(
+        &self,
+        provider: &str,
+        username: &str,
+        role: Role,
+        registration_code_hash: &str,
+        registration_lifetime: chrono::Duration,
+    ) -> Result<Uuid, AppError> {
+        let mut tx = self.0.begin().await?;
+
+        let user_id = insert_user(&mut tx, provider, username, role).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT
+                    INTO local_credential
+                        (username, user_id, registration_code_hash, registration_expires_at)
+                    VALUES
+                        ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(secs => $4))
+            "#,
+            username,
+            user_id,
+            registration_code_hash,
+            seconds(registration_lifetime),
+        )
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+
+    async fn register(
+        &self,
+        username: &str,
+        registration_code_hash: &str,
+        password_hash: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        let credential = sqlx::query!(
+            r#"
+                UPDATE local_credential
+                    SET password_hash = $3,
+                        registration_code_hash = NULL,
+                        registration_expires_at = NULL
+                    WHERE username = $1
+                        AND registration_code_hash = $2
+                        AND registration_expires_at > CURRENT_TIMESTAMP
+                    RETURNING user_id
+            "#,
+            username,
+            registration_code_hash,
+            password_hash,
+        )
+        .fetch_optional(&self.0)
+        .await?;
+
+        Ok(credential.map(|c| c.user_id))
+    }
+
+    async fn count_attempt(
+        &self,
+        username: &str,
+        max_failed_attempts: i32,
+        lockout: chrono::Duration,
+    ) -> Result<Option<Credential>, AppError> {
+        Ok(sqlx::query_as!(
+            Credential,
+            r#"
+                UPDATE local_credential
+                    SET failed_attempts = CASE
+                            WHEN failed_attempts + 1 >= $2 THEN 0
+                            ELSE failed_attempts + 1
+                        END,
+                        locked_until = CASE
+                            WHEN failed_attempts + 1 >= $2
+                                THEN CURRENT_TIMESTAMP + make_interval(secs => $3)
+                            ELSE locked_until
+                        END
+                    WHERE username = $1
+                        AND password_hash IS NOT NULL
+                        AND NOT COALESCE(locked_until > CURRENT_TIMESTAMP, FALSE)
+                    RETURNING
+                        user_id,
+                        password_hash AS "password_hash!",
+                        COALESCE(locked_until > CURRENT_TIMESTAMP, FALSE) AS "locked!"
+            "#,
+            username,
+            max_failed_attempts,
+            seconds(lockout),
+        )
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn reset_attempts(&self, username: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE local_credential
+                    SET failed_attempts = 0, locked_until = NULL
+                    WHERE username = $1
+            "#,
+            username,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_username(&self, user_id: Uuid) -> Result<Option<String>, AppError> {
+        let credential = sqlx::query!(
+            r#"
+                SELECT username
+                FROM local_credential
+                    WHERE user_id = $1
+                        AND password_hash IS NOT NULL
+            "#,
+            user_id,
+        )
+        .fetch_optional(&self.0)
+        .await?;
+
+        Ok(credential.map(|c| c.username))
+    }
+
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+                UPDATE local_credential
+                    SET password_hash = $2
+                    WHERE user_id = $1
+            "#,
+            user_id,
+            password_hash,
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+}
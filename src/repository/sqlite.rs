@@ -0,0 +1,2063 @@
+//! Queries are only checked at runtime, as the offline data of sqlx can only describe one database
+
+use axum::async_trait;
+use sqlx::{sqlite::SqliteRow, types::Json, Row, SqlitePool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Role, Scope};
+use crate::repository::{
+    both_directions, conflict, ensure_versions, AccountRepository, BalanceRepository, CostFilter,
+    CostRepository, Credential, CredentialRepository, DebtRepository, ExchangeRateRepository,
+    LedgerBalances, LedgerRepository, LoginCode, Movement, NewSession, OAuthRepository, OAuthState,
+    Page, PaymentFilter, PaymentRepository, PersonalAccessTokenRepository, SessionRepository,
+    TokenUser, UserRepository,
+};
+
+/// Dates are stored as text, which compares like the dates themselves
+const MIN_DATE: &str = "0000-01-01";
+const MAX_DATE: &str = "9999-12-31";
+
+fn not_found_if_unchanged(result: &sqlx::sqlite::SqliteQueryResult) -> Result<(), AppError> {
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(())
+}
+
+fn ledger(row: &SqliteRow) -> Result<entity::Ledger, sqlx::Error> {
+    Ok(entity::Ledger {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        base_currency: row.try_get("base_currency")?,
+        balance_version: row.try_get("balance_version")?,
+    })
+}
+
+fn account(row: &SqliteRow) -> Result<entity::Account, sqlx::Error> {
+    Ok(entity::Account {
+        id: row.try_get("id")?,
+        ledger_id: row.try_get("ledger_id")?,
+        name: row.try_get("name")?,
+        user_id: row.try_get("user_id")?,
+    })
+}
+
+fn cost(row: &SqliteRow) -> Result<entity::Cost, sqlx::Error> {
+    Ok(entity::Cost {
+        id: row.try_get("id")?,
+        ledger_id: row.try_get("ledger_id")?,
+        account_id: row.try_get("account_id")?,
+        amount: row.try_get("amount")?,
+        event_date: row.try_get("event_date")?,
+        description: row.try_get("description")?,
+        tags: row
+            .try_get::<Option<Json<Vec<String>>>, _>("tags")?
+            .map(|tags| tags.0),
+        currency: row.try_get("currency")?,
+    })
+}
+
+fn debt(row: &SqliteRow) -> Result<entity::Debt, sqlx::Error> {
+    Ok(entity::Debt {
+        id: row.try_get("debt_id")?,
+        debtor_account_id: row.try_get("debtor_account_id")?,
+        cost_id: row.try_get("cost_id")?,
+        amount: row.try_get("debt_amount")?,
+    })
+}
+
+fn payment(row: &SqliteRow) -> Result<entity::Payment, sqlx::Error> {
+    Ok(entity::Payment {
+        id: row.try_get("id")?,
+        ledger_id: row.try_get("ledger_id")?,
+        payer_account_id: row.try_get("payer_account_id")?,
+        lender_account_id: row.try_get("lender_account_id")?,
+        amount: row.try_get("amount")?,
+        event_date: row.try_get("event_date")?,
+        description: row.try_get("description")?,
+        currency: row.try_get("currency")?,
+    })
+}
+
+fn balance(row: &SqliteRow) -> Result<entity::Balance, sqlx::Error> {
+    Ok(entity::Balance {
+        ledger_id: row.try_get("ledger_id")?,
+        payer_account_id: row.try_get("payer_account_id")?,
+        lender_account_id: row.try_get("lender_account_id")?,
+        amount: row.try_get("amount")?,
+    })
+}
+
+fn exchange_rate(row: &SqliteRow) -> Result<entity::ExchangeRate, sqlx::Error> {
+    Ok(entity::ExchangeRate {
+        id: row.try_get("id")?,
+        date: row.try_get("date")?,
+        from_currency: row.try_get("from_currency")?,
+        to_currency: row.try_get("to_currency")?,
+        rate: row.try_get("rate")?,
+    })
+}
+
+const fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::Admin => "admin",
+        Role::Member => "member",
+        Role::ReadOnly => "read_only",
+    }
+}
+
+fn role(row: &SqliteRow) -> Result<Role, sqlx::Error> {
+    match row.try_get::<&str, _>("role")? {
+        "admin" => Ok(Role::Admin),
+        "member" => Ok(Role::Member),
+        "read_only" => Ok(Role::ReadOnly),
+        role => Err(sqlx::Error::Decode(format!("unknown role {role}").into())),
+    }
+}
+
+fn user(row: &SqliteRow) -> Result<entity::User, sqlx::Error> {
+    Ok(entity::User {
+        id: row.try_get("id")?,
+        role: role(row)?,
+        creation_date: row.try_get("creation_date")?,
+    })
+}
+
+fn identity(row: &SqliteRow) -> Result<entity::Identity, sqlx::Error> {
+    Ok(entity::Identity {
+        provider: row.try_get("provider")?,
+        subject: row.try_get("subject")?,
+        user_id: row.try_get("user_id")?,
+        username: row.try_get("username")?,
+        avatar: row.try_get("avatar")?,
+        creation_date: row.try_get("creation_date")?,
+    })
+}
+
+/// Like the serialized scope, which is stored in the json array of the token
+const fn scope_name(scope: Scope) -> &'static str {
+    match scope {
+        Scope::LedgersRead => "ledgers:read",
+        Scope::LedgersWrite => "ledgers:write",
+        Scope::CostsRead => "costs:read",
+        Scope::CostsWrite => "costs:write",
+        Scope::PaymentsRead => "payments:read",
+        Scope::PaymentsWrite => "payments:write",
+        Scope::ExchangeRatesRead => "exchange_rates:read",
+        Scope::ExchangeRatesWrite => "exchange_rates:write",
+        Scope::SnapshotRead => "snapshot:read",
+    }
+}
+
+fn token_user(row: &SqliteRow) -> Result<TokenUser, sqlx::Error> {
+    Ok(TokenUser {
+        user_id: row.try_get("user_id")?,
+        session_id: row.try_get("session_id")?,
+        username: row.try_get("username")?,
+        role: role(row)?,
+    })
+}
+
+fn session(row: &SqliteRow) -> Result<entity::Session, sqlx::Error> {
+    Ok(entity::Session {
+        id: row.try_get("id")?,
+        user_agent: row.try_get("user_agent")?,
+        ip: row.try_get("ip")?,
+        creation_date: row.try_get("creation_date")?,
+        refresh_expires_at: row.try_get("refresh_expires_at")?,
+    })
+}
+
+fn personal_access_token(row: &SqliteRow) -> Result<entity::PersonalAccessToken, sqlx::Error> {
+    Ok(entity::PersonalAccessToken {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        scopes: row.try_get::<Json<Vec<Scope>>, _>("scopes")?.0,
+        expires_at: row.try_get("expires_at")?,
+        last_used_at: row.try_get("last_used_at")?,
+        creation_date: row.try_get("creation_date")?,
+    })
+}
+
+/// Modifier of `datetime('now', ...)` for the end of the lifetime, which compares like
+/// `CURRENT_TIMESTAMP`
+fn expiry(lifetime: chrono::Duration) -> String {
+    format!("{:+} seconds", lifetime.num_seconds())
+}
+
+/// Increment the version of the balances of the ledger, `Conflict` if they changed since the given
+/// one
+///
+/// Sqlite has only one writer at a time, the transaction holds the write lock from this update on
+async fn next_version(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ledger_id: Uuid,
+    balance_version: i64,
+) -> Result<(), AppError> {
+    let result = sqlx::query(
+        r"
+            UPDATE ledger
+                SET balance_version = balance_version + 1
+                WHERE id = ?1
+                    AND balance_version = ?2
+        ",
+    )
+    .bind(ledger_id)
+    .bind(balance_version)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        let exists = sqlx::query(
+            r"
+                SELECT id
+                FROM ledger
+                    WHERE id = ?1
+            ",
+        )
+        .bind(ledger_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+        return Err(if exists {
+            conflict(ledger_id)
+        } else {
+            AppError::NotFound
+        });
+    }
+
+    Ok(())
+}
+
+/// Increment the versions of all ledgers and replace their given balances, see `ensure_versions`
+async fn next_versions(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ledgers: &[LedgerBalances],
+) -> Result<(), AppError> {
+    sqlx::query(
+        r"
+            UPDATE ledger
+                SET balance_version = balance_version + 1
+        ",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let current = sqlx::query(
+        r"
+            SELECT id, balance_version - 1 AS balance_version
+            FROM ledger
+        ",
+    )
+    .try_map(|row: SqliteRow| Ok((row.try_get("id")?, row.try_get("balance_version")?)))
+    .fetch_all(&mut *tx)
+    .await?;
+
+    ensure_versions(&current, ledgers)?;
+
+    for ledger in ledgers {
+        if let Some(balances) = &ledger.balances {
+            replace_balances(tx, ledger.ledger_id, balances).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace all stored balances of the ledger, returns the replaced ones
+async fn replace_balances(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    ledger_id: Uuid,
+    balances: &[entity::Balance],
+) -> Result<Vec<entity::Balance>, AppError> {
+    let replaced = sqlx::query(
+        r"
+            SELECT *
+            FROM balance
+                WHERE ledger_id = ?1
+        ",
+    )
+    .bind(ledger_id)
+    .try_map(|row| balance(&row))
+    .fetch_all(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r"
+            DELETE
+                FROM balance
+                    WHERE ledger_id = ?1
+        ",
+    )
+    .bind(ledger_id)
+    .execute(&mut *tx)
+    .await?;
+
+    for balance in balances {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO balance
+                        (ledger_id, payer_account_id, lender_account_id, amount)
+                    VALUES
+                        (       ?1,               ?2,                ?3,     ?4)
+            ",
+        )
+        .bind(ledger_id)
+        .bind(balance.payer_account_id)
+        .bind(balance.lender_account_id)
+        .bind(balance.amount)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(replaced)
+}
+
+pub struct SqliteLedgers(pub SqlitePool);
+
+#[async_trait]
+impl LedgerRepository for SqliteLedgers {
+    async fn get(&self, ledger_id: Uuid) -> Result<entity::Ledger, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM ledger
+                    WHERE id = ?1
+            ",
+        )
+        .bind(ledger_id)
+        .try_map(|row| ledger(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::Ledger>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT * FROM ledger
+            ",
+        )
+        .try_map(|row| ledger(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(&self, ledger: &entity::Ledger) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO ledger
+                        (id, name, base_currency)
+                    VALUES
+                        (?1,   ?2,            ?3)
+            ",
+        )
+        .bind(ledger.id)
+        .bind(&ledger.name)
+        .bind(&ledger.base_currency)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        ledger: &entity::Ledger,
+        balances: Option<&[entity::Balance]>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger.id, ledger.balance_version).await?;
+
+        sqlx::query(
+            r"
+                UPDATE ledger
+                    SET name = ?2, base_currency = ?3
+                    WHERE id = ?1
+            ",
+        )
+        .bind(ledger.id)
+        .bind(&ledger.name)
+        .bind(&ledger.base_currency)
+        .execute(&mut tx)
+        .await?;
+
+        if let Some(balances) = balances {
+            replace_balances(&mut tx, ledger.id, balances).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, ledger_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM ledger
+                        WHERE id = ?1
+            ",
+        )
+        .bind(ledger_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+}
+
+pub struct SqliteAccounts(pub SqlitePool);
+
+#[async_trait]
+impl AccountRepository for SqliteAccounts {
+    async fn get(&self, ledger_id: Uuid, account_id: Uuid) -> Result<entity::Account, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM account
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(account_id)
+        .bind(ledger_id)
+        .try_map(|row| account(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT * FROM account
+                    WHERE ledger_id = ?1
+            ",
+        )
+        .bind(ledger_id)
+        .try_map(|row| account(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Account>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT * FROM account
+                    WHERE user_id = ?1
+            ",
+        )
+        .bind(user_id)
+        .try_map(|row| account(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn find_of_user(
+        &self,
+        ledger_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<entity::Account>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT * FROM account
+                    WHERE ledger_id = ?1
+                        AND user_id = ?2
+            ",
+        )
+        .bind(ledger_id)
+        .bind(user_id)
+        .try_map(|row| account(&row))
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn find_in_ledger(
+        &self,
+        ledger_id: Uuid,
+        account_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, AppError> {
+        // sqlite has no arrays, so every id gets its own parameter
+        let placeholders = vec!["?"; account_ids.len()].join(", ");
+        let sql = format!(
+            r"
+                SELECT id
+                FROM account
+                    WHERE ledger_id = ?
+                        AND id IN ({placeholders})
+            "
+        );
+
+        let mut query = sqlx::query_scalar(&sql).bind(ledger_id);
+        for account_id in account_ids {
+            query = query.bind(account_id);
+        }
+
+        Ok(query.fetch_all(&self.0).await?)
+    }
+
+    async fn create(&self, account: &entity::Account) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO account
+                        (id, ledger_id, name, user_id)
+                    VALUES
+                        (?1,        ?2,   ?3,      ?4)
+            ",
+        )
+        .bind(account.id)
+        .bind(account.ledger_id)
+        .bind(&account.name)
+        .bind(account.user_id)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update(&self, account: &entity::Account) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                UPDATE account
+                    SET name = ?3, user_id = ?4
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(account.id)
+        .bind(account.ledger_id)
+        .bind(&account.name)
+        .bind(account.user_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+
+    async fn delete(&self, ledger_id: Uuid, account_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM account
+                        WHERE id = ?1
+                            AND ledger_id = ?2
+            ",
+        )
+        .bind(account_id)
+        .bind(ledger_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+}
+
+pub struct SqliteCosts(pub SqlitePool);
+
+async fn insert_debts(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    debts: &[entity::Debt],
+) -> Result<(), AppError> {
+    for debt in debts {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO debt
+                        (id, debtor_account_id, cost_id, amount)
+                    VALUES
+                        (?1,                ?2,      ?3,     ?4)
+            ",
+        )
+        .bind(debt.id)
+        .bind(debt.debtor_account_id)
+        .bind(debt.cost_id)
+        .bind(debt.amount)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Add the changes to the stored balances of both directions
+async fn apply_changes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    changes: &[entity::Balance],
+) -> Result<(), AppError> {
+    for side in both_directions(changes) {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO balance
+                        (ledger_id, payer_account_id, lender_account_id, amount)
+                    VALUES
+                        (       ?1,               ?2,                ?3,     ?4)
+                    ON CONFLICT (payer_account_id, lender_account_id)
+                        DO UPDATE SET amount = amount + excluded.amount
+            ",
+        )
+        .bind(side.ledger_id)
+        .bind(side.payer_account_id)
+        .bind(side.lender_account_id)
+        .bind(side.amount)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl CostRepository for SqliteCosts {
+    async fn get(&self, ledger_id: Uuid, cost_id: Uuid) -> Result<entity::Cost, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM cost
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(cost_id)
+        .bind(ledger_id)
+        .try_map(|row| cost(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_for_account(
+        &self,
+        ledger_id: Uuid,
+        account_id: Uuid,
+    ) -> Result<Vec<entity::Cost>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM cost
+                    WHERE account_id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(account_id)
+        .bind(ledger_id)
+        .try_map(|row| cost(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_with_debts(
+        &self,
+        ledger_id: Uuid,
+        filter: &CostFilter,
+        page: &Page,
+    ) -> Result<Vec<(entity::Cost, entity::Debt)>, AppError> {
+        let (after_position, after_id) = page.after.unzip();
+
+        Ok(sqlx::query(
+            r"
+                WITH filtered AS (
+                    SELECT
+                        c.*,
+                        CASE WHEN ?11 THEN c.amount
+                            ELSE CAST(julianday(c.event_date) - julianday('0001-01-01') AS INTEGER) + 1 END
+                            * CASE WHEN ?12 THEN -1 ELSE 1 END AS position
+                    FROM cost c
+                    WHERE
+                        c.ledger_id = ?1
+                            AND c.event_date BETWEEN COALESCE(?2, ?15) AND COALESCE(?3, ?16)
+                            AND (?4 IS NULL OR c.account_id = ?4)
+                            AND EXISTS (
+                                SELECT 1 FROM debt d WHERE d.cost_id = c.id AND (?5 IS NULL OR d.debtor_account_id = ?5)
+                            )
+                            AND NOT EXISTS (
+                                SELECT 1 FROM json_each(?6) t
+                                    WHERE t.value NOT IN (SELECT value FROM json_each(COALESCE(c.tags, '[]')))
+                            )
+                            AND c.amount BETWEEN COALESCE(?7, c.amount) AND COALESCE(?8, c.amount)
+                            AND (?9 IS NULL OR INSTR(LOWER(c.description), LOWER(?9)) > 0)
+                ),
+                page AS (
+                    SELECT *
+                    FROM filtered f
+                    WHERE ?13 IS NULL OR (f.position, f.id) > (?13, ?14)
+                    ORDER BY f.position, f.id
+                    LIMIT ?10
+                )
+                SELECT p.*, d.id AS debt_id, d.debtor_account_id, d.cost_id, d.amount AS debt_amount
+                FROM page p
+                    JOIN debt d ON d.cost_id = p.id
+                ORDER BY p.position, p.id, d.id
+            ",
+        )
+        .bind(ledger_id)
+        .bind(filter.start_date)
+        .bind(filter.end_date)
+        .bind(filter.payer_account_id)
+        .bind(filter.debtor_account_id)
+        .bind(Json(&filter.tags))
+        .bind(filter.min_amount)
+        .bind(filter.max_amount)
+        .bind(&filter.description)
+        .bind(page.limit)
+        .bind(page.by_amount)
+        .bind(page.descending)
+        .bind(after_position)
+        .bind(after_id)
+        .bind(MIN_DATE)
+        .bind(MAX_DATE)
+        .try_map(|row| Ok((cost(&row)?, debt(&row)?)))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_tags(&self, ledger_id: Uuid) -> Result<Vec<String>, AppError> {
+        Ok(sqlx::query_scalar(
+            r"
+                SELECT DISTINCT t.value
+                FROM cost c, json_each(c.tags) t
+                    WHERE c.ledger_id = ?1
+                    ORDER BY 1
+            ",
+        )
+        .bind(ledger_id)
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        // cost and debts are only stored together, a failing debt will roll back the whole cost
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, cost.ledger_id, balance_version).await?;
+
+        sqlx::query(
+            r"
+                INSERT
+                    INTO cost
+                        (id, ledger_id, account_id, amount, currency, description, event_date, tags)
+                    VALUES
+                        (?1,        ?2,         ?3,     ?4,       ?5,          ?6,         ?7,   ?8)
+            ",
+        )
+        .bind(cost.id)
+        .bind(cost.ledger_id)
+        .bind(cost.account_id)
+        .bind(cost.amount)
+        .bind(&cost.currency)
+        .bind(&cost.description)
+        .bind(cost.event_date)
+        .bind(cost.tags.as_ref().map(Json))
+        .execute(&mut tx)
+        .await?;
+
+        insert_debts(&mut tx, debts).await?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        cost: &entity::Cost,
+        debts: &[entity::Debt],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        // the cost and its debts are replaced together, so the split can never be half updated
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, cost.ledger_id, balance_version).await?;
+
+        let result = sqlx::query(
+            r"
+                UPDATE cost
+                    SET amount = ?3, currency = ?4, description = ?5, event_date = ?6, tags = ?7
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(cost.id)
+        .bind(cost.ledger_id)
+        .bind(cost.amount)
+        .bind(&cost.currency)
+        .bind(&cost.description)
+        .bind(cost.event_date)
+        .bind(cost.tags.as_ref().map(Json))
+        .execute(&mut tx)
+        .await?;
+
+        not_found_if_unchanged(&result)?;
+
+        sqlx::query(
+            r"
+                DELETE
+                    FROM debt
+                        WHERE cost_id = ?1
+            ",
+        )
+        .bind(cost.id)
+        .execute(&mut tx)
+        .await?;
+
+        insert_debts(&mut tx, debts).await?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        cost_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM cost
+                        WHERE id = ?1
+                            AND ledger_id = ?2
+            ",
+        )
+        .bind(cost_id)
+        .bind(ledger_id)
+        .execute(&mut tx)
+        .await?;
+
+        not_found_if_unchanged(&result)?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct SqliteDebts(pub SqlitePool);
+
+#[async_trait]
+impl DebtRepository for SqliteDebts {
+    async fn get_of_cost(&self, cost_id: Uuid) -> Result<Vec<entity::Debt>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT id AS debt_id, debtor_account_id, cost_id, amount AS debt_amount
+                FROM debt
+                    WHERE cost_id = ?1
+            ",
+        )
+        .bind(cost_id)
+        .try_map(|row| debt(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+}
+
+pub struct SqliteBalances(pub SqlitePool);
+
+#[async_trait]
+impl BalanceRepository for SqliteBalances {
+    async fn get_movements(
+        &self,
+        ledger_id: Uuid,
+        currency: &str,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<Movement>, AppError> {
+        Ok(sqlx::query(
+            r"
+                WITH movement AS (
+                    SELECT c.account_id AS creditor, d.debtor_account_id AS debtor, d.amount, c.currency, c.event_date
+                        FROM debt d
+                            JOIN cost c ON c.id = d.cost_id
+                        WHERE c.ledger_id = ?1
+                            AND c.event_date BETWEEN COALESCE(?3, ?5) AND COALESCE(?4, ?6)
+                    UNION ALL
+                    SELECT p.payer_account_id, p.lender_account_id, p.amount, p.currency, p.event_date
+                        FROM payment p
+                        WHERE p.ledger_id = ?1
+                            AND p.event_date BETWEEN COALESCE(?3, ?5) AND COALESCE(?4, ?6)
+                )
+                SELECT creditor, debtor, currency, MIN(event_date) AS event_date,
+                    CASE WHEN currency = ?2 THEN SUM(amount) ELSE MIN(amount) END AS amount,
+                    CASE WHEN currency = ?2 THEN 1 ELSE COUNT(*) END AS count
+                FROM movement
+                GROUP BY creditor, debtor, currency,
+                    CASE WHEN currency = ?2 THEN NULL ELSE event_date END,
+                    CASE WHEN currency = ?2 THEN NULL ELSE amount END
+            ",
+        )
+        .bind(ledger_id)
+        .bind(currency)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(MIN_DATE)
+        .bind(MAX_DATE)
+        .try_map(|row: SqliteRow| {
+            Ok(Movement {
+                creditor_account_id: row.try_get("creditor")?,
+                debtor_account_id: row.try_get("debtor")?,
+                currency: row.try_get("currency")?,
+                event_date: row.try_get("event_date")?,
+                amount: row.try_get("amount")?,
+                count: row.try_get("count")?,
+            })
+        })
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self, ledger_id: Uuid) -> Result<Vec<entity::Balance>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM balance
+                    WHERE ledger_id = ?1
+            ",
+        )
+        .bind(ledger_id)
+        .try_map(|row| balance(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn replace_all(
+        &self,
+        ledger_id: Uuid,
+        balance_version: i64,
+        balances: &[entity::Balance],
+    ) -> Result<Vec<entity::Balance>, AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+        let replaced = replace_balances(&mut tx, ledger_id, balances).await?;
+
+        tx.commit().await?;
+
+        Ok(replaced)
+    }
+}
+
+pub struct SqlitePayments(pub SqlitePool);
+
+#[async_trait]
+impl PaymentRepository for SqlitePayments {
+    async fn get(&self, ledger_id: Uuid, payment_id: Uuid) -> Result<entity::Payment, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM payment
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(payment_id)
+        .bind(ledger_id)
+        .try_map(|row| payment(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(
+        &self,
+        ledger_id: Uuid,
+        filter: &PaymentFilter,
+        page: &Page,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        let (after_position, after_id) = page.after.unzip();
+
+        Ok(sqlx::query(
+            r"
+                WITH filtered AS (
+                    SELECT
+                        p.*,
+                        CASE WHEN ?9 THEN p.amount
+                            ELSE CAST(julianday(p.event_date) - julianday('0001-01-01') AS INTEGER) + 1 END
+                            * CASE WHEN ?10 THEN -1 ELSE 1 END AS position
+                    FROM payment p
+                    WHERE
+                        p.ledger_id = ?1
+                            AND p.event_date BETWEEN COALESCE(?2, ?13) AND COALESCE(?3, ?14)
+                            AND (?4 IS NULL OR p.payer_account_id = ?4)
+                            AND (?5 IS NULL OR p.lender_account_id = ?5)
+                            AND p.amount BETWEEN COALESCE(?6, p.amount) AND COALESCE(?7, p.amount)
+                )
+                SELECT *
+                FROM filtered f
+                WHERE ?11 IS NULL OR (f.position, f.id) > (?11, ?12)
+                ORDER BY f.position, f.id
+                LIMIT ?8
+            ",
+        )
+        .bind(ledger_id)
+        .bind(filter.start_date)
+        .bind(filter.end_date)
+        .bind(filter.payer_account_id)
+        .bind(filter.lender_account_id)
+        .bind(filter.min_amount)
+        .bind(filter.max_amount)
+        .bind(page.limit)
+        .bind(page.by_amount)
+        .bind(page.descending)
+        .bind(after_position)
+        .bind(after_id)
+        .bind(MIN_DATE)
+        .bind(MAX_DATE)
+        .try_map(|row| payment(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_for_account(
+        &self,
+        payer_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM payment
+                    WHERE payer_account_id = ?1
+                        AND event_date BETWEEN COALESCE(?2, ?4) AND COALESCE(?3, ?5)
+            ",
+        )
+        .bind(payer_account_id)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(MIN_DATE)
+        .bind(MAX_DATE)
+        .try_map(|row| payment(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_of_account(
+        &self,
+        lender_account_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM payment
+                    WHERE lender_account_id = ?1
+                        AND event_date BETWEEN COALESCE(?2, ?4) AND COALESCE(?3, ?5)
+            ",
+        )
+        .bind(lender_account_id)
+        .bind(start_date)
+        .bind(end_date)
+        .bind(MIN_DATE)
+        .bind(MAX_DATE)
+        .try_map(|row| payment(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(
+        &self,
+        ledger_id: Uuid,
+        payments: &[entity::Payment],
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        // either all payments are stored together with their balances or none
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        for payment in payments {
+            sqlx::query(
+                r"
+                    INSERT
+                        INTO payment
+                            (id, ledger_id, payer_account_id, lender_account_id, amount, currency, description, event_date)
+                        VALUES
+                            (?1,        ?2,               ?3,                ?4,     ?5,       ?6,          ?7,         ?8)
+                ",
+            )
+            .bind(payment.id)
+            .bind(payment.ledger_id)
+            .bind(payment.payer_account_id)
+            .bind(payment.lender_account_id)
+            .bind(payment.amount)
+            .bind(&payment.currency)
+            .bind(&payment.description)
+            .bind(payment.event_date)
+            .execute(&mut tx)
+            .await?;
+        }
+
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        payment: &entity::Payment,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, payment.ledger_id, balance_version).await?;
+
+        let result = sqlx::query(
+            r"
+                UPDATE payment
+                    SET lender_account_id = ?3, amount = ?4, currency = ?5, description = ?6, event_date = ?7
+                    WHERE id = ?1
+                        AND ledger_id = ?2
+            ",
+        )
+        .bind(payment.id)
+        .bind(payment.ledger_id)
+        .bind(payment.lender_account_id)
+        .bind(payment.amount)
+        .bind(&payment.currency)
+        .bind(&payment.description)
+        .bind(payment.event_date)
+        .execute(&mut tx)
+        .await?;
+
+        not_found_if_unchanged(&result)?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        ledger_id: Uuid,
+        payment_id: Uuid,
+        changes: &[entity::Balance],
+        balance_version: i64,
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_version(&mut tx, ledger_id, balance_version).await?;
+
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM payment
+                        WHERE id = ?1
+                            AND ledger_id = ?2
+            ",
+        )
+        .bind(payment_id)
+        .bind(ledger_id)
+        .execute(&mut tx)
+        .await?;
+
+        not_found_if_unchanged(&result)?;
+        apply_changes(&mut tx, changes).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct SqliteExchangeRates(pub SqlitePool);
+
+#[async_trait]
+impl ExchangeRateRepository for SqliteExchangeRates {
+    async fn get(&self, exchange_rate_id: Uuid) -> Result<entity::ExchangeRate, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM exchange_rate
+                    WHERE id = ?1
+            ",
+        )
+        .bind(exchange_rate_id)
+        .try_map(|row| exchange_rate(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::ExchangeRate>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM exchange_rate
+                    ORDER BY date DESC
+            ",
+        )
+        .try_map(|row| exchange_rate(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(
+        &self,
+        exchange_rate: &entity::ExchangeRate,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_versions(&mut tx, ledgers).await?;
+
+        sqlx::query(
+            r"
+                INSERT
+                    INTO exchange_rate
+                        (id, date, from_currency, to_currency, rate)
+                    VALUES
+                        (?1,   ?2,            ?3,          ?4,   ?5)
+            ",
+        )
+        .bind(exchange_rate.id)
+        .bind(exchange_rate.date)
+        .bind(&exchange_rate.from_currency)
+        .bind(&exchange_rate.to_currency)
+        .bind(exchange_rate.rate)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(
+        &self,
+        exchange_rate_id: Uuid,
+        ledgers: &[LedgerBalances],
+    ) -> Result<(), AppError> {
+        let mut tx = self.0.begin().await?;
+
+        next_versions(&mut tx, ledgers).await?;
+
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM exchange_rate
+                        WHERE id = ?1
+            ",
+        )
+        .bind(exchange_rate_id)
+        .execute(&mut tx)
+        .await?;
+
+        not_found_if_unchanged(&result)?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+pub struct SqliteUsers(pub SqlitePool);
+
+#[async_trait]
+impl UserRepository for SqliteUsers {
+    async fn get(&self, user_id: Uuid) -> Result<entity::User, AppError> {
+        Ok(sqlx::query(
+            r#"
+                SELECT id, role, creation_date
+                FROM "user"
+                    WHERE id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .try_map(|row| user(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all(&self) -> Result<Vec<entity::User>, AppError> {
+        Ok(sqlx::query(
+            r#"
+                SELECT id, role, creation_date
+                FROM "user"
+                    ORDER BY creation_date
+            "#,
+        )
+        .try_map(|row| user(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn create(&self, provider: &str, subject: &str, role: Role) -> Result<Uuid, AppError> {
+        let uuid = Uuid::new_v4();
+
+        let mut tx = self.0.begin().await?;
+
+        sqlx::query(
+            r#"
+                INSERT
+                    INTO "user"
+                        (id, role)
+                    VALUES
+                        (?1,   ?2)
+            "#,
+        )
+        .bind(uuid)
+        .bind(role_name(role))
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r"
+                INSERT
+                    INTO identity
+                        (provider, subject, user_id)
+                    VALUES
+                        (?1,            ?2,      ?3)
+            ",
+        )
+        .bind(provider.to_lowercase())
+        .bind(subject)
+        .bind(uuid)
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(uuid)
+    }
+
+    async fn update_role(&self, user_id: Uuid, role: Role) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+                UPDATE "user"
+                    SET role = ?2
+                    WHERE id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .bind(role_name(role))
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+
+    async fn delete(&self, user_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+                DELETE
+                    FROM "user"
+                        WHERE id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+
+    async fn get_identities(&self, user_id: Uuid) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM identity
+                    WHERE user_id = ?1
+                    ORDER BY creation_date
+            ",
+        )
+        .bind(user_id)
+        .try_map(|row| identity(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn get_all_identities(&self) -> Result<Vec<entity::Identity>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT *
+                FROM identity
+                    ORDER BY creation_date
+            ",
+        )
+        .try_map(|row| identity(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn find_by_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        Ok(sqlx::query_scalar(
+            r"
+                SELECT user_id
+                FROM identity
+                    WHERE provider = ?1
+                        AND subject = ?2
+            ",
+        )
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn add_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO identity
+                        (provider, subject, user_id)
+                    VALUES
+                        (?1,            ?2,      ?3)
+            ",
+        )
+        .bind(provider)
+        .bind(subject)
+        .bind(user_id)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+        username: &str,
+        avatar: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                UPDATE identity
+                    SET username = ?3, avatar = ?4
+                    WHERE provider = ?1
+                        AND subject = ?2
+            ",
+        )
+        .bind(provider)
+        .bind(subject)
+        .bind(username)
+        .bind(avatar)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+}
+
+pub struct SqliteSessions(pub SqlitePool);
+
+#[async_trait]
+impl SessionRepository for SqliteSessions {
+    async fn create(
+        &self,
+        session: &NewSession,
+        access_lifetime: chrono::Duration,
+        refresh_lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO session
+                        (id, user_id, username, user_agent, ip,
+                         token_hash, expires_at,
+                         refresh_token_hash, refresh_expires_at)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5,
+                         ?6, datetime('now', ?7),
+                         ?8, datetime('now', ?9))
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(session.user_id)
+        .bind(&session.username)
+        .bind(&session.user_agent)
+        .bind(&session.ip)
+        .bind(&session.token_hash)
+        .bind(expiry(access_lifetime))
+        .bind(&session.refresh_token_hash)
+        .bind(expiry(refresh_lifetime))
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user(&self, token_hash: &str) -> Result<Option<TokenUser>, AppError> {
+        Ok(sqlx::query(
+            r#"
+                SELECT u.id AS user_id, s.id AS session_id, s.username, u.role
+                FROM session s
+                    JOIN "user" u ON u.id = s.user_id
+                WHERE s.token_hash = ?1
+                    AND s.expires_at > CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(token_hash)
+        .try_map(|row| token_user(&row))
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn refresh(
+        &self,
+        refresh_token_hash: &str,
+        token_hash: &str,
+        access_lifetime: chrono::Duration,
+        new_refresh_token_hash: &str,
+    ) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                UPDATE session
+                    SET token_hash = ?2,
+                        expires_at = datetime('now', ?3),
+                        refresh_token_hash = ?4,
+                        previous_refresh_token_hash = refresh_token_hash
+                    WHERE refresh_token_hash = ?1
+                        AND refresh_expires_at > CURRENT_TIMESTAMP
+            ",
+        )
+        .bind(refresh_token_hash)
+        .bind(token_hash)
+        .bind(expiry(access_lifetime))
+        .bind(new_refresh_token_hash)
+        .execute(&self.0)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::Forbidden);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_reused(&self, refresh_token_hash: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM session
+                        WHERE previous_refresh_token_hash = ?1
+            ",
+        )
+        .bind(refresh_token_hash)
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_all_of_user(&self, user_id: Uuid) -> Result<Vec<entity::Session>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT id, user_agent, ip, creation_date, refresh_expires_at
+                FROM session
+                    WHERE user_id = ?1
+                    ORDER BY creation_date DESC
+            ",
+        )
+        .bind(user_id)
+        .try_map(|row| session(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn delete(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM session
+                        WHERE id = ?1
+                            AND user_id = ?2
+            ",
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+
+    async fn delete_all_except(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                DELETE
+                    FROM session
+                        WHERE user_id = ?1
+                            AND id != ?2
+            ",
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM session
+                        WHERE refresh_expires_at <= CURRENT_TIMESTAMP
+            ",
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct SqliteOAuth(pub SqlitePool);
+
+#[async_trait]
+impl OAuthRepository for SqliteOAuth {
+    async fn store_state(
+        &self,
+        state: &OAuthState,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO oauth_state
+                        (csrf_token, provider, origin_uri, pkce_verifier, nonce, link_user_id, expires_at)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5, ?6, datetime('now', ?7))
+            ",
+        )
+        .bind(&state.csrf_token)
+        .bind(&state.provider)
+        .bind(&state.origin_uri)
+        .bind(&state.pkce_verifier)
+        .bind(&state.nonce)
+        .bind(state.link_user_id)
+        .bind(expiry(lifetime))
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_state(&self, csrf_token: &str) -> Result<Option<OAuthState>, AppError> {
+        Ok(sqlx::query(
+            r"
+                DELETE
+                    FROM oauth_state
+                        WHERE csrf_token = ?1
+                            AND expires_at > CURRENT_TIMESTAMP
+                    RETURNING csrf_token, provider, origin_uri, pkce_verifier, nonce, link_user_id
+            ",
+        )
+        .bind(csrf_token)
+        .try_map(|row: SqliteRow| {
+            Ok(OAuthState {
+                csrf_token: row.try_get("csrf_token")?,
+                provider: row.try_get("provider")?,
+                origin_uri: row.try_get("origin_uri")?,
+                pkce_verifier: row.try_get("pkce_verifier")?,
+                nonce: row.try_get("nonce")?,
+                link_user_id: row.try_get("link_user_id")?,
+            })
+        })
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn store_login_code(
+        &self,
+        code_hash: &str,
+        login: &LoginCode,
+        lifetime: chrono::Duration,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                INSERT
+                    INTO login_code
+                        (code_hash, user_id, username, user_agent, ip, expires_at)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5, datetime('now', ?6))
+            ",
+        )
+        .bind(code_hash)
+        .bind(login.user_id)
+        .bind(&login.username)
+        .bind(&login.user_agent)
+        .bind(&login.ip)
+        .bind(expiry(lifetime))
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_login_code(&self, code_hash: &str) -> Result<Option<LoginCode>, AppError> {
+        Ok(sqlx::query(
+            r"
+                DELETE
+                    FROM login_code
+                        WHERE code_hash = ?1
+                            AND expires_at > CURRENT_TIMESTAMP
+                    RETURNING user_id, username, user_agent, ip
+            ",
+        )
+        .bind(code_hash)
+        .try_map(|row: SqliteRow| {
+            Ok(LoginCode {
+                user_id: row.try_get("user_id")?,
+                username: row.try_get("username")?,
+                user_agent: row.try_get("user_agent")?,
+                ip: row.try_get("ip")?,
+            })
+        })
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let states = sqlx::query(
+            r"
+                DELETE
+                    FROM oauth_state
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            ",
+        )
+        .execute(&self.0)
+        .await?;
+
+        let codes = sqlx::query(
+            r"
+                DELETE
+                    FROM login_code
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            ",
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(states.rows_affected() + codes.rows_affected())
+    }
+}
+
+pub struct SqlitePersonalAccessTokens(pub SqlitePool);
+
+#[async_trait]
+impl PersonalAccessTokenRepository for SqlitePersonalAccessTokens {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        name: &str,
+        token_hash: &str,
+        scopes: &[Scope],
+        lifetime: Option<chrono::Duration>,
+    ) -> Result<entity::PersonalAccessToken, AppError> {
+        Ok(sqlx::query(
+            r"
+                INSERT
+                    INTO personal_access_token
+                        (id, user_id, name, token_hash, scopes, expires_at)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5, datetime('now', ?6))
+                    RETURNING id, name, scopes, expires_at, last_used_at, creation_date
+            ",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(name)
+        .bind(token_hash)
+        .bind(Json(scopes))
+        .bind(lifetime.map(expiry))
+        .try_map(|row| personal_access_token(&row))
+        .fetch_one(&self.0)
+        .await?)
+    }
+
+    async fn get_all_of_user(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<entity::PersonalAccessToken>, AppError> {
+        Ok(sqlx::query(
+            r"
+                SELECT id, name, scopes, expires_at, last_used_at, creation_date
+                FROM personal_access_token
+                    WHERE user_id = ?1
+                    ORDER BY creation_date DESC
+            ",
+        )
+        .bind(user_id)
+        .try_map(|row| personal_access_token(&row))
+        .fetch_all(&self.0)
+        .await?)
+    }
+
+    async fn delete(&self, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM personal_access_token
+                        WHERE id = ?1
+                            AND user_id = ?2
+            ",
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .execute(&self.0)
+        .await?;
+
+        not_found_if_unchanged(&result)
+    }
+
+    async fn use_token(
+        &self,
+        token_hash: &str,
+        scope: Scope,
+    ) -> Result<Option<TokenUser>, AppError> {
+        Ok(sqlx::query(
+            r#"
+                UPDATE personal_access_token
+                    SET last_used_at = CURRENT_TIMESTAMP
+                    WHERE token_hash = ?1
+                        AND EXISTS (SELECT 1 FROM json_each(scopes) WHERE value = ?2)
+                        AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+                    RETURNING user_id, NULL AS session_id,
+                        (SELECT u.role
+                            FROM "user" u
+                                WHERE u.id = personal_access_token.user_id) AS role,
+                        COALESCE(
+                            (SELECT i.username
+                                FROM identity i
+                                    WHERE i.user_id = personal_access_token.user_id
+                                    ORDER BY i.creation_date
+                                    LIMIT 1),
+                            name) AS username
+            "#,
+        )
+        .bind(token_hash)
+        .bind(scope_name(scope))
+        .try_map(|row| token_user(&row))
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn purge_expired(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r"
+                DELETE
+                    FROM personal_access_token
+                        WHERE expires_at <= CURRENT_TIMESTAMP
+            ",
+        )
+        .execute(&self.0)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct SqliteCredentials(pub SqlitePool);
+
+#[async_trait]
+impl CredentialRepository for SqliteCredentials {
+    async fn invite(
+        &self,
+        provider: &str,
+        username: &str,
+        role: Role,
+        registration_code_hash: &str,
+        registration_lifetime: chrono::Duration,
+    ) -> Result<Uuid, AppError> {
+        let user_id = Uuid::new_v4();
+
+        let mut tx = self.0.begin().await?;
+
+        sqlx::query(
+            r#"
+                INSERT
+                    INTO "user"
+                        (id, role)
+                    VALUES
+                        (?1,   ?2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(role_name(role))
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r"
+                INSERT
+                    INTO identity
+                        (provider, subject, user_id)
+                    VALUES
+                        (?1,            ?2,      ?3)
+            ",
+        )
+        .bind(provider.to_lowercase())
+        .bind(username)
+        .bind(user_id)
+        .execute(&mut tx)
+        .await?;
+
+        sqlx::query(
+            r"
+                INSERT
+                    INTO local_credential
+                        (username, user_id, registration_code_hash, registration_expires_at)
+                    VALUES
+                        (?1, ?2, ?3, datetime('now', ?4))
+            ",
+        )
+        .bind(username)
+        .bind(user_id)
+        .bind(registration_code_hash)
+        .bind(expiry(registration_lifetime))
+        .execute(&mut tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user_id)
+    }
+
+    async fn register(
+        &self,
+        username: &str,
+        registration_code_hash: &str,
+        password_hash: &str,
+    ) -> Result<Option<Uuid>, AppError> {
+        Ok(sqlx::query_scalar(
+            r"
+                UPDATE local_credential
+                    SET password_hash = ?3,
+                        registration_code_hash = NULL,
+                        registration_expires_at = NULL
+                    WHERE username = ?1
+                        AND registration_code_hash = ?2
+                        AND registration_expires_at > CURRENT_TIMESTAMP
+                    RETURNING user_id
+            ",
+        )
+        .bind(username)
+        .bind(registration_code_hash)
+        .bind(password_hash)
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn count_attempt(
+        &self,
+        username: &str,
+        max_failed_attempts: i32,
+        lockout: chrono::Duration,
+    ) -> Result<Option<Credential>, AppError> {
+        Ok(sqlx::query(
+            r"
+                UPDATE local_credential
+                    SET failed_attempts = CASE
+                            WHEN failed_attempts + 1 >= ?2 THEN 0
+                            ELSE failed_attempts + 1
+                        END,
+                        locked_until = CASE
+                            WHEN failed_attempts + 1 >= ?2 THEN datetime('now', ?3)
+                            ELSE locked_until
+                        END
+                    WHERE username = ?1
+                        AND password_hash IS NOT NULL
+                        AND NOT COALESCE(locked_until > CURRENT_TIMESTAMP, FALSE)
+                    RETURNING
+                        user_id,
+                        password_hash,
+                        COALESCE(locked_until > CURRENT_TIMESTAMP, FALSE) AS locked
+            ",
+        )
+        .bind(username)
+        .bind(max_failed_attempts)
+        .bind(expiry(lockout))
+        .try_map(|row: SqliteRow| {
+            Ok(Credential {
+                user_id: row.try_get("user_id")?,
+                password_hash: row.try_get("password_hash")?,
+                locked: row.try_get("locked")?,
+            })
+        })
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn reset_attempts(&self, username: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                UPDATE local_credential
+                    SET failed_attempts = 0, locked_until = NULL
+                    WHERE username = ?1
+            ",
+        )
+        .bind(username)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_username(&self, user_id: Uuid) -> Result<Option<String>, AppError> {
+        Ok(sqlx::query_scalar(
+            r"
+                SELECT username
+                FROM local_credential
+                    WHERE user_id = ?1
+                        AND password_hash IS NOT NULL
+            ",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.0)
+        .await?)
+    }
+
+    async fn update_password(&self, user_id: Uuid, password_hash: &str) -> Result<(), AppError> {
+        sqlx::query(
+            r"
+                UPDATE local_credential
+                    SET password_hash = ?2
+                    WHERE user_id = ?1
+            ",
+        )
+        .bind(user_id)
+        .bind(password_hash)
+        .execute(&self.0)
+        .await?;
+
+        Ok(())
+    }
+}
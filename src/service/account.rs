@@ -1,78 +1,163 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::model::entity;
+use crate::model::dto::auth::AuthUser;
+use crate::model::entity::{self, Role};
+use crate::repository::Repositories;
 use crate::service;
 
-pub async fn get(pool: &PgPool, account_id: Uuid) -> Result<entity::Account, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Account,
-        r#"
-            SELECT *
-            FROM account
-                WHERE id = $1
-        "#,
-        account_id
-    )
-    .fetch_one(pool)
-    .await?)
+pub async fn get(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+) -> Result<entity::Account, AppError> {
+    repos.accounts.get(ledger_id, account_id).await
 }
 
-pub async fn get_all(pool: &PgPool) -> Result<Vec<entity::Account>, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Account,
-        r#"
-            SELECT * FROM account
-        "#
-    )
-    .fetch_all(pool)
-    .await?)
+pub async fn get_all(
+    repos: &Repositories,
+    ledger_id: Uuid,
+) -> Result<Vec<entity::Account>, AppError> {
+    repos.accounts.get_all(ledger_id).await
 }
 
-pub async fn create(pool: &PgPool, account_name: String) -> Result<entity::Account, AppError> {
-    let uuid = Uuid::new_v4();
-
-    sqlx::query!(
-        r#"
-            INSERT
-                INTO account
-                    (id, name)
-                VALUES
-                    ($1,   $2)
-        "#,
-        &uuid,
-        account_name,
-    )
-    .execute(pool)
-    .await?;
-
-    get(pool, uuid).await
+/// All accounts linked to the user, over all ledgers
+pub async fn get_all_of_user(
+    repos: &Repositories,
+    user_id: Uuid,
+) -> Result<Vec<entity::Account>, AppError> {
+    repos.accounts.get_all_of_user(user_id).await
 }
 
-pub async fn delete(pool: &PgPool, account_id: Uuid) -> Result<(), AppError> {
-    let result = sqlx::query!(
-        r#"
-            DELETE
-                FROM account
-                    WHERE id = $1
-        "#,
-        account_id,
-    )
-    .execute(pool)
-    .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound);
+/// The account of the user in the ledger, which is used if no account is given
+pub async fn get_of_user(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    user_id: Uuid,
+) -> Result<entity::Account, AppError> {
+    repos
+        .accounts
+        .find_of_user(ledger_id, user_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::Service(format!(
+                "user {user_id} has no account in ledger {ledger_id}"
+            ))
+        })
+}
+
+/// Make sure the account belongs to the user, only admins can act for other accounts
+pub async fn ensure_owned_by(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    user: &AuthUser,
+) -> Result<(), AppError> {
+    if user.role == Role::Admin {
+        return Ok(());
+    }
+
+    let account = get(repos, ledger_id, account_id).await?;
+    if account.user_id != Some(user.id) {
+        return Err(AppError::Forbidden);
     }
 
     Ok(())
 }
 
-pub async fn get_tags(pool: &PgPool, account_id: Uuid) -> Result<Vec<String>, AppError> {
-    let costs = service::cost::get_for_account(pool, account_id).await?;
+/// Balance of the account in the base currency of the ledger, positive if others owe the account
+pub async fn get_balance(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+) -> Result<i64, AppError> {
+    let snapshot = service::cost::get_current_snapshot(repos, ledger_id, None, None).await?;
+
+    Ok(snapshot
+        .iter()
+        .filter(|debt| debt.payer_account.id == account_id)
+        .map(|debt| debt.amount.cents())
+        .sum())
+}
+
+pub async fn create(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_name: String,
+    user_id: Option<Uuid>,
+) -> Result<entity::Account, AppError> {
+    let account = entity::Account {
+        id: Uuid::new_v4(),
+        ledger_id,
+        name: account_name,
+        user_id,
+    };
+
+    repos.accounts.create(&account).await?;
+
+    get(repos, ledger_id, account.id).await
+}
+
+/// Make sure the user may link the account to `user_id`, given the user it is linked to now
+///
+/// Admins may link any user, members only themselves and only to accounts without a user
+pub fn ensure_may_link(
+    user: &AuthUser,
+    current: Option<Uuid>,
+    user_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    if user.role == Role::Admin || current == user_id {
+        return Ok(());
+    }
+
+    if current.is_none() && user_id == Some(user.id) {
+        return Ok(());
+    }
+
+    Err(AppError::Forbidden)
+}
+
+/// Rename the account, it stays linked to its user if no other user is given
+pub async fn update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    account_name: String,
+    user_id: Option<Uuid>,
+    user: &AuthUser,
+) -> Result<entity::Account, AppError> {
+    let current = get(repos, ledger_id, account_id).await?;
+    let user_id = user_id.or(current.user_id);
+    ensure_may_link(user, current.user_id, user_id)?;
+
+    let account = entity::Account {
+        id: account_id,
+        ledger_id,
+        name: account_name,
+        user_id,
+    };
+
+    repos.accounts.update(&account).await?;
+
+    get(repos, ledger_id, account_id).await
+}
+
+pub async fn delete(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+) -> Result<(), AppError> {
+    repos.accounts.delete(ledger_id, account_id).await
+}
+
+pub async fn get_tags(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    let costs = service::cost::get_for_account(repos, ledger_id, account_id).await?;
 
     // map tags, sort and remove duplicate values
     let result = costs
@@ -80,10 +165,36 @@ pub async fn get_tags(pool: &PgPool, account_id: Uuid) -> Result<Vec<String>, Ap
         .cloned()
         .filter_map(|e| e.tags)
         .flatten()
-        .collect::<HashSet<_>>()
-        .iter()
-        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
         .collect::<Vec<_>>();
 
     Ok(result)
 }
+
+/// Make sure all given accounts belong to the ledger, so no entry can reference another ledger
+pub async fn ensure_in_ledger(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_ids: &[Uuid],
+) -> Result<(), AppError> {
+    let account_ids = account_ids
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let found = repos
+        .accounts
+        .find_in_ledger(ledger_id, &account_ids)
+        .await?;
+
+    if let Some(missing) = account_ids.iter().find(|id| !found.contains(id)) {
+        return Err(AppError::Service(format!(
+            "account {missing} does not exist in ledger {ledger_id}"
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::{dto::response, entity, money::Money};
+use crate::repository::{LedgerBalances, Repositories};
+use crate::service;
+use crate::service::exchange_rate::ExchangeRates;
+
+/// Attempts of a write before its conflict is returned, see `retry`
+const MAX_ATTEMPTS: usize = 5;
+
+/// Run the write again while the balances it calculated its changes from were changed
+/// concurrently, every attempt has to read the ledger (and its balance version) again
+pub async fn retry<T, F, Fut>(mut write: F) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match write().await {
+            Err(AppError::Conflict(_)) if attempt < MAX_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Changes of the balances by the debts of the cost, the debtors owe the payer
+pub fn of_cost(
+    rates: &ExchangeRates,
+    cost: &entity::Cost,
+    debts: &[entity::Debt],
+) -> Result<Vec<entity::Balance>, AppError> {
+    debts
+        .iter()
+        .map(|debt| {
+            Ok(entity::Balance {
+                ledger_id: cost.ledger_id,
+                payer_account_id: cost.account_id,
+                lender_account_id: debt.debtor_account_id,
+                amount: rates.convert(debt.amount, &cost.currency, cost.event_date)?,
+            })
+        })
+        .collect()
+}
+
+/// Change of the balance by the payment, the lender owes the payment back to its payer
+pub fn of_payment(
+    rates: &ExchangeRates,
+    payment: &entity::Payment,
+) -> Result<entity::Balance, AppError> {
+    Ok(entity::Balance {
+        ledger_id: payment.ledger_id,
+        payer_account_id: payment.payer_account_id,
+        lender_account_id: payment.lender_account_id,
+        amount: rates.convert(payment.amount, &payment.currency, payment.event_date)?,
+    })
+}
+
+/// Changes that undo the given ones, e.g. of a cost that is removed
+pub fn reverted(changes: Vec<entity::Balance>) -> Vec<entity::Balance> {
+    changes
+        .into_iter()
+        .map(|change| entity::Balance {
+            amount: -change.amount,
+            ..change
+        })
+        .collect()
+}
+
+/// Calculate the balances from all debts and payments in the given range, without the stored ones
+///
+/// Every pair is part of the result in both directions, debts of accounts to themselves are not
+pub async fn calculate(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    rates: &ExchangeRates,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
+) -> Result<Vec<entity::Balance>, AppError> {
+    // summed up by the storage in one query, instead of loading all debts and payments per account
+    let movements = repos
+        .balances
+        .get_movements(ledger_id, rates.currency(), start_date, end_date)
+        .await?;
+
+    // the overall debt of the lender account to the payer account
+    let mut results: HashMap<(Uuid, Uuid), i64> = HashMap::new();
+    for movement in movements {
+        let amount = rates.convert(movement.amount, &movement.currency, movement.event_date)?
+            * movement.count;
+
+        *results
+            .entry((movement.creditor_account_id, movement.debtor_account_id))
+            .or_insert(0) += amount;
+        *results
+            .entry((movement.debtor_account_id, movement.creditor_account_id))
+            .or_insert(0) -= amount;
+    }
+
+    Ok(results
+        .into_iter()
+        .filter(|((payer, lender), _)| payer != lender)
+        .map(|((payer, lender), amount)| entity::Balance {
+            ledger_id,
+            payer_account_id: payer,
+            lender_account_id: lender,
+            amount,
+        })
+        .collect())
+}
+
+/// Calculate the balances of the ledgers with the given rates instead of the stored ones
+///
+/// The ledgers have to be read before the rates, so the calculated balances are not older than
+/// their balance versions. Ledgers with amounts the rates can not convert are part of the result
+/// with their error
+pub async fn calculate_all(
+    repos: &Repositories,
+    ledgers: Vec<entity::Ledger>,
+    rates: &[entity::ExchangeRate],
+) -> Vec<(entity::Ledger, Result<Vec<entity::Balance>, AppError>)> {
+    let mut results = Vec::new();
+    for ledger in ledgers {
+        let rates = ExchangeRates::new(ledger.base_currency.clone(), rates.to_vec());
+        let balances = calculate(repos, ledger.id, &rates, None, None).await;
+
+        results.push((ledger, balances));
+    }
+
+    results
+}
+
+/// The calculated balances of the ledger, to be stored if they are still of its balance version
+pub fn of_ledger(
+    ledger: &entity::Ledger,
+    balances: Option<Vec<entity::Balance>>,
+) -> LedgerBalances {
+    LedgerBalances {
+        ledger_id: ledger.id,
+        balance_version: ledger.balance_version,
+        balances,
+    }
+}
+
+/// Recalculate the stored balances of every ledger from scratch, one ledger after another
+///
+/// Returns all pairs whose stored balance was different from the calculated one
+pub async fn rebuild(repos: &Repositories) -> Result<Vec<response::BalanceDriftDto>, AppError> {
+    let mut drifts = Vec::new();
+    for ledger in service::ledger::get_all(repos).await? {
+        drifts.extend(retry(|| rebuild_ledger(repos, ledger.id)).await?);
+    }
+
+    Ok(drifts)
+}
+
+/// One attempt to recalculate the stored balances of the ledger, returns their drift
+async fn rebuild_ledger(
+    repos: &Repositories,
+    ledger_id: Uuid,
+) -> Result<Vec<response::BalanceDriftDto>, AppError> {
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency.clone()).await?;
+    let calculated = calculate(repos, ledger.id, &rates, None, None).await?;
+    let stored = repos
+        .balances
+        .replace_all(ledger.id, ledger.balance_version, &calculated)
+        .await?;
+
+    Ok(drift(&stored, &calculated, &ledger.base_currency))
+}
+
+/// Pairs with different amounts, a missing pair has an amount of 0
+fn drift(
+    stored: &[entity::Balance],
+    calculated: &[entity::Balance],
+    currency: &str,
+) -> Vec<response::BalanceDriftDto> {
+    let mut amounts: HashMap<(Uuid, Uuid, Uuid), (i64, i64)> = HashMap::new();
+    for balance in stored {
+        amounts
+            .entry((
+                balance.ledger_id,
+                balance.payer_account_id,
+                balance.lender_account_id,
+            ))
+            .or_default()
+            .0 += balance.amount;
+    }
+    for balance in calculated {
+        amounts
+            .entry((
+                balance.ledger_id,
+                balance.payer_account_id,
+                balance.lender_account_id,
+            ))
+            .or_default()
+            .1 += balance.amount;
+    }
+
+    let mut drifts = amounts
+        .into_iter()
+        .filter(|(_, (stored, calculated))| stored != calculated)
+        .map(
+            |((ledger_id, payer, lender), (stored, calculated))| response::BalanceDriftDto {
+                ledger_id,
+                payer_account_id: payer,
+                lender_account_id: lender,
+                stored: Money::from_cents(stored),
+                calculated: Money::from_cents(calculated),
+                currency: currency.to_string(),
+            },
+        )
+        .collect::<Vec<_>>();
+    drifts.sort_by_key(|d| (d.payer_account_id, d.lender_account_id));
+
+    drifts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::dto::request;
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+    }
+
+    fn payment(lender_account_id: Uuid, amount: &str, currency: &str) -> request::CreatePaymentDto {
+        request::CreatePaymentDto {
+            lender_account_id,
+            amount: amount.parse().unwrap(),
+            currency: Some(currency.to_string()),
+            event_date: date(2),
+            description: None,
+        }
+    }
+
+    fn stored(balances: &[entity::Balance], payer: Uuid, lender: Uuid) -> Option<i64> {
+        balances
+            .iter()
+            .find(|b| b.payer_account_id == payer && b.lender_account_id == lender)
+            .map(|b| b.amount)
+    }
+
+    struct Setup {
+        repos: Repositories,
+        ledger_id: Uuid,
+        alice: Uuid,
+        bob: Uuid,
+    }
+
+    async fn setup() -> Setup {
+        let repos = Repositories::memory();
+        let ledger = service::ledger::create(&repos, "Flat".to_string(), None)
+            .await
+            .unwrap();
+        let alice = service::account::create(&repos, ledger.id, "Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = service::account::create(&repos, ledger.id, "Bob".to_string(), None)
+            .await
+            .unwrap();
+
+        Setup {
+            repos,
+            ledger_id: ledger.id,
+            alice: alice.id,
+            bob: bob.id,
+        }
+    }
+
+    #[tokio::test]
+    async fn payments_are_stored_in_both_directions_and_reverted() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        let first = service::payment::create(&repos, ledger_id, alice, payment(bob, "10.0", "EUR"))
+            .await
+            .unwrap();
+        service::payment::create(&repos, ledger_id, bob, payment(alice, "4.0", "EUR"))
+            .await
+            .unwrap();
+
+        let balances = repos.balances.get_all(ledger_id).await.unwrap();
+        assert_eq!(stored(&balances, alice, bob), Some(600));
+        assert_eq!(stored(&balances, bob, alice), Some(-600));
+
+        service::payment::update(&repos, ledger_id, first.id, payment(bob, "5.0", "EUR"))
+            .await
+            .unwrap();
+        let balances = repos.balances.get_all(ledger_id).await.unwrap();
+        assert_eq!(stored(&balances, alice, bob), Some(100));
+
+        service::payment::delete(&repos, ledger_id, first.id)
+            .await
+            .unwrap();
+        let balances = repos.balances.get_all(ledger_id).await.unwrap();
+        assert_eq!(stored(&balances, alice, bob), Some(-400));
+        assert_eq!(stored(&balances, bob, alice), Some(400));
+        assert!(rebuild(&repos).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rebuild_reports_and_repairs_drift() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+        service::payment::create(&repos, ledger_id, alice, payment(bob, "10.0", "EUR"))
+            .await
+            .unwrap();
+
+        // e.g. changed by hand in the database
+        repos
+            .balances
+            .replace_all(
+                ledger_id,
+                service::ledger::get(&repos, ledger_id)
+                    .await
+                    .unwrap()
+                    .balance_version,
+                &[entity::Balance {
+                    ledger_id,
+                    payer_account_id: alice,
+                    lender_account_id: bob,
+                    amount: 700,
+                }],
+            )
+            .await
+            .unwrap();
+
+        let drifts = rebuild(&repos).await.unwrap();
+        let mut drifts = drifts
+            .iter()
+            .map(|d| {
+                (
+                    d.payer_account_id,
+                    d.lender_account_id,
+                    d.stored.cents(),
+                    d.calculated.cents(),
+                )
+            })
+            .collect::<Vec<_>>();
+        drifts.sort_unstable();
+        let mut expected = vec![(alice, bob, 700, 1000), (bob, alice, 0, -1000)];
+        expected.sort_unstable();
+        assert_eq!(drifts, expected);
+
+        assert!(rebuild(&repos).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn new_rates_are_applied_to_stored_balances() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        let first = service::exchange_rate::create(
+            &repos,
+            date(1),
+            "USD".to_string(),
+            "EUR".to_string(),
+            0.5,
+        )
+        .await
+        .unwrap();
+        service::payment::create(&repos, ledger_id, alice, payment(bob, "10.0", "USD"))
+            .await
+            .unwrap();
+
+        // a newer rate that is valid on the date of the payment
+        service::exchange_rate::create(&repos, date(2), "USD".to_string(), "EUR".to_string(), 0.8)
+            .await
+            .unwrap();
+        let balances = repos.balances.get_all(ledger_id).await.unwrap();
+        assert_eq!(stored(&balances, alice, bob), Some(800));
+
+        // without the first rate the payment can still be converted, but not without both
+        service::exchange_rate::delete(&repos, first.id)
+            .await
+            .unwrap();
+        assert!(rebuild(&repos).await.unwrap().is_empty());
+
+        let last = service::exchange_rate::get_all(&repos).await.unwrap();
+        assert!(matches!(
+            service::exchange_rate::delete(&repos, last[0].id).await,
+            Err(AppError::Service(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn writes_of_outdated_balances_conflict() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+        let outdated = service::ledger::get(&repos, ledger_id).await.unwrap();
+        service::payment::create(&repos, ledger_id, alice, payment(bob, "10.0", "EUR"))
+            .await
+            .unwrap();
+
+        // calculated before the payment was stored
+        assert!(matches!(
+            repos
+                .balances
+                .replace_all(ledger_id, outdated.balance_version, &[])
+                .await,
+            Err(AppError::Conflict(_))
+        ));
+
+        // rates apply to every ledger, so their writes have to cover all current ones
+        let rate = entity::ExchangeRate {
+            id: Uuid::new_v4(),
+            date: date(1),
+            from_currency: "USD".to_string(),
+            to_currency: "EUR".to_string(),
+            rate: 0.5,
+        };
+        assert!(matches!(
+            repos
+                .exchange_rates
+                .create(&rate, &[of_ledger(&outdated, Some(Vec::new()))])
+                .await,
+            Err(AppError::Conflict(_))
+        ));
+        assert!(matches!(
+            repos.exchange_rates.create(&rate, &[]).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        let balances = repos.balances.get_all(ledger_id).await.unwrap();
+        assert_eq!(stored(&balances, alice, bob), Some(1000));
+        assert!(service::exchange_rate::get_all(&repos)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(rebuild(&repos).await.unwrap().is_empty());
+    }
+}
@@ -1,357 +1,730 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::helper::Conversion;
 use crate::model::{
     dto::{request, response},
     entity,
+    money::Money,
+    percentage::Percentage,
 };
+use crate::repository::{CostFilter, Repositories};
 use crate::service;
 
 pub async fn create(
-    pool: &PgPool,
+    repos: &Repositories,
+    ledger_id: Uuid,
     account_id: Uuid,
-    debtors: Vec<request::CreateDebtorDto>,
-    amount: f64,
-    description: Option<String>,
-    event_date: chrono::NaiveDate,
-    tags: Option<Vec<String>>,
-) -> Result<entity::Cost, AppError> {
-    struct CreateDebtor {
-        account_id: Uuid,
-        amount: i64,
+    cost: request::CreateCostDto,
+) -> Result<response::CostDto, AppError> {
+    let cost_id =
+        service::balance::retry(|| try_create(repos, ledger_id, account_id, &cost)).await?;
+
+    get_with_debtors(repos, ledger_id, cost_id).await
+}
+
+/// One attempt of `create`, fails with a conflict if the balances changed in between
+async fn try_create(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    cost: &request::CreateCostDto,
+) -> Result<Uuid, AppError> {
+    let (amount, debtors) = split_debtors(cost.amount, &cost.split, &cost.debtors)?;
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let currency =
+        service::exchange_rate::parse_currency(cost.currency.clone(), &ledger.base_currency)?;
+    let tags = unique_tags(cost.tags.clone());
+
+    let mut account_ids = debtors.iter().map(|d| d.account_id).collect::<Vec<_>>();
+    account_ids.push(account_id);
+    service::account::ensure_in_ledger(repos, ledger_id, &account_ids).await?;
+
+    let cost = entity::Cost {
+        id: Uuid::new_v4(),
+        ledger_id,
+        account_id,
+        amount,
+        event_date: cost.event_date,
+        description: cost.description.clone(),
+        tags: Some(tags),
+        currency,
+    };
+
+    let debts = into_debts(cost.id, debtors);
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let changes = service::balance::of_cost(&rates, &cost, &debts)?;
+
+    repos
+        .costs
+        .create(&cost, &debts, &changes, ledger.balance_version)
+        .await?;
+
+    Ok(cost.id)
+}
+
+pub async fn update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    cost_id: Uuid,
+    cost: request::CreateCostDto,
+) -> Result<response::CostDto, AppError> {
+    service::balance::retry(|| try_update(repos, ledger_id, cost_id, &cost)).await?;
+
+    get_with_debtors(repos, ledger_id, cost_id).await
+}
+
+/// One attempt of `update`, fails with a conflict if the balances changed in between
+async fn try_update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    cost_id: Uuid,
+    cost: &request::CreateCostDto,
+) -> Result<(), AppError> {
+    let (amount, debtors) = split_debtors(cost.amount, &cost.split, &cost.debtors)?;
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let currency =
+        service::exchange_rate::parse_currency(cost.currency.clone(), &ledger.base_currency)?;
+    let tags = unique_tags(cost.tags.clone());
+
+    let account_ids = debtors.iter().map(|d| d.account_id).collect::<Vec<_>>();
+    service::account::ensure_in_ledger(repos, ledger_id, &account_ids).await?;
+
+    // read after the ledger, so the reverted debts are not older than its balance version
+    let current = get(repos, ledger_id, cost_id).await?;
+    let current_debts = get_debts(repos, cost_id).await?;
+
+    let cost = entity::Cost {
+        amount,
+        event_date: cost.event_date,
+        description: cost.description.clone(),
+        tags: Some(tags),
+        currency,
+        ..current.clone()
+    };
+    let debts = into_debts(cost_id, debtors);
+
+    // the balances lose the current debts and get the new ones
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let mut changes =
+        service::balance::reverted(service::balance::of_cost(&rates, &current, &current_debts)?);
+    changes.extend(service::balance::of_cost(&rates, &cost, &debts)?);
+
+    repos
+        .costs
+        .update(&cost, &debts, &changes, ledger.balance_version)
+        .await
+}
+
+struct CreateDebtor {
+    account_id: Uuid,
+    amount: i64,
+}
+
+fn into_debts(cost_id: Uuid, debtors: Vec<CreateDebtor>) -> Vec<entity::Debt> {
+    debtors
+        .into_iter()
+        .map(|d| entity::Debt {
+            id: Uuid::new_v4(),
+            debtor_account_id: d.account_id,
+            cost_id,
+            amount: d.amount,
+        })
+        .collect()
+}
+
+/// Convert the given split into cents per debtor, the debtors always cover exactly the whole cost
+fn split_debtors(
+    amount: Money,
+    split: &request::SplitMode,
+    debtors: &[request::CreateDebtorDto],
+) -> Result<(i64, Vec<CreateDebtor>), AppError> {
+    let amount = amount.cents();
+
+    let amounts = match split {
+        request::SplitMode::Exact => {
+            let amounts = debtors
+                .iter()
+                .map(|d| d.amount.unwrap_or_default().cents())
+                .collect::<Vec<_>>();
+
+            let debtors_amount_sum = amounts.iter().sum::<i64>();
+            if debtors_amount_sum != amount {
+                return Err(AppError::Service(format!(
+                    "sum of all debtors amount needs to be {amount} but is {debtors_amount_sum}"
+                )));
+            }
+
+            amounts
+        }
+        request::SplitMode::Equal => distribute(amount, &vec![1; debtors.len()]),
+        request::SplitMode::Percentage => {
+            // percentages are exact basis points to allow splits like 33.33%
+            let weights = debtors
+                .iter()
+                .map(|d| d.percentage.unwrap_or_default().basis_points())
+                .collect::<Vec<_>>();
+
+            let percentage_sum = Percentage::from_basis_points(weights.iter().sum());
+            if percentage_sum != Percentage::WHOLE {
+                return Err(AppError::Service(format!(
+                    "sum of all debtors percentage needs to be {} but is {percentage_sum}",
+                    Percentage::WHOLE
+                )));
+            }
+
+            distribute(amount, &weights)
+        }
+        request::SplitMode::Shares => {
+            let weights = debtors
+                .iter()
+                .map(|d| i64::from(d.shares.unwrap_or_default()))
+                .collect::<Vec<_>>();
+
+            distribute(amount, &weights)
+        }
+    };
+
+    if amounts.is_empty() || amounts.iter().any(|amount| *amount <= 0) {
+        return Err(AppError::Service(format!(
+            "amount {amount} can not be split onto {} debtors",
+            debtors.len()
+        )));
     }
 
     let debtors = debtors
         .iter()
-        .map(|d| CreateDebtor {
+        .zip(amounts)
+        .map(|(d, amount)| CreateDebtor {
             account_id: d.account_id,
-            amount: Conversion::to_int(d.amount),
+            amount,
         })
         .collect::<Vec<_>>();
 
-    let amount = Conversion::to_int(amount);
-    let debtors_amount_sum = debtors.iter().map(|d| d.amount).sum::<i64>();
-    if debtors_amount_sum != amount {
-        return Err(AppError::Service(format!(
-            "sum of all debtors amount needs to be {amount} but is {debtors_amount_sum}"
-        )));
+    Ok((amount, debtors))
+}
+
+/// Distribute the amount by the given weights (largest remainder method)
+///
+/// Every debtor gets the rounded down part of the amount, the leftover cents go to the debtors
+/// with the largest remainders, on ties to the debtor that was given first
+fn distribute(amount: i64, weights: &[i64]) -> Vec<i64> {
+    let total_weight = weights.iter().sum::<i64>();
+    if total_weight <= 0 {
+        return Vec::new();
     }
 
-    // sort and remove duplicate values
-    let tags = tags
-        .unwrap_or_default()
-        .iter()
-        .cloned()
-        .collect::<HashSet<_>>()
+    let amount = i128::from(amount);
+    let total_weight = i128::from(total_weight);
+
+    let mut parts = weights
         .iter()
-        .cloned()
+        .map(|weight| {
+            let exact = amount * i128::from(*weight);
+            (exact / total_weight, exact % total_weight)
+        })
         .collect::<Vec<_>>();
 
-    let cost_uuid = Uuid::new_v4();
-    sqlx::query!(
-        r#"
-            INSERT
-                INTO cost
-                    (id, account_id, amount, description, event_date, tags)
-                VALUES
-                    ($1,         $2,     $3,          $4,         $5,   $6)
-        "#,
-        &cost_uuid,
-        account_id,
-        amount,
-        description,
-        event_date,
-        &tags[..]
-    )
-    .execute(pool)
-    .await?;
-
-    for debtor in &debtors {
-        let debt_uuid = Uuid::new_v4();
-        sqlx::query!(
-            r#"
-                INSERT
-                    INTO debt
-                        (id, debtor_account_id, cost_id, amount)
-                    VALUES
-                        ($1,                $2,      $3,     $4)
-            "#,
-            &debt_uuid,
-            debtor.account_id,
-            &cost_uuid,
-            debtor.amount,
-        )
-        .execute(pool)
-        .await?;
+    let leftover = amount - parts.iter().map(|part| part.0).sum::<i128>();
+
+    let mut order = (0..parts.len()).collect::<Vec<_>>();
+    // stable sort keeps the given order for equal remainders
+    order.sort_by(|a, b| parts[*b].1.cmp(&parts[*a].1));
+    for index in order
+        .iter()
+        .cycle()
+        .take(usize::try_from(leftover).unwrap_or(0))
+    {
+        parts[*index].0 += 1;
     }
 
-    get(pool, cost_uuid).await
+    #[allow(clippy::cast_possible_truncation)]
+    parts.iter().map(|part| part.0 as i64).collect()
+}
+
+fn unique_tags(tags: Option<Vec<String>>) -> Vec<String> {
+    // sort and remove duplicate values
+    tags.unwrap_or_default()
+        .into_iter()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>()
+}
+
+pub async fn delete(repos: &Repositories, ledger_id: Uuid, cost_id: Uuid) -> Result<(), AppError> {
+    service::balance::retry(|| try_delete(repos, ledger_id, cost_id)).await
 }
 
-pub async fn delete(pool: &PgPool, cost_id: Uuid) -> Result<(), AppError> {
-    let result = sqlx::query!(
-        r#"
-            DELETE
-                FROM cost
-                    WHERE id = $1
-        "#,
-        cost_id,
-    )
-    .execute(pool)
-    .await?;
-
-    if result.rows_affected() == 0 {
+/// One attempt of `delete`, fails with a conflict if the balances changed in between
+async fn try_delete(repos: &Repositories, ledger_id: Uuid, cost_id: Uuid) -> Result<(), AppError> {
+    // read first, so the reverted debts are not older than its balance version
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let cost = get(repos, ledger_id, cost_id).await?;
+    let debts = get_debts(repos, cost_id).await?;
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let changes = service::balance::reverted(service::balance::of_cost(&rates, &cost, &debts)?);
+
+    repos
+        .costs
+        .delete(ledger_id, cost_id, &changes, ledger.balance_version)
+        .await
+}
+
+pub async fn get(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    cost_id: Uuid,
+) -> Result<entity::Cost, AppError> {
+    repos.costs.get(ledger_id, cost_id).await
+}
+
+/// The cost if it was payed by the account, other costs are not found under its path
+pub async fn get_of_payer(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    cost_id: Uuid,
+) -> Result<entity::Cost, AppError> {
+    let cost = get(repos, ledger_id, cost_id).await?;
+    if cost.account_id != account_id {
         return Err(AppError::NotFound);
     }
 
-    Ok(())
+    Ok(cost)
+}
+
+pub async fn get_debts(repos: &Repositories, cost_id: Uuid) -> Result<Vec<entity::Debt>, AppError> {
+    repos.debts.get_of_cost(cost_id).await
 }
 
-pub async fn get(pool: &PgPool, cost_id: Uuid) -> Result<entity::Cost, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Cost,
-        r#"
-            SELECT *
-            FROM cost
-                WHERE id = $1
-        "#,
-        cost_id
-    )
-    .fetch_one(pool)
-    .await?)
+/// The cost together with all of its debts
+pub async fn get_with_debtors(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    cost_id: Uuid,
+) -> Result<response::CostDto, AppError> {
+    let cost = get(repos, ledger_id, cost_id).await?;
+    let debts = get_debts(repos, cost_id).await?;
+
+    Ok((cost, debts).into())
 }
 
 pub async fn get_for_account(
-    pool: &PgPool,
+    repos: &Repositories,
+    ledger_id: Uuid,
     account_id: Uuid,
 ) -> Result<Vec<entity::Cost>, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Cost,
-        r#"
-            SELECT *
-            FROM cost
-                WHERE account_id = $1
-        "#,
-        account_id
-    )
-    .fetch_all(pool)
-    .await?)
+    repos.costs.get_for_account(ledger_id, account_id).await
 }
 
+/// One page of the costs that match all filters of the query, with their debtors
 pub async fn get_all(
-    pool: &PgPool,
-    start_date: Option<chrono::NaiveDate>,
-    end_date: Option<chrono::NaiveDate>,
-) -> Result<Vec<response::CostDto>, AppError> {
-    struct CostJoinedDebt {
-        cost: entity::Cost,
-        debt: entity::Debt,
-    }
-
-    #[allow(clippy::unwrap_used)]
-    let result = sqlx::query!(
-        r#"
-            SELECT c.*, d.id AS debt_id, d.debtor_account_id, d.amount AS debtor_amount
-            FROM cost c
-                JOIN debt d ON d.cost_id = c.id
-            WHERE
-                c.event_date BETWEEN $1 AND $2
-        "#,
-        start_date.unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
-        end_date.unwrap_or_else(|| chrono::NaiveDate::from_ymd_opt(3000, 1, 1).unwrap()),
-    )
-    .map(|row| CostJoinedDebt {
-        cost: entity::Cost {
-            id: row.id,
-            account_id: row.account_id,
-            amount: row.amount,
-            event_date: row.event_date,
-            description: row.description,
-            tags: row.tags,
-        },
-        debt: entity::Debt {
-            id: row.debt_id,
-            debtor_account_id: row.debtor_account_id,
-            cost_id: row.id,
-            amount: row.debtor_amount,
-        },
-    })
-    .fetch_all(pool)
-    .await?
-    .iter()
-    // group by cost-id as db will return multiple rows for a join and we want it grouped into a vector
-    .fold(
-        Into::<Vec<response::CostDto>>::into(Vec::new()),
-        |mut acc, e| {
-            if let Some(entry) = acc.iter_mut().find(|pred| pred.id == e.cost.id) {
-                entry.debtors.push(e.debt.clone().into());
-            } else {
-                let mut cost: response::CostDto = e.cost.clone().into();
-                cost.debtors.push(e.debt.clone().into());
-                acc.push(cost);
-            }
+    repos: &Repositories,
+    ledger_id: Uuid,
+    query: request::CostsQuery,
+) -> Result<response::CostPageDto, AppError> {
+    let mut page = service::page::of_query(
+        query.sort,
+        query.order,
+        query.limit,
+        query.cursor.as_deref(),
+    )?;
+    let filter = CostFilter {
+        start_date: query.start_date,
+        end_date: query.end_date,
+        payer_account_id: query.payer_account_id,
+        debtor_account_id: query.debtor_account_id,
+        tags: query
+            .tags
+            .iter()
+            .flat_map(|tags| tags.split(','))
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect(),
+        min_amount: query.min_amount.map(Money::cents),
+        max_amount: query.max_amount.map(Money::cents),
+        description: query.description,
+    };
 
-            acc
-        },
-    );
+    // one more cost than requested tells if there is a next page
+    let limit = page.limit;
+    page.limit += 1;
+    let rows = repos
+        .costs
+        .get_all_with_debts(ledger_id, &filter, &page)
+        .await?;
 
-    Ok(result)
-}
+    // the rows of a cost follow each other, grouped by the index of the cost instead of searching
+    let mut costs: Vec<(entity::Cost, response::CostDto)> = Vec::new();
+    let mut indices: HashMap<Uuid, usize> = HashMap::new();
+    for (cost, debt) in rows {
+        let index = *indices.entry(cost.id).or_insert_with(|| {
+            costs.push((cost.clone(), cost.into()));
+            costs.len() - 1
+        });
+        costs[index].1.debtors.push(debt.into());
+    }
 
-pub async fn get_debts_of_account(
-    pool: &PgPool,
-    account_id: Uuid,
-) -> Result<Vec<(Uuid, i64)>, AppError> {
-    let records = sqlx::query!(
-        r#"
-            SELECT d.amount, d.debtor_account_id
-                FROM debt d
-                    JOIN cost c ON c.id = d.cost_id
-                WHERE c.account_id = $1
-        "#,
-        account_id
-    )
-    .fetch_all(pool)
-    .await?;
-
-    // calculate the overall debt to the different accounts
-    let mut results: HashMap<Uuid, i64> = HashMap::new();
-    for record in &records {
-        *results.entry(record.debtor_account_id).or_insert(0) += record.amount;
-    }
-
-    // transform hashmap into vector
-    Ok(results.iter().map(|r| (*r.0, *r.1)).collect::<Vec<_>>())
-}
+    let next_cursor = if costs.len() > usize::try_from(limit).unwrap_or(0) {
+        costs.pop();
+        costs.last().map(|(cost, _)| {
+            service::page::cursor(&page, page.position(cost.amount, cost.event_date), cost.id)
+        })
+    } else {
+        None
+    };
 
-pub async fn get_debts_for_account(
-    pool: &PgPool,
-    account_id: Uuid,
-) -> Result<Vec<(Uuid, i64)>, AppError> {
-    let records = sqlx::query!(
-        r#"
-            SELECT d.amount, c.account_id
-                FROM debt d
-                    JOIN cost c ON c.id = d.cost_id
-                WHERE d.debtor_account_id = $1
-        "#,
-        account_id
-    )
-    .fetch_all(pool)
-    .await?;
-
-    // calculate the overall debt to the different accounts
-    let mut results: HashMap<Uuid, i64> = HashMap::new();
-    for record in &records {
-        *results.entry(record.account_id).or_insert(0) += record.amount;
-    }
-
-    // transform hashmap into vector
-    Ok(results.iter().map(|r| (*r.0, *r.1)).collect::<Vec<_>>())
+    Ok(response::CostPageDto {
+        costs: costs.into_iter().map(|(_, cost)| cost).collect(),
+        next_cursor,
+    })
 }
 
+/// Debts between all accounts based on the costs and payments in the given range
+///
+/// Without a range the stored balances are used, otherwise they are calculated from the costs and
+/// payments of the range, `end_date` alone results in the state of that day
+///
+/// All amounts are converted into the base currency of the ledger with the rate valid on their `event_date`
 pub async fn get_current_snapshot(
-    pool: &PgPool,
+    repos: &Repositories,
+    ledger_id: Uuid,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
 ) -> Result<Vec<response::CalculatedDebtDto>, AppError> {
-    let accounts = service::account::get_all(pool).await?;
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let accounts = service::account::get_all(repos, ledger_id).await?;
 
-    let mut all_debts: Vec<response::CalculatedDebtDto> = Vec::new();
-    for account in &accounts {
-        let payed_payments = service::payment::get_for_account(pool, account.id).await?;
-        let given_payments = service::payment::get_of_account(pool, account.id).await?;
-        let to_pay_debts = get_debts_for_account(pool, account.id).await?;
-        let being_payed_debts = get_debts_of_account(pool, account.id).await?;
+    let balances = if start_date.is_none() && end_date.is_none() {
+        repos.balances.get_all(ledger_id).await?
+    } else {
+        let rates = service::exchange_rate::get_rates(repos, ledger.base_currency.clone()).await?;
+        service::balance::calculate(repos, ledger_id, &rates, start_date, end_date).await?
+    };
 
-        all_debts = accumulate_costs(
-            &payed_payments,
-            &given_payments,
-            &to_pay_debts,
-            &being_payed_debts,
-            &accounts,
-            account,
-            all_debts,
-        );
-    }
+    // a balance of an unknown account is a drift of the stored balances, see the balance rebuild
+    let account = |id| {
+        accounts
+            .iter()
+            .find(|account: &&entity::Account| account.id == id)
+            .cloned()
+            .ok_or_else(|| {
+                AppError::InternalServer(format!(
+                    "balance of ledger {ledger_id} refers to unknown account {id}"
+                ))
+            })
+    };
 
-    Ok(all_debts)
+    balances
+        .into_iter()
+        .map(|balance| {
+            Ok(response::CalculatedDebtDto {
+                payer_account: account(balance.payer_account_id)?.into(),
+                lender_account: account(balance.lender_account_id)?.into(),
+                amount: Money::from_cents(balance.amount),
+                currency: ledger.base_currency.clone(),
+            })
+        })
+        .collect()
 }
 
-fn accumulate_costs(
-    payed_payments: &[entity::Payment],
-    given_payments: &[entity::Payment],
-    to_pay: &[(Uuid, i64)],
-    being_paid: &[(Uuid, i64)],
-    accounts: &[entity::Account],
-    payer_account: &entity::Account,
-    accumulated_debts: Vec<response::CalculatedDebtDto>,
-) -> Vec<response::CalculatedDebtDto> {
-    // calculate the overall debt from payer account to lender account
-    let mut results: HashMap<Uuid, i64> = HashMap::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::exchange_rate::ExchangeRates;
 
-    // payer account pays to lender account via payment
-    for payment in payed_payments {
-        *results.entry(payment.lender_account_id).or_insert(0) += payment.amount;
+    /// Debt of an account, together with the cost it belongs to
+    #[derive(Debug, Clone)]
+    struct AccountDebt {
+        /// the other side of the debt, the payer of the cost or the debtor
+        account_id: Uuid,
+        amount: i64,
+        currency: String,
+        event_date: chrono::NaiveDate,
     }
 
-    // lender account could have payed to payer account via payment
-    for payment in given_payments {
-        *results.entry(payment.payer_account_id).or_insert(0) -= payment.amount;
+    /// Where the debts per account are loaded from, they are only needed to compare the snapshot
+    /// with its calculation before balances were summed up by the storage
+    enum AccountDebts<'a> {
+        /// collected from the costs of every account, which is fast enough in memory
+        Costs,
+        /// with one query each, like the former calculation
+        Postgres(&'a sqlx::PgPool),
     }
 
-    // lender account could have debts to payer account via debts
-    for debt in being_paid {
-        *results.entry(debt.0).or_insert(0) += debt.1;
+    impl AccountDebts<'_> {
+        /// Debts of the costs of the ledger in the range, together with their payer
+        async fn all(
+            repos: &Repositories,
+            ledger_id: Uuid,
+            start_date: Option<chrono::NaiveDate>,
+            end_date: Option<chrono::NaiveDate>,
+        ) -> Result<Vec<(entity::Cost, entity::Debt)>, AppError> {
+            let mut all = Vec::new();
+            for account in service::account::get_all(repos, ledger_id).await? {
+                for cost in repos.costs.get_for_account(ledger_id, account.id).await? {
+                    if start_date.is_some_and(|start| cost.event_date < start)
+                        || end_date.is_some_and(|end| cost.event_date > end)
+                    {
+                        continue;
+                    }
+
+                    for debt in repos.debts.get_of_cost(cost.id).await? {
+                        all.push((cost.clone(), debt));
+                    }
+                }
+            }
+
+            Ok(all)
+        }
+
+        /// Debts of other accounts for costs payed by the account
+        async fn of_account(
+            &self,
+            repos: &Repositories,
+            ledger_id: Uuid,
+            account_id: Uuid,
+            start_date: Option<chrono::NaiveDate>,
+            end_date: Option<chrono::NaiveDate>,
+        ) -> Result<Vec<AccountDebt>, AppError> {
+            match self {
+                Self::Costs => Ok(Self::all(repos, ledger_id, start_date, end_date)
+                    .await?
+                    .into_iter()
+                    .filter(|(cost, _)| cost.account_id == account_id)
+                    .map(|(cost, debt)| AccountDebt {
+                        account_id: debt.debtor_account_id,
+                        amount: debt.amount,
+                        currency: cost.currency,
+                        event_date: cost.event_date,
+                    })
+                    .collect()),
+                Self::Postgres(pool) => Ok(sqlx::query_as!(
+                    AccountDebt,
+                    r#"
+                        SELECT d.debtor_account_id AS account_id, d.amount, c.currency, c.event_date
+                            FROM debt d
+                                JOIN cost c ON c.id = d.cost_id
+                            WHERE c.account_id = $1
+                                AND c.event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+                    "#,
+                    account_id,
+                    start_date,
+                    end_date,
+                )
+                .fetch_all(*pool)
+                .await?),
+            }
+        }
+
+        /// Debts of the account for costs payed by other accounts
+        async fn for_account(
+            &self,
+            repos: &Repositories,
+            ledger_id: Uuid,
+            account_id: Uuid,
+            start_date: Option<chrono::NaiveDate>,
+            end_date: Option<chrono::NaiveDate>,
+        ) -> Result<Vec<AccountDebt>, AppError> {
+            match self {
+                Self::Costs => Ok(Self::all(repos, ledger_id, start_date, end_date)
+                    .await?
+                    .into_iter()
+                    .filter(|(_, debt)| debt.debtor_account_id == account_id)
+                    .map(|(cost, debt)| AccountDebt {
+                        account_id: cost.account_id,
+                        amount: debt.amount,
+                        currency: cost.currency,
+                        event_date: cost.event_date,
+                    })
+                    .collect()),
+                Self::Postgres(pool) => Ok(sqlx::query_as!(
+                    AccountDebt,
+                    r#"
+                        SELECT c.account_id, d.amount, c.currency, c.event_date
+                            FROM debt d
+                                JOIN cost c ON c.id = d.cost_id
+                            WHERE d.debtor_account_id = $1
+                                AND c.event_date BETWEEN COALESCE($2, '-infinity'::DATE) AND COALESCE($3, 'infinity'::DATE)
+                    "#,
+                    account_id,
+                    start_date,
+                    end_date,
+                )
+                .fetch_all(*pool)
+                .await?),
+            }
+        }
     }
 
-    // payer account could have debts to lender account via debts
-    for debt in to_pay {
-        *results.entry(debt.0).or_insert(0) -= debt.1;
+    /// Sum up the debts per account, converted into the currency of the given rates
+    fn sum_debts(
+        debts: &[AccountDebt],
+        rates: &ExchangeRates,
+    ) -> Result<Vec<(Uuid, i64)>, AppError> {
+        // calculate the overall debt to the different accounts
+        let mut results: HashMap<Uuid, i64> = HashMap::new();
+        for debt in debts {
+            *results.entry(debt.account_id).or_insert(0) +=
+                rates.convert(debt.amount, &debt.currency, debt.event_date)?;
+        }
+
+        // transform hashmap into vector
+        Ok(results.iter().map(|r| (*r.0, *r.1)).collect::<Vec<_>>())
     }
 
-    let mut accumulated_debts = accumulated_debts;
-    for result in &results {
-        let lender_account = accounts
-            .iter()
-            .find(|acc| acc.id == *result.0)
-            .unwrap_or(payer_account);
+    /// The snapshot like it was calculated before, with four queries per account
+    ///
+    /// Reference for the single query of the storage, which has to give the same result
+    async fn accumulated_snapshot(
+        repos: &Repositories,
+        debts: &AccountDebts<'_>,
+        ledger_id: Uuid,
+        start_date: Option<chrono::NaiveDate>,
+        end_date: Option<chrono::NaiveDate>,
+    ) -> Result<Vec<response::CalculatedDebtDto>, AppError> {
+        let ledger = service::ledger::get(repos, ledger_id).await?;
+        let accounts = service::account::get_all(repos, ledger_id).await?;
+        let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+
+        let mut all_debts: Vec<response::CalculatedDebtDto> = Vec::new();
+        for account in &accounts {
+            let payed_payments = convert_payments(
+                repos
+                    .payments
+                    .get_for_account(account.id, start_date, end_date)
+                    .await?,
+                &rates,
+            )?;
+            let given_payments = convert_payments(
+                repos
+                    .payments
+                    .get_of_account(account.id, start_date, end_date)
+                    .await?,
+                &rates,
+            )?;
+            let to_pay_debts = sum_debts(
+                &debts
+                    .for_account(repos, ledger_id, account.id, start_date, end_date)
+                    .await?,
+                &rates,
+            )?;
+            let being_payed_debts = sum_debts(
+                &debts
+                    .of_account(repos, ledger_id, account.id, start_date, end_date)
+                    .await?,
+                &rates,
+            )?;
 
-        // lender will have their own costs (to see the general distribution of cost, so ignore them here
-        if lender_account.id == payer_account.id {
-            continue;
+            all_debts = accumulate_costs(
+                &payed_payments,
+                &given_payments,
+                &to_pay_debts,
+                &being_payed_debts,
+                &accounts,
+                account,
+                rates.currency(),
+                all_debts,
+            );
         }
 
-        accumulated_debts.push(response::CalculatedDebtDto {
-            payer_account: payer_account.clone().into(),
-            lender_account: lender_account.clone().into(),
-            // only transform to float at the end to not run into rounding errors
-            amount: Conversion::to_float(*result.1),
-        });
+        Ok(all_debts)
     }
 
-    accumulated_debts.clone()
-}
+    fn convert_payments(
+        payments: Vec<entity::Payment>,
+        rates: &ExchangeRates,
+    ) -> Result<Vec<entity::Payment>, AppError> {
+        payments
+            .into_iter()
+            .map(|payment| {
+                Ok(entity::Payment {
+                    amount: rates.convert(payment.amount, &payment.currency, payment.event_date)?,
+                    currency: rates.currency().to_string(),
+                    ..payment
+                })
+            })
+            .collect()
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[allow(clippy::too_many_arguments)]
+    fn accumulate_costs(
+        payed_payments: &[entity::Payment],
+        given_payments: &[entity::Payment],
+        to_pay: &[(Uuid, i64)],
+        being_paid: &[(Uuid, i64)],
+        accounts: &[entity::Account],
+        payer_account: &entity::Account,
+        currency: &str,
+        accumulated_debts: Vec<response::CalculatedDebtDto>,
+    ) -> Vec<response::CalculatedDebtDto> {
+        // calculate the overall debt from payer account to lender account
+        let mut results: HashMap<Uuid, i64> = HashMap::new();
+
+        // payer account pays to lender account via payment
+        for payment in payed_payments {
+            *results.entry(payment.lender_account_id).or_insert(0) += payment.amount;
+        }
+
+        // lender account could have payed to payer account via payment
+        for payment in given_payments {
+            *results.entry(payment.payer_account_id).or_insert(0) -= payment.amount;
+        }
+
+        // lender account could have debts to payer account via debts
+        for debt in being_paid {
+            *results.entry(debt.0).or_insert(0) += debt.1;
+        }
+
+        // payer account could have debts to lender account via debts
+        for debt in to_pay {
+            *results.entry(debt.0).or_insert(0) -= debt.1;
+        }
+
+        let mut accumulated_debts = accumulated_debts;
+        for result in &results {
+            let lender_account = accounts
+                .iter()
+                .find(|acc| acc.id == *result.0)
+                .unwrap_or(payer_account);
+
+            // lender will have their own costs (to see the general distribution of cost, so ignore them here
+            if lender_account.id == payer_account.id {
+                continue;
+            }
+
+            accumulated_debts.push(response::CalculatedDebtDto {
+                payer_account: payer_account.clone().into(),
+                lender_account: lender_account.clone().into(),
+                amount: Money::from_cents(*result.1),
+                currency: currency.to_string(),
+            });
+        }
+
+        accumulated_debts.clone()
+    }
 
     #[test]
     fn correctly_calculate_current_snapshot() {
         let lender_id = Uuid::new_v4();
         let payer_id = Uuid::new_v4();
 
+        let ledger_id = Uuid::new_v4();
+
         let lender_account = entity::Account {
             id: lender_id,
+            ledger_id,
             name: "Lender".to_string(),
+            user_id: None,
         };
 
         let payer_account = entity::Account {
             id: payer_id,
+            ledger_id,
             name: "Payer".to_string(),
+            user_id: None,
         };
 
         // payer added cost of 412
@@ -362,11 +735,13 @@ mod tests {
         // lender pays back 100 to payer
         let payed_payments = vec![entity::Payment {
             id: Uuid::new_v4(),
+            ledger_id,
             payer_account_id: payer_id,
             lender_account_id: lender_id,
             amount: 100,
             event_date: chrono::NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
             description: None,
+            currency: "EUR".to_string(),
         }];
 
         // noone pays back to lender
@@ -379,10 +754,657 @@ mod tests {
             &being_paid,
             &[lender_account, payer_account.clone()],
             &payer_account,
+            "EUR",
             vec![],
         );
 
         // resulting in lender: 11 was needed, 100 was payed, so 89 is now owed to lender instead
-        assert_eq!(results[0].amount, 0.89);
+        assert_eq!(results[0].amount, Money::from_cents(89));
+    }
+
+    fn debtors(count: usize) -> Vec<request::CreateDebtorDto> {
+        (0..count)
+            .map(|_| request::CreateDebtorDto {
+                account_id: Uuid::new_v4(),
+                amount: None,
+                percentage: None,
+                shares: None,
+            })
+            .collect()
+    }
+
+    fn money(amount: &str) -> Money {
+        amount.parse().unwrap()
+    }
+
+    fn percentage(percentage: &str) -> Percentage {
+        percentage.parse().unwrap()
+    }
+
+    fn split_amounts(
+        amount: Money,
+        split: &request::SplitMode,
+        debtors: &[request::CreateDebtorDto],
+    ) -> Vec<i64> {
+        let (_, debtors) = split_debtors(amount, split, debtors).unwrap();
+        debtors.iter().map(|d| d.amount).collect()
+    }
+
+    #[test]
+    fn split_equal_assigns_leftover_cents_in_given_order() {
+        let amounts = split_amounts(money("10.0"), &request::SplitMode::Equal, &debtors(3));
+
+        assert_eq!(amounts, vec![334, 333, 333]);
+        assert_eq!(amounts.iter().sum::<i64>(), 1000);
+    }
+
+    #[test]
+    fn split_percentage_assigns_leftover_cents_to_largest_remainder() {
+        let mut debtors = debtors(3);
+        debtors[0].percentage = Some(percentage("33.33"));
+        debtors[1].percentage = Some(percentage("33.33"));
+        debtors[2].percentage = Some(percentage("33.34"));
+
+        let amounts = split_amounts(money("0.10"), &request::SplitMode::Percentage, &debtors);
+
+        // 3.333, 3.333 and 3.334 cents
+        assert_eq!(amounts, vec![3, 3, 4]);
+    }
+
+    #[test]
+    fn split_percentage_needs_to_add_up_to_100() {
+        let mut debtors = debtors(2);
+        debtors[0].percentage = Some(percentage("50"));
+        debtors[1].percentage = Some(percentage("40"));
+
+        assert!(split_debtors(money("10.0"), &request::SplitMode::Percentage, &debtors).is_err());
+    }
+
+    #[test]
+    fn split_shares() {
+        let mut debtors = debtors(3);
+        debtors[0].shares = Some(2);
+        debtors[1].shares = Some(1);
+        debtors[2].shares = Some(1);
+
+        let amounts = split_amounts(money("10.01"), &request::SplitMode::Shares, &debtors);
+
+        assert_eq!(amounts, vec![501, 250, 250]);
+    }
+
+    #[test]
+    fn split_exact_needs_to_add_up_to_amount() {
+        let mut debtors = debtors(2);
+        debtors[0].amount = Some(money("4.0"));
+        debtors[1].amount = Some(money("5.0"));
+
+        assert!(split_debtors(money("10.0"), &request::SplitMode::Exact, &debtors).is_err());
+
+        debtors[1].amount = Some(money("6.0"));
+        let amounts = split_amounts(money("10.0"), &request::SplitMode::Exact, &debtors);
+
+        assert_eq!(amounts, vec![400, 600]);
+    }
+
+    #[test]
+    fn split_into_empty_debts_is_rejected() {
+        assert!(split_debtors(money("0.02"), &request::SplitMode::Equal, &debtors(3)).is_err());
+        assert!(split_debtors(money("10.0"), &request::SplitMode::Equal, &debtors(0)).is_err());
+    }
+
+    #[test]
+    fn tags_are_sorted_without_duplicates() {
+        let tags = ["rent", "food", "rent", "bar"].map(ToString::to_string);
+
+        assert_eq!(unique_tags(Some(tags.to_vec())), ["bar", "food", "rent"]);
+        assert!(unique_tags(None).is_empty());
+    }
+
+    struct Setup {
+        repos: Repositories,
+        ledger_id: Uuid,
+        alice: Uuid,
+        bob: Uuid,
+    }
+
+    async fn setup() -> Setup {
+        let repos = Repositories::memory();
+        let ledger = service::ledger::create(&repos, "Flat".to_string(), None)
+            .await
+            .unwrap();
+        let alice = service::account::create(&repos, ledger.id, "Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = service::account::create(&repos, ledger.id, "Bob".to_string(), None)
+            .await
+            .unwrap();
+
+        Setup {
+            repos,
+            ledger_id: ledger.id,
+            alice: alice.id,
+            bob: bob.id,
+        }
+    }
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2023, 1, day).unwrap()
+    }
+
+    fn equal_cost(amount: &str, day: u32, debtors: &[Uuid]) -> request::CreateCostDto {
+        request::CreateCostDto {
+            debtors: debtors
+                .iter()
+                .map(|account_id| request::CreateDebtorDto {
+                    account_id: *account_id,
+                    amount: None,
+                    percentage: None,
+                    shares: None,
+                })
+                .collect(),
+            split: request::SplitMode::Equal,
+            amount: money(amount),
+            currency: None,
+            event_date: date(day),
+            description: None,
+            tags: None,
+        }
+    }
+
+    fn amount_between(
+        snapshot: &[response::CalculatedDebtDto],
+        payer: Uuid,
+        lender: Uuid,
+    ) -> Option<i64> {
+        snapshot
+            .iter()
+            .find(|d| d.payer_account.id == payer && d.lender_account.id == lender)
+            .map(|d| d.amount.cents())
+    }
+
+    #[tokio::test]
+    async fn cost_with_wrong_debtor_sum_is_not_stored() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        let mut cost = equal_cost("10.0", 1, &[alice, bob]);
+        cost.split = request::SplitMode::Exact;
+        cost.debtors[0].amount = Some(money("4.0"));
+        cost.debtors[1].amount = Some(money("5.0"));
+
+        assert!(matches!(
+            create(&repos, ledger_id, alice, cost).await,
+            Err(AppError::Service(_))
+        ));
+        assert!(get_all(&repos, ledger_id, request::CostsQuery::default())
+            .await
+            .unwrap()
+            .costs
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn cost_with_debtor_of_another_ledger_is_rejected() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            ..
+        } = setup().await;
+        let other = setup().await;
+
+        let cost = equal_cost("10.0", 1, &[alice, other.alice]);
+
+        assert!(create(&repos, ledger_id, alice, cost).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn update_replaces_all_debts() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        let cost = create(
+            &repos,
+            ledger_id,
+            alice,
+            equal_cost("10.0", 1, &[alice, bob]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cost.debtors.len(), 2);
+        let cost = update(&repos, ledger_id, cost.id, equal_cost("9.0", 2, &[bob]))
+            .await
+            .unwrap();
+        assert_eq!(cost.debtors.len(), 1);
+
+        let costs = get_all(&repos, ledger_id, request::CostsQuery::default())
+            .await
+            .unwrap()
+            .costs;
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs[0].amount, money("9.0"));
+        assert_eq!(costs[0].debtors.len(), 1);
+        assert_eq!(costs[0].debtors[0].account_id, bob);
+    }
+
+    #[tokio::test]
+    async fn pages_contain_every_matching_cost_once() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+        for (amount, day) in [("5.0", 1), ("5.0", 1), ("8.0", 2), ("3.0", 2), ("5.0", 4)] {
+            create(
+                &repos,
+                ledger_id,
+                alice,
+                equal_cost(amount, day, &[alice, bob]),
+            )
+            .await
+            .unwrap();
+        }
+        create(&repos, ledger_id, alice, equal_cost("9.0", 3, &[alice]))
+            .await
+            .unwrap();
+
+        let query = || request::CostsQuery {
+            debtor_account_id: Some(bob),
+            sort: request::SortBy::Amount,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut costs = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = get_all(&repos, ledger_id, request::CostsQuery { cursor, ..query() })
+                .await
+                .unwrap();
+            assert!(page.costs.len() <= 2);
+            costs.extend(page.costs);
+
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let amounts = costs.iter().map(|c| c.amount.cents()).collect::<Vec<_>>();
+        assert_eq!(amounts, vec![800, 500, 500, 500, 300]);
+        let mut ids = costs.iter().map(|c| c.id).collect::<Vec<_>>();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+        assert!(costs.iter().all(|c| c.debtors.len() == 2));
+    }
+
+    #[tokio::test]
+    async fn snapshot_accumulates_costs_and_payments() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        // bob owes alice 15
+        create(
+            &repos,
+            ledger_id,
+            alice,
+            equal_cost("30.0", 1, &[alice, bob]),
+        )
+        .await
+        .unwrap();
+        // and pays back 10 USD, which are 5 EUR
+        service::exchange_rate::create(&repos, date(1), "USD".to_string(), "EUR".to_string(), 0.5)
+            .await
+            .unwrap();
+        service::payment::create(
+            &repos,
+            ledger_id,
+            bob,
+            request::CreatePaymentDto {
+                lender_account_id: alice,
+                amount: money("10.0"),
+                currency: Some("USD".to_string()),
+                event_date: date(2),
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let snapshot = get_current_snapshot(&repos, ledger_id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(amount_between(&snapshot, alice, bob), Some(1000));
+        assert_eq!(amount_between(&snapshot, bob, alice), Some(-1000));
+
+        let snapshot = get_current_snapshot(&repos, ledger_id, None, Some(date(1)))
+            .await
+            .unwrap();
+        assert_eq!(amount_between(&snapshot, alice, bob), Some(1500));
+        assert_eq!(amount_between(&snapshot, bob, alice), Some(-1500));
+    }
+
+    /// Costs and payments between random accounts, in the base currency and two others
+    async fn generate(
+        repos: &Repositories,
+        accounts: usize,
+        costs: usize,
+        payments: usize,
+    ) -> Uuid {
+        use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let start = chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let day = |rng: &mut StdRng| start + chrono::Duration::days(rng.gen_range(0..365));
+        let currency = |rng: &mut StdRng| {
+            ["EUR", "EUR", "USD", "CHF"]
+                .choose(rng)
+                .map(ToString::to_string)
+        };
+
+        let ledger = service::ledger::create(repos, "Generated".to_string(), None)
+            .await
+            .unwrap();
+        let mut account_ids = Vec::new();
+        for i in 0..accounts {
+            let account = service::account::create(repos, ledger.id, format!("{i}"), None)
+                .await
+                .unwrap();
+            account_ids.push(account.id);
+        }
+
+        // a new rate every month, chf is only known the other way around
+        for month in 0..12 {
+            let date = start + chrono::Duration::days(month * 31);
+            #[allow(clippy::cast_precision_loss)]
+            let rate = 0.37f64.mul_add(month as f64 / 12.0, 0.83);
+            for (from, to) in [("USD", "EUR"), ("EUR", "CHF")] {
+                service::exchange_rate::create(repos, date, from.to_string(), to.to_string(), rate)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        for _ in 0..costs {
+            let debtors = account_ids
+                .choose_multiple(&mut rng, (accounts / 2).max(1))
+                .copied()
+                .collect::<Vec<_>>();
+            let payer = *account_ids.choose(&mut rng).unwrap();
+
+            let mut cost = equal_cost("0", 1, &debtors);
+            cost.amount = Money::from_cents(rng.gen_range(100..100_000));
+            cost.currency = currency(&mut rng);
+            cost.event_date = day(&mut rng);
+            create(repos, ledger.id, payer, cost).await.unwrap();
+        }
+
+        for _ in 0..payments {
+            let accounts = account_ids
+                .choose_multiple(&mut rng, 2)
+                .copied()
+                .collect::<Vec<_>>();
+
+            service::payment::create(
+                repos,
+                ledger.id,
+                accounts[0],
+                request::CreatePaymentDto {
+                    lender_account_id: accounts[1],
+                    amount: Money::from_cents(rng.gen_range(100..100_000)),
+                    currency: currency(&mut rng),
+                    event_date: day(&mut rng),
+                    description: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        ledger.id
+    }
+
+    fn sorted(snapshot: Vec<response::CalculatedDebtDto>) -> Vec<(Uuid, Uuid, i64)> {
+        let mut snapshot: Vec<_> = snapshot
+            .into_iter()
+            .map(|d| (d.payer_account.id, d.lender_account.id, d.amount.cents()))
+            .collect();
+        snapshot.sort_unstable();
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn snapshot_is_the_same_as_accumulated_per_account() {
+        let repos = Repositories::memory();
+        let ledger_id = generate(&repos, 5, 200, 50).await;
+        let day = |ordinal| chrono::NaiveDate::from_yo_opt(2023, ordinal);
+
+        // the stored balances have to follow updates and deletes as well
+        let costs = get_all(&repos, ledger_id, request::CostsQuery::default())
+            .await
+            .unwrap()
+            .costs;
+        for cost in &costs[..10] {
+            delete(&repos, ledger_id, cost.id).await.unwrap();
+        }
+        for cost in &costs[10..20] {
+            let debtors = cost
+                .debtors
+                .iter()
+                .map(|d| d.account_id)
+                .collect::<Vec<_>>();
+            update(
+                &repos,
+                ledger_id,
+                cost.id,
+                equal_cost("12.34", 15, &debtors),
+            )
+            .await
+            .unwrap();
+        }
+        let payments =
+            service::payment::get_all(&repos, ledger_id, request::PaymentsQuery::default())
+                .await
+                .unwrap()
+                .payments;
+        service::payment::delete(&repos, ledger_id, payments[0].id)
+            .await
+            .unwrap();
+
+        for (start_date, end_date) in [(None, None), (None, day(40)), (day(100), day(300))] {
+            assert_eq!(
+                sorted(
+                    accumulated_snapshot(
+                        &repos,
+                        &AccountDebts::Costs,
+                        ledger_id,
+                        start_date,
+                        end_date
+                    )
+                    .await
+                    .unwrap()
+                ),
+                sorted(
+                    get_current_snapshot(&repos, ledger_id, start_date, end_date)
+                        .await
+                        .unwrap()
+                ),
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cost_without_exchange_rate_is_rejected() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            bob,
+        } = setup().await;
+
+        // the stored balances are in the base currency, so the cost has to be converted right away
+        let mut cost = equal_cost("30.0", 1, &[alice, bob]);
+        cost.currency = Some("USD".to_string());
+
+        assert!(matches!(
+            create(&repos, ledger_id, alice, cost).await,
+            Err(AppError::Service(_))
+        ));
+        assert!(get_all(&repos, ledger_id, request::CostsQuery::default())
+            .await
+            .unwrap()
+            .costs
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn snapshot_with_balance_of_unknown_account_fails() {
+        let Setup {
+            repos,
+            ledger_id,
+            alice,
+            ..
+        } = setup().await;
+        let ledger = service::ledger::get(&repos, ledger_id).await.unwrap();
+
+        // e.g. changed by hand in the database
+        repos
+            .balances
+            .replace_all(
+                ledger_id,
+                ledger.balance_version,
+                &[entity::Balance {
+                    ledger_id,
+                    payer_account_id: alice,
+                    lender_account_id: Uuid::new_v4(),
+                    amount: 100,
+                }],
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            get_current_snapshot(&repos, ledger_id, None, None).await,
+            Err(AppError::InternalServer(_))
+        ));
+    }
+
+    /// Compare the calculation in one query and the stored balances with the calculation per
+    /// account on postgres, which needs `DATABASE_URL`
+    ///
+    /// `cargo test --release snapshot_benchmark -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore = "benchmark, needs a postgres server"]
+    async fn snapshot_benchmark() {
+        use sqlx::postgres::PgPoolOptions;
+
+        const RUNS: u32 = 5;
+
+        // the timings are logged, the test writer shows them with `--nocapture`
+        let _ = tracing_subscriber::fmt()
+            .with_env_filter("info,sqlx=warn")
+            .with_test_writer()
+            .try_init();
+
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL is set");
+        let database = format!("money_tracker_bench_{}", Uuid::new_v4().simple());
+        let server = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&url)
+            .await
+            .unwrap();
+        sqlx::query(&format!(r#"CREATE DATABASE "{database}""#))
+            .execute(&server)
+            .await
+            .unwrap();
+
+        let (base, _) = url.rsplit_once('/').unwrap();
+        let pool = PgPoolOptions::new()
+            .connect(&format!("{base}/{database}"))
+            .await
+            .unwrap();
+        crate::migrate(&pool).await.unwrap();
+        let repos = Repositories::postgres(pool.clone());
+
+        let ledger_id = generate(&repos, 20, 5000, 1000).await;
+
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            accumulated_snapshot(
+                &repos,
+                &AccountDebts::Postgres(&pool),
+                ledger_id,
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        let per_account = started.elapsed() / RUNS;
+
+        let ledger = service::ledger::get(&repos, ledger_id).await.unwrap();
+        let rates = service::exchange_rate::get_rates(&repos, ledger.base_currency)
+            .await
+            .unwrap();
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            service::balance::calculate(&repos, ledger_id, &rates, None, None)
+                .await
+                .unwrap();
+        }
+        let single_query = started.elapsed() / RUNS;
+
+        // the snapshot without a range only reads the balances stored with every change
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            get_current_snapshot(&repos, ledger_id, None, None)
+                .await
+                .unwrap();
+        }
+        let stored = started.elapsed() / RUNS;
+
+        tracing::info!(
+            ?per_account,
+            ?single_query,
+            ?stored,
+            "snapshot of 20 accounts, 5000 costs and 1000 payments"
+        );
+
+        let expected = sorted(
+            accumulated_snapshot(
+                &repos,
+                &AccountDebts::Postgres(&pool),
+                ledger_id,
+                None,
+                None,
+            )
+            .await
+            .unwrap(),
+        );
+        let actual = sorted(
+            get_current_snapshot(&repos, ledger_id, None, None)
+                .await
+                .unwrap(),
+        );
+
+        pool.close().await;
+        sqlx::query(&format!(r#"DROP DATABASE "{database}" WITH (FORCE)"#))
+            .execute(&server)
+            .await
+            .unwrap();
+
+        assert_eq!(expected, actual);
     }
 }
@@ -0,0 +1,285 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::auth::provider::Identity;
+use crate::error::AppError;
+use crate::model::entity::Role;
+use crate::repository::Repositories;
+use crate::service;
+
+/// Name of the identity provider of users with a password
+pub const PROVIDER: &str = "local";
+
+const MIN_PASSWORD_LENGTH: usize = 12;
+
+/// Failed logins in a row after which the login is locked
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+const LOCKOUT_MINUTES: i64 = 15;
+
+/// Days the invited user has to register
+const REGISTRATION_LIFETIME_DAYS: i64 = 7;
+
+/// Password login is optional, it is enabled via `LOCAL_LOGIN=true`
+pub fn is_enabled() -> bool {
+    std::env::var("LOCAL_LOGIN").is_ok_and(|enabled| enabled == "true")
+}
+
+/// Usernames are case insensitive
+pub fn normalize(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+fn validate_password(password: &str) -> Result<(), AppError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(AppError::Service(format!(
+            "password needs at least {MIN_PASSWORD_LENGTH} characters"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Argon2id hash with a random salt, hashing is slow on purpose so it does not block the runtime
+async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|err| AppError::InternalServer(format!("could not hash password: {err}")))
+    })
+    .await
+    .map_err(|err| AppError::InternalServer(err.to_string()))?
+}
+
+async fn verify_password(hash: String, password: String) -> Result<bool, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&hash)
+            .map_err(|err| AppError::InternalServer(format!("invalid password hash: {err}")))?;
+
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|err| AppError::InternalServer(err.to_string()))?
+}
+
+/// Create the user with a `local` identity, returns its id and the one time code needed to register
+pub async fn invite(
+    repos: &Repositories,
+    username: &str,
+    role: Role,
+) -> Result<(Uuid, String), AppError> {
+    let username = normalize(username);
+    if username.is_empty() {
+        return Err(AppError::Service("username can not be empty".to_string()));
+    }
+
+    let registration_code = service::session::generate();
+
+    let user_id = repos
+        .credentials
+        .invite(
+            PROVIDER,
+            &username,
+            role,
+            &service::session::hash(&registration_code),
+            chrono::Duration::days(REGISTRATION_LIFETIME_DAYS),
+        )
+        .await?;
+
+    let identity = Identity {
+        subject: username.clone(),
+        username,
+        avatar: None,
+    };
+    service::identity::update_profile(repos, PROVIDER, &identity).await?;
+
+    Ok((user_id, registration_code))
+}
+
+/// Set the first password of the invited user, the registration code can only be used once
+pub async fn register(
+    repos: &Repositories,
+    username: &str,
+    registration_code: &str,
+    password: String,
+) -> Result<Uuid, AppError> {
+    validate_password(&password)?;
+
+    let password_hash = hash_password(password).await?;
+
+    repos
+        .credentials
+        .register(
+            &normalize(username),
+            &service::session::hash(registration_code),
+            &password_hash,
+        )
+        .await?
+        .ok_or(AppError::Forbidden)
+}
+
+/// Hash that is checked for unknown users, so the response time does not reveal which usernames exist
+async fn dummy_hash() -> Result<&'static str, AppError> {
+    static DUMMY_HASH: OnceCell<String> = OnceCell::const_new();
+
+    DUMMY_HASH
+        .get_or_try_init(|| hash_password(service::session::generate()))
+        .await
+        .map(String::as_str)
+}
+
+/// Check the password, the login is locked for a while after too many failed attempts
+///
+/// The attempt is counted before the password is checked, so parallel guesses can not pass the
+/// limit, a correct password resets the count. Unknown users, locked logins and wrong passwords
+/// are not distinguished for the caller
+pub async fn login(
+    repos: &Repositories,
+    username: &str,
+    password: String,
+) -> Result<Uuid, AppError> {
+    let username = normalize(username);
+
+    let credential = repos
+        .credentials
+        .count_attempt(
+            &username,
+            MAX_FAILED_ATTEMPTS,
+            chrono::Duration::minutes(LOCKOUT_MINUTES),
+        )
+        .await?;
+
+    let Some(credential) = credential else {
+        verify_password(dummy_hash().await?.to_string(), password).await?;
+        return Err(AppError::Forbidden);
+    };
+
+    if !verify_password(credential.password_hash, password).await? {
+        if credential.locked {
+            tracing::warn!(
+                "login of {username} is locked after {MAX_FAILED_ATTEMPTS} failed attempts"
+            );
+        }
+
+        return Err(AppError::Forbidden);
+    }
+
+    repos.credentials.reset_attempts(&username).await?;
+
+    Ok(credential.user_id)
+}
+
+/// Replace the password of the user, all other sessions of the user are ended
+pub async fn change_password(
+    repos: &Repositories,
+    user_id: Uuid,
+    session_id: Uuid,
+    current_password: String,
+    new_password: String,
+) -> Result<(), AppError> {
+    let username = repos
+        .credentials
+        .get_username(user_id)
+        .await?
+        .ok_or_else(|| AppError::Service("user has no password login".to_string()))?;
+
+    // the same lockout applies, so a stolen session can not guess the password
+    login(repos, &username, current_password).await?;
+
+    validate_password(&new_password)?;
+
+    let password_hash = hash_password(new_password).await?;
+
+    repos
+        .credentials
+        .update_password(user_id, &password_hash)
+        .await?;
+
+    service::session::delete_all_except(repos, user_id, session_id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_short_passwords() {
+        assert!(validate_password("short").is_err());
+        assert!(validate_password("long enough password").is_ok());
+    }
+
+    #[tokio::test]
+    async fn verify_hashed_password() {
+        let hash = hash_password("correct horse battery".to_string())
+            .await
+            .unwrap();
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(
+            verify_password(hash.clone(), "correct horse battery".to_string())
+                .await
+                .unwrap()
+        );
+        assert!(!verify_password(hash, "wrong horse battery".to_string())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn lock_login_after_failed_attempts() {
+        for repos in [Repositories::memory(), Repositories::sqlite_memory().await] {
+            let (user_id, code) = invite(&repos, " Alice ", Role::Member).await.unwrap();
+
+            assert!(register(
+                &repos,
+                "alice",
+                "wrong code",
+                "correct horse battery".into()
+            )
+            .await
+            .is_err());
+            assert_eq!(
+                user_id,
+                register(&repos, "ALICE", &code, "correct horse battery".into())
+                    .await
+                    .unwrap()
+            );
+            // the registration code can only be used once
+            assert!(
+                register(&repos, "alice", &code, "other horse battery".into())
+                    .await
+                    .is_err()
+            );
+
+            assert_eq!(
+                user_id,
+                login(&repos, "alice", "correct horse battery".into())
+                    .await
+                    .unwrap()
+            );
+
+            for _ in 0..MAX_FAILED_ATTEMPTS {
+                assert!(login(&repos, "alice", "wrong horse battery".into())
+                    .await
+                    .is_err());
+            }
+            assert!(login(&repos, "alice", "correct horse battery".into())
+                .await
+                .is_err());
+
+            let identities = repos.users.get_identities(user_id).await.unwrap();
+            assert_eq!(Some("alice"), identities[0].username.as_deref());
+        }
+    }
+}
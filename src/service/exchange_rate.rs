@@ -0,0 +1,234 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity;
+use crate::repository::Repositories;
+use crate::service;
+
+/// Validate the given ISO 4217 code, if nothing is given the base currency is used
+pub fn parse_currency(currency: Option<String>, base_currency: &str) -> Result<String, AppError> {
+    currency.map_or_else(|| Ok(base_currency.to_string()), validate_currency)
+}
+
+/// Validate the given ISO 4217 code
+pub fn validate_currency(currency: String) -> Result<String, AppError> {
+    let currency = currency.to_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(AppError::Service(format!(
+            "given currency {currency} is not a valid ISO 4217 code (e.g. EUR)"
+        )));
+    }
+
+    Ok(currency)
+}
+
+pub async fn create(
+    repos: &Repositories,
+    date: chrono::NaiveDate,
+    from_currency: String,
+    to_currency: String,
+    rate: f64,
+) -> Result<entity::ExchangeRate, AppError> {
+    let from_currency = validate_currency(from_currency)?;
+    let to_currency = validate_currency(to_currency)?;
+
+    if from_currency == to_currency {
+        return Err(AppError::Service(format!(
+            "exchange rate needs two different currencies but got {from_currency} twice"
+        )));
+    }
+
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(AppError::Service(format!(
+            "given rate {rate} is non existent or negative which it can not be"
+        )));
+    }
+
+    let exchange_rate = entity::ExchangeRate {
+        id: Uuid::new_v4(),
+        date,
+        from_currency,
+        to_currency,
+        rate,
+    };
+
+    // stored balances are converted with the rates, so they change together
+    service::balance::retry(|| try_create(repos, &exchange_rate)).await?;
+
+    get(repos, exchange_rate.id).await
+}
+
+/// One attempt to store the rate with the balances converted by it, fails with a conflict if
+/// the balances of a ledger changed in between
+async fn try_create(
+    repos: &Repositories,
+    exchange_rate: &entity::ExchangeRate,
+) -> Result<(), AppError> {
+    let ledgers = service::ledger::get_all(repos).await?;
+    let mut rates = get_all(repos).await?;
+    rates.push(exchange_rate.clone());
+
+    // ledgers that could not be converted before might still miss other rates
+    let balances = service::balance::calculate_all(repos, ledgers, &rates)
+        .await
+        .into_iter()
+        .map(|(ledger, balances)| service::balance::of_ledger(&ledger, balances.ok()))
+        .collect::<Vec<_>>();
+
+    repos.exchange_rates.create(exchange_rate, &balances).await
+}
+
+/// Rates still needed to convert the amounts of a ledger can not be deleted
+pub async fn delete(repos: &Repositories, exchange_rate_id: Uuid) -> Result<(), AppError> {
+    service::balance::retry(|| try_delete(repos, exchange_rate_id)).await
+}
+
+/// One attempt of `delete`, fails with a conflict if the balances of a ledger changed in between
+async fn try_delete(repos: &Repositories, exchange_rate_id: Uuid) -> Result<(), AppError> {
+    let ledgers = service::ledger::get_all(repos).await?;
+    let rates = get_all(repos)
+        .await?
+        .into_iter()
+        .filter(|rate| rate.id != exchange_rate_id)
+        .collect::<Vec<_>>();
+    let balances = service::balance::calculate_all(repos, ledgers, &rates)
+        .await
+        .into_iter()
+        .map(|(ledger, balances)| Ok(service::balance::of_ledger(&ledger, Some(balances?))))
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    repos
+        .exchange_rates
+        .delete(exchange_rate_id, &balances)
+        .await
+}
+
+pub async fn get(
+    repos: &Repositories,
+    exchange_rate_id: Uuid,
+) -> Result<entity::ExchangeRate, AppError> {
+    repos.exchange_rates.get(exchange_rate_id).await
+}
+
+pub async fn get_all(repos: &Repositories) -> Result<Vec<entity::ExchangeRate>, AppError> {
+    repos.exchange_rates.get_all().await
+}
+
+/// Load all rates to convert amounts into the given currency
+pub async fn get_rates(repos: &Repositories, currency: String) -> Result<ExchangeRates, AppError> {
+    Ok(ExchangeRates::new(currency, get_all(repos).await?))
+}
+
+pub struct ExchangeRates {
+    currency: String,
+    /// sorted by date, newest first
+    rates: Vec<entity::ExchangeRate>,
+}
+
+impl ExchangeRates {
+    pub fn new(currency: String, mut rates: Vec<entity::ExchangeRate>) -> Self {
+        rates.sort_by_key(|rate| std::cmp::Reverse(rate.date));
+
+        Self { currency, rates }
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Convert the amount (in cents) with the newest rate that is valid on the given date
+    ///
+    /// Rates are used in both directions, e.g. a USD to EUR rate can also convert EUR to USD
+    pub fn convert(
+        &self,
+        amount: i64,
+        currency: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<i64, AppError> {
+        if currency == self.currency {
+            return Ok(amount);
+        }
+
+        let rate = self
+            .rates
+            .iter()
+            .filter(|rate| rate.date <= date)
+            .find_map(|rate| {
+                if rate.from_currency == currency && rate.to_currency == self.currency {
+                    Some(rate.rate)
+                } else if rate.from_currency == self.currency && rate.to_currency == currency {
+                    Some(1.0 / rate.rate)
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| {
+                AppError::Service(format!(
+                    "no exchange rate from {currency} to {} exists for {date}",
+                    self.currency
+                ))
+            })?;
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+        Ok((amount as f64 * rate).round() as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2023, month, day).unwrap()
+    }
+
+    fn rate(date: chrono::NaiveDate, from: &str, to: &str, rate: f64) -> entity::ExchangeRate {
+        entity::ExchangeRate {
+            id: Uuid::new_v4(),
+            date,
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate,
+        }
+    }
+
+    #[test]
+    fn convert_with_rate_valid_on_date() {
+        let rates = ExchangeRates {
+            currency: "EUR".to_string(),
+            rates: vec![
+                rate(date(3, 1), "USD", "EUR", 0.5),
+                rate(date(1, 1), "USD", "EUR", 0.9),
+            ],
+        };
+
+        assert_eq!(rates.convert(1000, "EUR", date(1, 1)).unwrap(), 1000);
+        assert_eq!(rates.convert(1000, "USD", date(2, 28)).unwrap(), 900);
+        assert_eq!(rates.convert(1000, "USD", date(3, 1)).unwrap(), 500);
+        assert!(rates
+            .convert(1000, "USD", date(1, 1).pred_opt().unwrap())
+            .is_err());
+        assert!(rates.convert(1000, "CHF", date(3, 1)).is_err());
+    }
+
+    #[test]
+    fn convert_with_inverse_rate() {
+        let rates = ExchangeRates {
+            currency: "EUR".to_string(),
+            rates: vec![rate(date(1, 1), "EUR", "SEK", 10.0)],
+        };
+
+        assert_eq!(rates.convert(1005, "SEK", date(1, 1)).unwrap(), 101);
+    }
+
+    #[test]
+    fn parse_currency_code() {
+        assert_eq!(
+            parse_currency(Some("usd".to_string()), "EUR").unwrap(),
+            "USD"
+        );
+        assert_eq!(parse_currency(None, "EUR").unwrap(), "EUR");
+        assert!(parse_currency(Some("EURO".to_string()), "EUR").is_err());
+        assert!(parse_currency(Some("E1R".to_string()), "EUR").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use uuid::Uuid;
+
+use crate::auth::provider::Identity;
+use crate::error::AppError;
+use crate::repository::Repositories;
+
+/// The user that logs in with the identity, if it was invited or linked
+pub async fn get_user_id(
+    repos: &Repositories,
+    provider: &str,
+    subject: &str,
+) -> Result<Option<Uuid>, AppError> {
+    repos.users.find_by_identity(provider, subject).await
+}
+
+/// Allow the user to also login with the identity, it can only belong to one user
+pub async fn link(
+    repos: &Repositories,
+    user_id: Uuid,
+    provider: &str,
+    identity: &Identity,
+) -> Result<(), AppError> {
+    match get_user_id(repos, provider, &identity.subject).await? {
+        Some(linked_user_id) if linked_user_id == user_id => Ok(()),
+        Some(_) => Err(AppError::Service(format!(
+            "{provider} user {} is already linked to another user",
+            identity.username
+        ))),
+        None => {
+            repos
+                .users
+                .add_identity(user_id, provider, &identity.subject)
+                .await
+        }
+    }
+}
+
+/// Keep the profile up to date with the provider, which is done on every login
+pub async fn update_profile(
+    repos: &Repositories,
+    provider: &str,
+    identity: &Identity,
+) -> Result<(), AppError> {
+    repos
+        .users
+        .update_identity(
+            provider,
+            &identity.subject,
+            &identity.username,
+            identity.avatar.as_deref(),
+        )
+        .await
+}
@@ -0,0 +1,184 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::AuthUser;
+use crate::model::entity::{self, Role};
+use crate::repository::Repositories;
+use crate::service;
+
+pub async fn get(repos: &Repositories, ledger_id: Uuid) -> Result<entity::Ledger, AppError> {
+    repos.ledgers.get(ledger_id).await
+}
+
+pub async fn get_all(repos: &Repositories) -> Result<Vec<entity::Ledger>, AppError> {
+    repos.ledgers.get_all().await
+}
+
+/// Ledgers in which the user has an account, admins see every ledger
+pub async fn get_all_of_user(
+    repos: &Repositories,
+    user: &AuthUser,
+) -> Result<Vec<entity::Ledger>, AppError> {
+    let ledgers = get_all(repos).await?;
+    if user.role == Role::Admin {
+        return Ok(ledgers);
+    }
+
+    let ledger_ids = service::account::get_all_of_user(repos, user.id)
+        .await?
+        .into_iter()
+        .map(|account| account.ledger_id)
+        .collect::<Vec<_>>();
+
+    Ok(ledgers
+        .into_iter()
+        .filter(|ledger| ledger_ids.contains(&ledger.id))
+        .collect())
+}
+
+/// Make sure the user has an account in the ledger, admins can access every ledger
+/// (e.g. to link invited users to their accounts)
+///
+/// Ledgers of others are `NotFound`, so their existence is not revealed
+pub async fn ensure_member(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    user: &AuthUser,
+) -> Result<(), AppError> {
+    if user.role == Role::Admin {
+        return Ok(());
+    }
+
+    repos
+        .accounts
+        .find_of_user(ledger_id, user.id)
+        .await?
+        .map(|_| ())
+        .ok_or(AppError::NotFound)
+}
+
+pub async fn create(
+    repos: &Repositories,
+    name: String,
+    base_currency: Option<String>,
+) -> Result<entity::Ledger, AppError> {
+    let ledger = entity::Ledger {
+        id: Uuid::new_v4(),
+        name,
+        base_currency: service::exchange_rate::parse_currency(base_currency, "EUR")?,
+        balance_version: 0,
+    };
+
+    repos.ledgers.create(&ledger).await?;
+
+    get(repos, ledger.id).await
+}
+
+/// Create the ledger together with an account of the user, which makes them its first member
+pub async fn create_with_account(
+    repos: &Repositories,
+    user: &AuthUser,
+    name: String,
+    base_currency: Option<String>,
+) -> Result<entity::Ledger, AppError> {
+    let ledger = create(repos, name, base_currency).await?;
+    service::account::create(repos, ledger.id, user.username.clone(), Some(user.id)).await?;
+
+    Ok(ledger)
+}
+
+pub async fn update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    name: String,
+    base_currency: Option<String>,
+) -> Result<entity::Ledger, AppError> {
+    service::balance::retry(|| try_update(repos, ledger_id, &name, base_currency.as_deref()))
+        .await?;
+
+    get(repos, ledger_id).await
+}
+
+/// One attempt of `update`, fails with a conflict if the balances changed in between
+///
+/// Without a base currency the current one is kept
+async fn try_update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    name: &str,
+    base_currency: Option<&str>,
+) -> Result<(), AppError> {
+    let current = get(repos, ledger_id).await?;
+    let ledger = entity::Ledger {
+        id: ledger_id,
+        name: name.to_string(),
+        base_currency: service::exchange_rate::parse_currency(
+            base_currency.map(ToString::to_string),
+            &current.base_currency,
+        )?,
+        balance_version: current.balance_version,
+    };
+
+    // the stored balances are in the base currency, all amounts have to be convertible into the new one
+    let balances = if current.base_currency == ledger.base_currency {
+        None
+    } else {
+        let rates = service::exchange_rate::get_rates(repos, ledger.base_currency.clone()).await?;
+        Some(service::balance::calculate(repos, ledger_id, &rates, None, None).await?)
+    };
+
+    repos.ledgers.update(&ledger, balances.as_deref()).await
+}
+
+pub async fn delete(repos: &Repositories, ledger_id: Uuid) -> Result<(), AppError> {
+    repos.ledgers.delete(ledger_id).await
+}
+
+/// All distinct tags used by costs of the ledger
+pub async fn get_tags(repos: &Repositories, ledger_id: Uuid) -> Result<Vec<String>, AppError> {
+    repos.costs.get_tags(ledger_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::dto::request;
+
+    #[tokio::test]
+    async fn rename_keeps_the_base_currency() {
+        let repos = Repositories::memory();
+        let ledger = create(&repos, "Trip".to_string(), Some("USD".to_string()))
+            .await
+            .unwrap();
+        let alice = service::account::create(&repos, ledger.id, "Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = service::account::create(&repos, ledger.id, "Bob".to_string(), None)
+            .await
+            .unwrap();
+        service::payment::create(
+            &repos,
+            ledger.id,
+            alice.id,
+            request::CreatePaymentDto {
+                lender_account_id: bob.id,
+                amount: "10.0".parse().unwrap(),
+                currency: None,
+                event_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+        let balances = repos.balances.get_all(ledger.id).await.unwrap();
+
+        // there are no rates, so a switch to EUR would fail
+        let renamed = update(&repos, ledger.id, "Holiday".to_string(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(renamed.name, "Holiday");
+        assert_eq!(renamed.base_currency, "USD");
+        assert_eq!(repos.balances.get_all(ledger.id).await.unwrap(), balances);
+    }
+}
@@ -1,3 +1,14 @@
 pub mod account;
+pub mod balance;
 pub mod cost;
+pub mod credential;
+pub mod exchange_rate;
+pub mod identity;
+pub mod ledger;
+pub mod oauth;
+pub mod page;
 pub mod payment;
+pub mod personal_access_token;
+pub mod session;
+pub mod settlement;
+pub mod user;
@@ -0,0 +1,76 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{ClientInfo, TokenDto};
+use crate::repository::{LoginCode, OAuthState, Repositories};
+use crate::service;
+
+/// Minutes the user has to finish the login at the provider
+const STATE_LIFETIME_MINUTES: i64 = 10;
+
+/// Seconds the caller has to exchange the login code for a session
+const LOGIN_CODE_LIFETIME_SECONDS: i64 = 60;
+
+/// Remember the started login, so that only the provider redirect with this state is accepted
+pub async fn store_state(repos: &Repositories, state: &OAuthState) -> Result<(), AppError> {
+    repos
+        .oauth
+        .store_state(state, chrono::Duration::minutes(STATE_LIFETIME_MINUTES))
+        .await
+}
+
+/// Get the started login of the state, every state can only be used once
+pub async fn take_state(repos: &Repositories, csrf_token: &str) -> Result<OAuthState, AppError> {
+    repos
+        .oauth
+        .take_state(csrf_token)
+        .await?
+        .ok_or(AppError::Forbidden)
+}
+
+/// Remember the finished login and return a code, which can be exchanged once for a session
+pub async fn create_login_code(
+    repos: &Repositories,
+    user_id: Uuid,
+    username: &str,
+    client_info: ClientInfo,
+) -> Result<String, AppError> {
+    let code = service::session::generate();
+
+    repos
+        .oauth
+        .store_login_code(
+            &service::session::hash(&code),
+            &LoginCode {
+                user_id,
+                username: username.to_string(),
+                user_agent: client_info.user_agent,
+                ip: client_info.ip,
+            },
+            chrono::Duration::seconds(LOGIN_CODE_LIFETIME_SECONDS),
+        )
+        .await?;
+
+    Ok(code)
+}
+
+/// Start the session of the finished login
+pub async fn exchange_login_code(repos: &Repositories, code: &str) -> Result<TokenDto, AppError> {
+    let login = repos
+        .oauth
+        .take_login_code(&service::session::hash(code))
+        .await?
+        .ok_or(AppError::Forbidden)?;
+
+    let client_info = ClientInfo {
+        user_agent: login.user_agent,
+        ip: login.ip,
+    };
+
+    service::session::create(repos, login.user_id, &login.username, client_info).await
+}
+
+/// Delete all unfinished logins and unused login codes, returns how many were deleted
+pub async fn purge_expired(repos: &Repositories) -> Result<u64, AppError> {
+    repos.oauth.purge_expired().await
+}
@@ -0,0 +1,102 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::request::{SortBy, SortOrder};
+use crate::repository::Page;
+
+pub const DEFAULT_LIMIT: i64 = 50;
+pub const MAX_LIMIT: i64 = 200;
+
+/// Page of a listing, starting after the entry of the cursor
+///
+/// The cursor only continues a listing with the same sorting
+pub fn of_query(
+    sort: SortBy,
+    order: SortOrder,
+    limit: Option<i64>,
+    cursor: Option<&str>,
+) -> Result<Page, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::Service(format!(
+            "limit has to be between 1 and {MAX_LIMIT}"
+        )));
+    }
+
+    let after = match cursor {
+        Some(cursor) => {
+            let (cursor_sort, cursor_order, after) =
+                decode(cursor).ok_or_else(|| AppError::Service("invalid cursor".to_string()))?;
+            if cursor_sort != sort || cursor_order != order {
+                return Err(AppError::Service(
+                    "cursor belongs to a different sorting".to_string(),
+                ));
+            }
+
+            Some(after)
+        }
+        None => None,
+    };
+
+    Ok(Page {
+        by_amount: sort == SortBy::Amount,
+        descending: order == SortOrder::Desc,
+        after,
+        limit,
+    })
+}
+
+/// Cursor to continue the listing after the given entry
+///
+/// Not meant to be read by clients, only hex encoded to be safe in a query string
+pub fn cursor(page: &Page, position: i64, id: Uuid) -> String {
+    let sort = if page.by_amount { "amount" } else { "date" };
+    let order = if page.descending { "desc" } else { "asc" };
+
+    hex::encode(format!("{sort},{order},{position},{id}"))
+}
+
+fn decode(cursor: &str) -> Option<(SortBy, SortOrder, (i64, Uuid))> {
+    let cursor = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+    let mut parts = cursor.split(',');
+
+    let sort = match parts.next()? {
+        "date" => SortBy::Date,
+        "amount" => SortBy::Amount,
+        _ => return None,
+    };
+    let order = match parts.next()? {
+        "asc" => SortOrder::Asc,
+        "desc" => SortOrder::Desc,
+        _ => return None,
+    };
+    let position = parts.next()?.parse().ok()?;
+    let id = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((sort, order, (position, id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_continues_the_same_sorting_only() {
+        let page = of_query(SortBy::Amount, SortOrder::Asc, None, None).unwrap();
+        let id = Uuid::new_v4();
+        let cursor = cursor(&page, -1250, id);
+
+        let next = of_query(SortBy::Amount, SortOrder::Asc, Some(10), Some(&cursor)).unwrap();
+        assert_eq!(next.after, Some((-1250, id)));
+        assert_eq!(next.limit, 10);
+
+        assert!(of_query(SortBy::Date, SortOrder::Asc, None, Some(&cursor)).is_err());
+        assert!(of_query(SortBy::Amount, SortOrder::Desc, None, Some(&cursor)).is_err());
+        assert!(of_query(SortBy::Amount, SortOrder::Asc, None, Some("zz")).is_err());
+        assert!(of_query(SortBy::Amount, SortOrder::Asc, Some(0), None).is_err());
+        assert!(of_query(SortBy::Amount, SortOrder::Asc, Some(MAX_LIMIT + 1), None).is_err());
+    }
+}
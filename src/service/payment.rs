@@ -1,114 +1,335 @@
-use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{error::AppError, model::entity};
+use crate::error::AppError;
+use crate::model::{
+    dto::{request, response},
+    entity,
+    money::Money,
+};
+use crate::repository::{PaymentFilter, Repositories};
+use crate::service;
 
 pub async fn create(
-    pool: &PgPool,
+    repos: &Repositories,
+    ledger_id: Uuid,
     payer_account_id: Uuid,
-    lender_account_id: Uuid,
-    amount: i64,
-    description: Option<String>,
-    event_date: chrono::NaiveDate,
+    payment: request::CreatePaymentDto,
 ) -> Result<entity::Payment, AppError> {
-    let uuid = Uuid::new_v4();
-
-    sqlx::query!(
-        r#"
-            INSERT
-                INTO payment
-                    (id, payer_account_id, lender_account_id, amount, description, event_date)
-                VALUES
-                    ($1,               $2,                $3,     $4,          $5,         $6)
-        "#,
-        &uuid,
-        payer_account_id,
-        lender_account_id,
-        amount,
-        description,
-        event_date
-    )
-    .execute(pool)
-    .await?;
+    let payment_id =
+        service::balance::retry(|| try_create(repos, ledger_id, payer_account_id, &payment))
+            .await?;
 
-    get(pool, uuid).await
+    get(repos, ledger_id, payment_id).await
 }
 
-pub async fn delete(pool: &PgPool, payment_id: Uuid) -> Result<(), AppError> {
-    let result = sqlx::query!(
-        r#"
-            DELETE
-                FROM payment
-                    WHERE id = $1
-        "#,
-        payment_id,
+/// One attempt of `create`, fails with a conflict if the balances changed in between
+async fn try_create(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payer_account_id: Uuid,
+    payment: &request::CreatePaymentDto,
+) -> Result<Uuid, AppError> {
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let currency =
+        service::exchange_rate::parse_currency(payment.currency.clone(), &ledger.base_currency)?;
+    service::account::ensure_in_ledger(
+        repos,
+        ledger_id,
+        &[payer_account_id, payment.lender_account_id],
     )
-    .execute(pool)
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(AppError::NotFound);
+    let payment = entity::Payment {
+        id: Uuid::new_v4(),
+        ledger_id,
+        payer_account_id,
+        lender_account_id: payment.lender_account_id,
+        amount: payment.amount.cents(),
+        event_date: payment.event_date,
+        description: payment.description.clone(),
+        currency,
+    };
+    validate(&payment)?;
+
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let change = service::balance::of_payment(&rates, &payment)?;
+
+    repos
+        .payments
+        .create(
+            ledger_id,
+            std::slice::from_ref(&payment),
+            &[change],
+            ledger.balance_version,
+        )
+        .await?;
+
+    Ok(payment.id)
+}
+
+pub async fn update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payment_id: Uuid,
+    payment: request::CreatePaymentDto,
+) -> Result<entity::Payment, AppError> {
+    service::balance::retry(|| try_update(repos, ledger_id, payment_id, &payment)).await?;
+
+    get(repos, ledger_id, payment_id).await
+}
+
+/// One attempt of `update`, fails with a conflict if the balances changed in between
+async fn try_update(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payment_id: Uuid,
+    payment: &request::CreatePaymentDto,
+) -> Result<(), AppError> {
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let currency =
+        service::exchange_rate::parse_currency(payment.currency.clone(), &ledger.base_currency)?;
+    service::account::ensure_in_ledger(repos, ledger_id, &[payment.lender_account_id]).await?;
+
+    // read after the ledger, so the reverted payment is not older than its balance version
+    let current = get(repos, ledger_id, payment_id).await?;
+
+    let payment = entity::Payment {
+        lender_account_id: payment.lender_account_id,
+        amount: payment.amount.cents(),
+        event_date: payment.event_date,
+        description: payment.description.clone(),
+        currency,
+        ..current.clone()
+    };
+    validate(&payment)?;
+
+    // the balances lose the current payment and get the new one
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let mut changes =
+        service::balance::reverted(vec![service::balance::of_payment(&rates, &current)?]);
+    changes.push(service::balance::of_payment(&rates, &payment)?);
+
+    repos
+        .payments
+        .update(&payment, &changes, ledger.balance_version)
+        .await
+}
+
+/// A payment moves a positive amount from one account to another
+fn validate(payment: &entity::Payment) -> Result<(), AppError> {
+    if payment.amount <= 0 {
+        return Err(AppError::Service(format!(
+            "amount of a payment needs to be positive but is {}",
+            Money::from_cents(payment.amount)
+        )));
+    }
+
+    if payment.payer_account_id == payment.lender_account_id {
+        return Err(AppError::Service(format!(
+            "account {} can not pay itself",
+            payment.payer_account_id
+        )));
     }
 
     Ok(())
 }
 
-pub async fn get(pool: &PgPool, payment_id: Uuid) -> Result<entity::Payment, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Payment,
-        r#"
-            SELECT *
-            FROM payment
-                WHERE id = $1
-        "#,
-        payment_id
-    )
-    .fetch_one(pool)
-    .await?)
+pub async fn delete(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payment_id: Uuid,
+) -> Result<(), AppError> {
+    service::balance::retry(|| try_delete(repos, ledger_id, payment_id)).await
 }
 
-pub async fn get_for_account(
-    pool: &PgPool,
-    payer_account_id: Uuid,
-) -> Result<Vec<entity::Payment>, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Payment,
-        r#"
-            SELECT *
-            FROM payment
-                WHERE payer_account_id = $1
-        "#,
-        payer_account_id
-    )
-    .fetch_all(pool)
-    .await?)
+/// One attempt of `delete`, fails with a conflict if the balances changed in between
+async fn try_delete(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payment_id: Uuid,
+) -> Result<(), AppError> {
+    // read first, so the reverted payment is not older than its balance version
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let payment = get(repos, ledger_id, payment_id).await?;
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let change = service::balance::of_payment(&rates, &payment)?;
+
+    repos
+        .payments
+        .delete(
+            ledger_id,
+            payment_id,
+            &service::balance::reverted(vec![change]),
+            ledger.balance_version,
+        )
+        .await
+}
+
+pub async fn get(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    payment_id: Uuid,
+) -> Result<entity::Payment, AppError> {
+    repos.payments.get(ledger_id, payment_id).await
 }
 
+/// The payment if it was payed by the account, other payments are not found under its path
+pub async fn get_of_payer(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    payment_id: Uuid,
+) -> Result<entity::Payment, AppError> {
+    let payment = get(repos, ledger_id, payment_id).await?;
+    if payment.payer_account_id != account_id {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(payment)
+}
+
+/// The payment if the account payed or received it, other payments are not found under its path
+pub async fn get_of_party(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    payment_id: Uuid,
+) -> Result<entity::Payment, AppError> {
+    let payment = get(repos, ledger_id, payment_id).await?;
+    if payment.payer_account_id != account_id && payment.lender_account_id != account_id {
+        return Err(AppError::NotFound);
+    }
+
+    Ok(payment)
+}
+
+/// One page of the payments that match all filters of the query
+pub async fn get_all(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    query: request::PaymentsQuery,
+) -> Result<response::PaymentPageDto, AppError> {
+    let mut page = service::page::of_query(
+        query.sort,
+        query.order,
+        query.limit,
+        query.cursor.as_deref(),
+    )?;
+    let filter = PaymentFilter {
+        start_date: query.start_date,
+        end_date: query.end_date,
+        payer_account_id: query.payer_account_id,
+        lender_account_id: query.lender_account_id,
+        min_amount: query.min_amount.map(Money::cents),
+        max_amount: query.max_amount.map(Money::cents),
+    };
+
+    // one more payment than requested tells if there is a next page
+    let limit = page.limit;
+    page.limit += 1;
+    let mut payments = repos.payments.get_all(ledger_id, &filter, &page).await?;
+
+    let next_cursor = if payments.len() > usize::try_from(limit).unwrap_or(0) {
+        payments.pop();
+        payments.last().map(|payment| {
+            service::page::cursor(
+                &page,
+                page.position(payment.amount, payment.event_date),
+                payment.id,
+            )
+        })
+    } else {
+        None
+    };
+
+    Ok(response::PaymentPageDto {
+        payments: payments.into_iter().map(Into::into).collect(),
+        next_cursor,
+    })
+}
+
+/// Payments payed and received by the account in the given range, newest first
 pub async fn get_of_account(
-    pool: &PgPool,
-    payer_account_id: Uuid,
-) -> Result<Vec<entity::Payment>, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Payment,
-        r#"
-            SELECT *
-            FROM payment
-                WHERE lender_account_id = $1
-        "#,
-        payer_account_id
-    )
-    .fetch_all(pool)
-    .await?)
+    repos: &Repositories,
+    ledger_id: Uuid,
+    account_id: Uuid,
+    start_date: Option<chrono::NaiveDate>,
+    end_date: Option<chrono::NaiveDate>,
+) -> Result<response::AccountPaymentsDto, AppError> {
+    // the queries by account do not know the ledger
+    service::account::get(repos, ledger_id, account_id).await?;
+
+    let mut outgoing = repos
+        .payments
+        .get_for_account(account_id, start_date, end_date)
+        .await?;
+    let mut incoming = repos
+        .payments
+        .get_of_account(account_id, start_date, end_date)
+        .await?;
+    for payments in [&mut outgoing, &mut incoming] {
+        payments.sort_by(|a, b| b.event_date.cmp(&a.event_date).then(a.id.cmp(&b.id)));
+    }
+
+    Ok(response::AccountPaymentsDto {
+        outgoing: outgoing.into_iter().map(Into::into).collect(),
+        incoming: incoming.into_iter().map(Into::into).collect(),
+    })
 }
 
-pub async fn get_all(pool: &PgPool) -> Result<Vec<entity::Payment>, AppError> {
-    Ok(sqlx::query_as!(
-        entity::Payment,
-        r#"
-            SELECT *
-            FROM payment
-        "#,
-    )
-    .fetch_all(pool)
-    .await?)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(lender_account_id: Uuid, amount: &str) -> request::CreatePaymentDto {
+        request::CreatePaymentDto {
+            lender_account_id,
+            amount: amount.parse().unwrap(),
+            currency: None,
+            event_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+            description: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn payments_need_a_positive_amount_and_two_accounts() {
+        let repos = Repositories::memory();
+        let ledger = service::ledger::create(&repos, "Flat".to_string(), None)
+            .await
+            .unwrap();
+        let alice = service::account::create(&repos, ledger.id, "Alice".to_string(), None)
+            .await
+            .unwrap();
+        let bob = service::account::create(&repos, ledger.id, "Bob".to_string(), None)
+            .await
+            .unwrap();
+
+        for invalid in [
+            payment(bob.id, "-5.00"),
+            payment(bob.id, "0"),
+            payment(alice.id, "5.00"),
+        ] {
+            assert!(matches!(
+                create(&repos, ledger.id, alice.id, invalid).await,
+                Err(AppError::Service(_))
+            ));
+        }
+
+        let stored = create(&repos, ledger.id, alice.id, payment(bob.id, "5.00"))
+            .await
+            .unwrap();
+        for invalid in [
+            payment(bob.id, "-5.00"),
+            payment(bob.id, "0"),
+            payment(alice.id, "5.00"),
+        ] {
+            assert!(matches!(
+                update(&repos, ledger.id, stored.id, invalid).await,
+                Err(AppError::Service(_))
+            ));
+        }
+
+        assert_eq!(get(&repos, ledger.id, stored.id).await.unwrap().amount, 500);
+        assert_eq!(repos.balances.get_all(ledger.id).await.unwrap().len(), 2);
+    }
 }
@@ -0,0 +1,165 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Scope};
+use crate::repository::{Repositories, TokenUser};
+use crate::service;
+
+/// Distinguishes personal access tokens from the access tokens of sessions
+pub const PREFIX: &str = "mt_pat_";
+
+/// Create a token for the user, the returned token is not stored and can not be shown again
+pub async fn create(
+    repos: &Repositories,
+    user_id: Uuid,
+    name: String,
+    scopes: Vec<Scope>,
+    expires_in_days: Option<i32>,
+) -> Result<(String, entity::PersonalAccessToken), AppError> {
+    if scopes.is_empty() {
+        return Err(AppError::Service(
+            "token needs at least one scope".to_string(),
+        ));
+    }
+
+    if expires_in_days.is_some_and(|days| days < 1) {
+        return Err(AppError::Service(
+            "token has to be valid for at least one day".to_string(),
+        ));
+    }
+
+    let token = format!("{PREFIX}{}", service::session::generate());
+
+    let personal_access_token = repos
+        .personal_access_tokens
+        .create(
+            user_id,
+            &name,
+            &service::session::hash(&token),
+            &scopes,
+            expires_in_days.map(|days| chrono::Duration::days(days.into())),
+        )
+        .await?;
+
+    Ok((token, personal_access_token))
+}
+
+/// User of the token, `Forbidden` if it is unknown, expired or lacks the scope
+pub async fn get_user(
+    repos: &Repositories,
+    token: &str,
+    scope: Scope,
+) -> Result<TokenUser, AppError> {
+    repos
+        .personal_access_tokens
+        .use_token(&service::session::hash(token), scope)
+        .await?
+        .ok_or(AppError::Forbidden)
+}
+
+/// All tokens of the user, newest first
+pub async fn get_all_of_user(
+    repos: &Repositories,
+    user_id: Uuid,
+) -> Result<Vec<entity::PersonalAccessToken>, AppError> {
+    repos.personal_access_tokens.get_all_of_user(user_id).await
+}
+
+/// Revoke the token, scripts using it lose access immediately
+pub async fn delete(repos: &Repositories, user_id: Uuid, token_id: Uuid) -> Result<(), AppError> {
+    repos.personal_access_tokens.delete(user_id, token_id).await
+}
+
+/// Delete all tokens that expired, returns how many were deleted
+///
+/// Revoked tokens are deleted right away, expired ones are kept until they are purged
+pub async fn purge_expired(repos: &Repositories) -> Result<u64, AppError> {
+    repos.personal_access_tokens.purge_expired().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::entity::Role;
+
+    #[tokio::test]
+    async fn tokens_only_grant_their_scopes() {
+        for repos in [Repositories::memory(), Repositories::sqlite_memory().await] {
+            let user_id = repos
+                .users
+                .create("discord", "alice", Role::ReadOnly)
+                .await
+                .unwrap();
+
+            let (token, created) = create(
+                &repos,
+                user_id,
+                "script".to_string(),
+                vec![Scope::CostsRead, Scope::SnapshotRead],
+                Some(30),
+            )
+            .await
+            .unwrap();
+            assert!(token.starts_with(PREFIX));
+            assert!(created.last_used_at.is_none());
+
+            let user = get_user(&repos, &token, Scope::CostsRead).await.unwrap();
+            assert_eq!(user_id, user.user_id);
+            assert_eq!(None, user.session_id);
+            assert_eq!(Role::ReadOnly, user.role);
+            // the identity has no username before the first login
+            assert_eq!("script", user.username);
+
+            assert!(get_user(&repos, &token, Scope::CostsWrite).await.is_err());
+            assert!(get_user(&repos, "mt_pat_unknown", Scope::CostsRead)
+                .await
+                .is_err());
+
+            let tokens = get_all_of_user(&repos, user_id).await.unwrap();
+            assert_eq!(
+                vec![Scope::CostsRead, Scope::SnapshotRead],
+                tokens[0].scopes
+            );
+            assert!(tokens[0].last_used_at.is_some());
+
+            delete(&repos, user_id, created.id).await.unwrap();
+            assert!(get_user(&repos, &token, Scope::CostsRead).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn purge_only_expired_tokens() {
+        for repos in [Repositories::memory(), Repositories::sqlite_memory().await] {
+            let user_id = repos
+                .users
+                .create("discord", "alice", Role::Member)
+                .await
+                .unwrap();
+
+            for (name, lifetime) in [
+                ("expired", Some(chrono::Duration::seconds(-1))),
+                ("valid", Some(chrono::Duration::days(1))),
+                ("forever", None),
+            ] {
+                repos
+                    .personal_access_tokens
+                    .create(user_id, name, name, &[Scope::CostsRead], lifetime)
+                    .await
+                    .unwrap();
+            }
+
+            assert_eq!(1, purge_expired(&repos).await.unwrap());
+            assert_eq!(0, purge_expired(&repos).await.unwrap());
+
+            let names = get_all_of_user(&repos, user_id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect::<Vec<_>>();
+            assert!(names.contains(&"valid".to_string()));
+            assert!(names.contains(&"forever".to_string()));
+            assert_eq!(2, names.len());
+        }
+    }
+}
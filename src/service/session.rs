@@ -0,0 +1,268 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::dto::auth::{ClientInfo, TokenDto};
+use crate::model::entity;
+use crate::repository::{NewSession, Repositories, TokenUser};
+use crate::service;
+
+/// Minutes an access token is valid, can be overwritten via `ACCESS_TOKEN_LIFETIME_MINUTES`
+const DEFAULT_ACCESS_LIFETIME_MINUTES: i32 = 15;
+
+/// Days a session can be refreshed, can be overwritten via `REFRESH_TOKEN_LIFETIME_DAYS`
+const DEFAULT_REFRESH_LIFETIME_DAYS: i32 = 30;
+
+fn lifetime(variable: &str, default: i32) -> i32 {
+    std::env::var(variable)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn access_lifetime() -> chrono::Duration {
+    chrono::Duration::minutes(
+        lifetime(
+            "ACCESS_TOKEN_LIFETIME_MINUTES",
+            DEFAULT_ACCESS_LIFETIME_MINUTES,
+        )
+        .into(),
+    )
+}
+
+fn refresh_lifetime() -> chrono::Duration {
+    chrono::Duration::days(
+        lifetime("REFRESH_TOKEN_LIFETIME_DAYS", DEFAULT_REFRESH_LIFETIME_DAYS).into(),
+    )
+}
+
+/// Random token of 256 bits from the os CSPRNG, hex encoded
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Only this hash is stored, so a leaked database does not contain usable tokens
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_tokens() -> TokenDto {
+    TokenDto {
+        access_token: generate(),
+        refresh_token: generate(),
+        expires_in: access_lifetime().num_seconds(),
+    }
+}
+
+/// Start a new session of the user (e.g. a new device) and return its tokens
+pub async fn create(
+    repos: &Repositories,
+    user_id: Uuid,
+    username: &str,
+    client: ClientInfo,
+) -> Result<TokenDto, AppError> {
+    let tokens = new_tokens();
+
+    repos
+        .sessions
+        .create(
+            &NewSession {
+                user_id,
+                username: username.to_string(),
+                user_agent: client.user_agent,
+                ip: client.ip,
+                token_hash: hash(&tokens.access_token),
+                refresh_token_hash: hash(&tokens.refresh_token),
+            },
+            access_lifetime(),
+            refresh_lifetime(),
+        )
+        .await?;
+
+    Ok(tokens)
+}
+
+/// User of the access token of a session, `Forbidden` if it is unknown or expired
+pub async fn get_user(repos: &Repositories, access_token: &str) -> Result<TokenUser, AppError> {
+    repos
+        .sessions
+        .get_user(&hash(access_token))
+        .await?
+        .ok_or(AppError::Forbidden)
+}
+
+/// Exchange the refresh token for new tokens, the old ones can not be used afterwards
+///
+/// Using an already exchanged refresh token again ends the whole session,
+/// as either the client or an attacker holds a stolen token
+pub async fn refresh(repos: &Repositories, refresh_token: &str) -> Result<TokenDto, AppError> {
+    let refresh_hash = hash(refresh_token);
+
+    if repos.sessions.delete_reused(&refresh_hash).await? {
+        tracing::warn!("refresh token was used twice, its session is revoked");
+        return Err(AppError::Forbidden);
+    }
+
+    let tokens = new_tokens();
+
+    repos
+        .sessions
+        .refresh(
+            &refresh_hash,
+            &hash(&tokens.access_token),
+            access_lifetime(),
+            &hash(&tokens.refresh_token),
+        )
+        .await?;
+
+    Ok(tokens)
+}
+
+/// All sessions of the user, newest first
+pub async fn get_all_of_user(
+    repos: &Repositories,
+    user_id: Uuid,
+) -> Result<Vec<entity::Session>, AppError> {
+    repos.sessions.get_all_of_user(user_id).await
+}
+
+/// End one session of the user, e.g. of a lost device
+pub async fn delete(repos: &Repositories, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+    repos.sessions.delete(user_id, session_id).await
+}
+
+/// End every session of the user except the given one, e.g. after the password changed
+pub async fn delete_all_except(
+    repos: &Repositories,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<(), AppError> {
+    repos.sessions.delete_all_except(user_id, session_id).await
+}
+
+/// Delete all sessions that can not be refreshed anymore, returns how many were deleted
+pub async fn purge_expired(repos: &Repositories) -> Result<u64, AppError> {
+    repos.sessions.purge_expired().await
+}
+
+/// Regularly purge expired sessions, unfinished logins and expired personal access tokens for as
+/// long as the server runs
+pub async fn purge_expired_periodically(repos: Repositories, period: std::time::Duration) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        match purge_expired(&repos).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("purged {count} expired sessions"),
+            Err(err) => tracing::error!("could not purge expired sessions: {err:?}"),
+        }
+
+        if let Err(err) = service::oauth::purge_expired(&repos).await {
+            tracing::error!("could not purge expired logins: {err:?}");
+        }
+
+        match service::personal_access_token::purge_expired(&repos).await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("purged {count} expired personal access tokens"),
+            Err(err) => tracing::error!("could not purge expired personal access tokens: {err:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_unique_tokens() {
+        let token = generate();
+
+        assert_eq!(64, token.len());
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate());
+    }
+
+    #[test]
+    fn hash_token_deterministically() {
+        assert_eq!(hash("token"), hash("token"));
+        assert_ne!(hash("token"), hash("other"));
+        assert_ne!("token", hash("token"));
+    }
+
+    #[tokio::test]
+    async fn rotate_tokens_and_revoke_session_on_reuse() {
+        for repos in [Repositories::memory(), Repositories::sqlite_memory().await] {
+            let user_id = repos
+                .users
+                .create("discord", "alice", entity::Role::Member)
+                .await
+                .unwrap();
+
+            let first = create(&repos, user_id, "alice", ClientInfo::default())
+                .await
+                .unwrap();
+            let user = get_user(&repos, &first.access_token).await.unwrap();
+            assert_eq!(user_id, user.user_id);
+            assert_eq!("alice", user.username);
+
+            let second = refresh(&repos, &first.refresh_token).await.unwrap();
+            assert!(get_user(&repos, &first.access_token).await.is_err());
+            assert_eq!(
+                user.session_id,
+                get_user(&repos, &second.access_token)
+                    .await
+                    .unwrap()
+                    .session_id
+            );
+
+            // the first refresh token was stolen, so the session ends for both sides
+            assert!(matches!(
+                refresh(&repos, &first.refresh_token).await,
+                Err(AppError::Forbidden)
+            ));
+            assert!(get_user(&repos, &second.access_token).await.is_err());
+            assert!(get_all_of_user(&repos, user_id).await.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn end_other_sessions() {
+        for repos in [Repositories::memory(), Repositories::sqlite_memory().await] {
+            let user_id = repos
+                .users
+                .create("discord", "alice", entity::Role::Member)
+                .await
+                .unwrap();
+
+            let phone = create(&repos, user_id, "alice", ClientInfo::default())
+                .await
+                .unwrap();
+            let laptop = create(&repos, user_id, "alice", ClientInfo::default())
+                .await
+                .unwrap();
+            let session_id = get_user(&repos, &phone.access_token)
+                .await
+                .unwrap()
+                .session_id
+                .unwrap();
+
+            delete_all_except(&repos, user_id, session_id)
+                .await
+                .unwrap();
+
+            assert!(get_user(&repos, &phone.access_token).await.is_ok());
+            assert!(get_user(&repos, &laptop.access_token).await.is_err());
+            assert!(matches!(
+                delete(&repos, Uuid::new_v4(), session_id).await,
+                Err(AppError::NotFound)
+            ));
+            delete(&repos, user_id, session_id).await.unwrap();
+            assert_eq!(0, purge_expired(&repos).await.unwrap());
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::{dto::response, entity, money::Money};
+use crate::repository::Repositories;
+use crate::service;
+
+/// Calculate the transfers needed to settle all debts between the accounts
+pub async fn get_plan(
+    repos: &Repositories,
+    ledger_id: Uuid,
+) -> Result<Vec<response::CalculatedDebtDto>, AppError> {
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let snapshot = service::cost::get_current_snapshot(repos, ledger_id, None, None).await?;
+
+    // the snapshot contains every pair in both directions, so summing up all rows of an account
+    // results in the overall amount the account still gets from (or owes to) everyone else
+    let mut balances: HashMap<Uuid, (response::AccountDto, i64)> = HashMap::new();
+    for debt in &snapshot {
+        balances
+            .entry(debt.payer_account.id)
+            .or_insert_with(|| (debt.payer_account.clone(), 0))
+            .1 += debt.amount.cents();
+    }
+
+    Ok(settle(
+        balances.into_values().collect(),
+        &ledger.base_currency,
+    ))
+}
+
+/// Record all transfers of the current settlement plan as payments (in the base currency of the ledger)
+pub async fn apply(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    description: Option<String>,
+    event_date: chrono::NaiveDate,
+) -> Result<Vec<entity::Payment>, AppError> {
+    service::balance::retry(|| try_apply(repos, ledger_id, description.as_deref(), event_date))
+        .await
+}
+
+/// One attempt of `apply`, fails with a conflict if the balances changed since the plan was made
+async fn try_apply(
+    repos: &Repositories,
+    ledger_id: Uuid,
+    description: Option<&str>,
+    event_date: chrono::NaiveDate,
+) -> Result<Vec<entity::Payment>, AppError> {
+    // read before the plan, so the plan is not older than its balance version
+    let ledger = service::ledger::get(repos, ledger_id).await?;
+    let plan = get_plan(repos, ledger_id).await?;
+
+    let payments = plan
+        .iter()
+        .map(|transfer| entity::Payment {
+            id: Uuid::new_v4(),
+            ledger_id,
+            payer_account_id: transfer.payer_account.id,
+            lender_account_id: transfer.lender_account.id,
+            amount: transfer.amount.cents(),
+            event_date,
+            description: description.map(str::to_string),
+            currency: ledger.base_currency.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let rates = service::exchange_rate::get_rates(repos, ledger.base_currency).await?;
+    let changes = payments
+        .iter()
+        .map(|payment| service::balance::of_payment(&rates, payment))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    repos
+        .payments
+        .create(ledger_id, &payments, &changes, ledger.balance_version)
+        .await?;
+
+    Ok(payments)
+}
+
+/// Turn the balances of all accounts into transfers from accounts that owe money
+/// to accounts that get money
+///
+/// Accounts owing exactly what another account gets are matched first, afterwards the largest
+/// debtor always pays the largest creditor, which needs at most one transfer less than there are
+/// accounts with an open balance
+fn settle(
+    balances: Vec<(response::AccountDto, i64)>,
+    currency: &str,
+) -> Vec<response::CalculatedDebtDto> {
+    let mut creditors = balances
+        .iter()
+        .filter(|b| b.1 > 0)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut debtors = balances
+        .iter()
+        .filter(|b| b.1 < 0)
+        .map(|b| (b.0.clone(), -b.1))
+        .collect::<Vec<_>>();
+
+    let mut transfers = Vec::new();
+    let mut transfer = |debtor: &response::AccountDto, creditor: &response::AccountDto, amount| {
+        transfers.push(response::CalculatedDebtDto {
+            payer_account: debtor.clone(),
+            lender_account: creditor.clone(),
+            amount: Money::from_cents(amount),
+            currency: currency.to_string(),
+        });
+    };
+
+    // sort by amount (and id to always get the same plan for the same balances)
+    let sort = |list: &mut Vec<(response::AccountDto, i64)>| {
+        list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.id.cmp(&b.0.id)));
+    };
+    sort(&mut creditors);
+    sort(&mut debtors);
+
+    for debtor in &mut debtors {
+        if let Some(creditor) = creditors.iter_mut().find(|c| c.1 == debtor.1) {
+            transfer(&debtor.0, &creditor.0, debtor.1);
+            creditor.1 = 0;
+            debtor.1 = 0;
+        }
+    }
+
+    loop {
+        creditors.retain(|c| c.1 > 0);
+        debtors.retain(|d| d.1 > 0);
+        sort(&mut creditors);
+        sort(&mut debtors);
+
+        let (Some(creditor), Some(debtor)) = (creditors.first_mut(), debtors.first_mut()) else {
+            break;
+        };
+
+        let amount = creditor.1.min(debtor.1);
+        transfer(&debtor.0, &creditor.0, amount);
+        creditor.1 -= amount;
+        debtor.1 -= amount;
+    }
+
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(name: &str) -> response::AccountDto {
+        response::AccountDto {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            user_id: None,
+        }
+    }
+
+    fn sum_for(transfers: &[response::CalculatedDebtDto], account: &response::AccountDto) -> i64 {
+        transfers
+            .iter()
+            .map(|t| {
+                if t.payer_account.id == account.id {
+                    t.amount.cents()
+                } else if t.lender_account.id == account.id {
+                    -t.amount.cents()
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn settle_all_balances_with_minimal_transfers() {
+        let (a, b, c, d) = (account("A"), account("B"), account("C"), account("D"));
+        let balances = vec![
+            (a.clone(), 1500),
+            (b.clone(), -1000),
+            (c.clone(), -500),
+            (d.clone(), 0),
+        ];
+
+        let transfers = settle(balances, "EUR");
+
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(sum_for(&transfers, &a), -1500);
+        assert_eq!(sum_for(&transfers, &b), 1000);
+        assert_eq!(sum_for(&transfers, &c), 500);
+        assert_eq!(sum_for(&transfers, &d), 0);
+    }
+
+    #[test]
+    fn settle_matches_equal_balances_first() {
+        let (a, b) = (account("A"), account("B"));
+        let (x, y, z) = (account("X"), account("Y"), account("Z"));
+        let balances = vec![
+            (a.clone(), 600),
+            (b.clone(), 500),
+            (x.clone(), -500),
+            (y.clone(), -400),
+            (z.clone(), -200),
+        ];
+
+        let transfers = settle(balances, "EUR");
+
+        // without matching x to b first, the largest to largest strategy would need 4 transfers
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(sum_for(&transfers, &a), -600);
+        assert_eq!(sum_for(&transfers, &b), -500);
+        assert_eq!(sum_for(&transfers, &x), 500);
+        assert_eq!(sum_for(&transfers, &y), 400);
+        assert_eq!(sum_for(&transfers, &z), 200);
+    }
+
+    #[test]
+    fn settle_nothing_when_everything_is_payed() {
+        assert!(settle(vec![(account("A"), 0), (account("B"), 0)], "EUR").is_empty());
+    }
+}
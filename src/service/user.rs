@@ -0,0 +1,53 @@
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::model::entity::{self, Role};
+use crate::repository::Repositories;
+
+pub async fn get(repos: &Repositories, user_id: Uuid) -> Result<entity::User, AppError> {
+    repos.users.get(user_id).await
+}
+
+pub async fn get_all(repos: &Repositories) -> Result<Vec<entity::User>, AppError> {
+    repos.users.get_all().await
+}
+
+/// Allow the user of the provider to login with the given role
+pub async fn invite(
+    repos: &Repositories,
+    provider: String,
+    subject: String,
+    role: Role,
+) -> Result<entity::User, AppError> {
+    let user_id = repos.users.create(&provider, &subject, role).await?;
+
+    get(repos, user_id).await
+}
+
+pub async fn update_role(
+    repos: &Repositories,
+    user_id: Uuid,
+    role: Role,
+) -> Result<entity::User, AppError> {
+    repos.users.update_role(user_id, role).await?;
+
+    get(repos, user_id).await
+}
+
+/// Remove the user together with all of its identities and sessions
+pub async fn delete(repos: &Repositories, user_id: Uuid) -> Result<(), AppError> {
+    repos.users.delete(user_id).await
+}
+
+/// All logins of the user, oldest first
+pub async fn get_identities(
+    repos: &Repositories,
+    user_id: Uuid,
+) -> Result<Vec<entity::Identity>, AppError> {
+    repos.users.get_identities(user_id).await
+}
+
+/// Identities of all users, oldest first
+pub async fn get_all_identities(repos: &Repositories) -> Result<Vec<entity::Identity>, AppError> {
+    repos.users.get_all_identities().await
+}
@@ -0,0 +1,299 @@
+mod common;
+
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+use common::ADMIN;
+
+#[tokio::test]
+async fn login_invited_user_via_provider() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let login = app.login(ADMIN).await;
+    let me = app.get("/me", &login.access_token).await;
+
+    assert_eq!("admin", me["user"]["role"]);
+    assert_eq!(format!("user-{ADMIN}"), me["username"]);
+    assert_eq!("discord", me["user"]["identities"][0]["provider"]);
+    assert_eq!(
+        format!("user-{ADMIN}"),
+        me["user"]["identities"][0]["username"]
+    );
+}
+
+#[tokio::test]
+async fn reject_login_of_uninvited_user() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let response = app.authorize("not-invited").await;
+
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
+
+#[tokio::test]
+async fn only_redirect_to_allowed_origins() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let response = app
+        .request(
+            Method::GET,
+            "/auth/discord?origin_uri=https://evil.example.com",
+            None,
+            None,
+        )
+        .await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, response.status());
+}
+
+#[tokio::test]
+async fn exchange_login_code_only_once() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let login_code = app.login_code(ADMIN).await;
+    let exchange = || {
+        app.request(
+            Method::POST,
+            "/auth/token",
+            None,
+            Some(json!({ "login_code": login_code })),
+        )
+    };
+
+    assert_eq!(StatusCode::OK, exchange().await.status());
+    assert_eq!(StatusCode::FORBIDDEN, exchange().await.status());
+}
+
+#[tokio::test]
+async fn rotate_refresh_token_and_revoke_session_on_reuse() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let login = app.login(ADMIN).await;
+    let refresh = |refresh_token: String| {
+        app.request(
+            Method::POST,
+            "/auth/refresh",
+            None,
+            Some(json!({ "refresh_token": refresh_token })),
+        )
+    };
+
+    let response = refresh(login.refresh_token.clone()).await;
+    assert_eq!(StatusCode::OK, response.status());
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    let access_token = tokens["access_token"].as_str().unwrap();
+
+    // the old access token is replaced by the new one
+    app.call(
+        Method::GET,
+        "/me",
+        &login.access_token,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    app.get("/me", access_token).await;
+
+    // using the old refresh token again ends the session
+    let response = refresh(login.refresh_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    app.call(
+        Method::GET,
+        "/me",
+        access_token,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn logout_ends_only_current_session() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let first = app.login(ADMIN).await;
+    let second = app.login(ADMIN).await;
+
+    let sessions = app.get("/auth/sessions", &first.access_token).await;
+    assert_eq!(2, sessions.as_array().unwrap().len());
+
+    app.call(
+        Method::GET,
+        "/auth/logout",
+        &first.access_token,
+        None,
+        StatusCode::OK,
+    )
+    .await;
+
+    app.call(
+        Method::GET,
+        "/me",
+        &first.access_token,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    app.get("/me", &second.access_token).await;
+}
+
+#[tokio::test]
+async fn read_only_user_can_not_change_data() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let admin = app.login(ADMIN).await;
+    app.post(
+        "/admin/user",
+        &admin.access_token,
+        json!({ "provider": "discord", "subject": "42", "role": "read_only" }),
+    )
+    .await;
+
+    let reader = app.login("42").await;
+    app.get("/ledger", &reader.access_token).await;
+    app.call(
+        Method::POST,
+        "/ledger",
+        &reader.access_token,
+        Some(json!({ "name": "home" })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    app.call(
+        Method::GET,
+        "/admin/user",
+        &reader.access_token,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn only_admins_change_exchange_rates() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let admin = app.login(ADMIN).await;
+    app.post(
+        "/admin/user",
+        &admin.access_token,
+        json!({ "provider": "discord", "subject": "43", "role": "member" }),
+    )
+    .await;
+    let member = app.login("43").await;
+    app.call(
+        Method::POST,
+        "/exchange-rate",
+        &member.access_token,
+        Some(json!({
+            "date": "2026-10-01",
+            "from_currency": "USD",
+            "to_currency": "EUR",
+            "rate": 0.9,
+        })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn login_with_local_password() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+
+    let admin = app.login(ADMIN).await;
+    let invite = app
+        .post(
+            "/admin/user/local",
+            &admin.access_token,
+            json!({ "username": "Alice" }),
+        )
+        .await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/auth/local/register",
+            None,
+            Some(json!({
+                "username": "alice",
+                "registration_code": invite["registration_code"],
+                "password": "correct horse battery",
+            })),
+        )
+        .await;
+    assert_eq!(StatusCode::OK, response.status());
+
+    let login = |password: &'static str| {
+        app.request(
+            Method::POST,
+            "/auth/local/login",
+            None,
+            Some(json!({ "username": "ALICE", "password": password })),
+        )
+    };
+
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        login("wrong horse battery").await.status()
+    );
+
+    let response = login("correct horse battery").await;
+    assert_eq!(StatusCode::OK, response.status());
+    let tokens: serde_json::Value = response.json().await.unwrap();
+
+    let me = app
+        .get("/me", tokens["access_token"].as_str().unwrap())
+        .await;
+    assert_eq!("member", me["user"]["role"]);
+    assert_eq!("alice", me["username"]);
+
+    // a correct password resets the failed attempts, the fifth one in a row locks the login
+    for _ in 0..4 {
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            login("wrong horse battery").await.status()
+        );
+    }
+    assert_eq!(
+        StatusCode::OK,
+        login("correct horse battery").await.status()
+    );
+    for _ in 0..5 {
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            login("wrong horse battery").await.status()
+        );
+    }
+    assert_eq!(
+        StatusCode::FORBIDDEN,
+        login("correct horse battery").await.status()
+    );
+
+    let response = app
+        .request(
+            Method::POST,
+            "/auth/local/login",
+            None,
+            Some(json!({ "username": "bob", "password": "correct horse battery" })),
+        )
+        .await;
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+}
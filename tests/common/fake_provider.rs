@@ -0,0 +1,148 @@
+//! Stand-in for an OAuth provider like discord, so the login works without network access
+//!
+//! `/authorize` logs in whoever is given via the `login_as` query param,
+//! the userinfo of that subject is `{ "id": subject, "username": "user-{subject}" }`
+
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use axum::{
+    extract::Query,
+    http::{HeaderMap, StatusCode},
+    response::Redirect,
+    routing::{get, post},
+    Extension, Form, Json, Router,
+};
+use oauth2::{url::Url, PkceCodeChallenge, PkceCodeVerifier};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Default)]
+struct State {
+    /// code of the redirect -> (subject, pkce challenge)
+    codes: HashMap<String, (String, String)>,
+    /// access token -> subject
+    tokens: HashMap<String, String>,
+}
+
+type SharedState = Arc<Mutex<State>>;
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    state: String,
+    redirect_uri: String,
+    code_challenge: String,
+    login_as: String,
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    code: String,
+    code_verifier: String,
+}
+
+/// Address of the fake provider, which is started once per test binary
+pub fn address() -> SocketAddr {
+    static ADDRESS: OnceLock<SocketAddr> = OnceLock::new();
+
+    *ADDRESS.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("fake provider can bind");
+        listener
+            .set_nonblocking(true)
+            .expect("fake provider is non blocking");
+        let address = listener.local_addr().expect("fake provider has an address");
+
+        // own runtime, as the runtime of a single test ends with it
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .expect("runtime can be created")
+                .block_on(async {
+                    axum::Server::from_tcp(listener)
+                        .expect("fake provider can listen")
+                        .serve(app().into_make_service())
+                        .await
+                        .expect("fake provider can serve");
+                });
+        });
+
+        address
+    })
+}
+
+fn app() -> Router {
+    Router::new()
+        .route("/authorize", get(authorize))
+        .route("/token", post(token))
+        .route("/userinfo", get(userinfo))
+        .layer(Extension(SharedState::default()))
+}
+
+async fn authorize(
+    Extension(state): Extension<SharedState>,
+    Query(query): Query<AuthorizeQuery>,
+) -> Redirect {
+    let code = Uuid::new_v4().to_string();
+    state
+        .lock()
+        .expect("state is not poisoned")
+        .codes
+        .insert(code.clone(), (query.login_as, query.code_challenge));
+
+    let mut redirect = Url::parse(&query.redirect_uri).expect("redirect uri is valid");
+    redirect
+        .query_pairs_mut()
+        .append_pair("code", &code)
+        .append_pair("state", &query.state);
+
+    Redirect::to(redirect.as_str())
+}
+
+async fn token(
+    Extension(state): Extension<SharedState>,
+    Form(form): Form<TokenForm>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut state = state.lock().expect("state is not poisoned");
+
+    // every code can only be exchanged once and only with the verifier of its challenge
+    let (subject, challenge) = state
+        .codes
+        .remove(&form.code)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let verifier = PkceCodeVerifier::new(form.code_verifier);
+    if PkceCodeChallenge::from_code_verifier_sha256(&verifier).as_str() != challenge {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let access_token = Uuid::new_v4().to_string();
+    state.tokens.insert(access_token.clone(), subject);
+
+    Ok(Json(json!({
+        "access_token": access_token,
+        "token_type": "bearer",
+        "expires_in": 3600,
+    })))
+}
+
+async fn userinfo(
+    Extension(state): Extension<SharedState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, StatusCode> {
+    let token = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let state = state.lock().expect("state is not poisoned");
+    let subject = state.tokens.get(token).ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(json!({
+        "id": subject,
+        "username": format!("user-{subject}"),
+        "avatar": null,
+    })))
+}
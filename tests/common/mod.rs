@@ -0,0 +1,302 @@
+//! Boots the whole api against a throwaway database, with the fake provider as discord
+//!
+//! Needs `DATABASE_URL` of a postgres server, every test creates and drops its own database there.
+//! Tests are skipped if it is not set.
+
+#![allow(dead_code, clippy::expect_used, clippy::unwrap_used)]
+
+pub mod fake_provider;
+
+use std::{
+    net::{SocketAddr, TcpListener},
+    str::FromStr,
+    sync::Once,
+};
+
+use oauth2::url::Url;
+use reqwest::{redirect, Method, Response, StatusCode};
+use serde_json::Value;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    ConnectOptions, PgPool,
+};
+use uuid::Uuid;
+
+/// Discord id of an admin, which is invited by the migrations
+pub const ADMIN: &str = "138371651942219777";
+
+pub const ORIGIN: &str = "http://localhost:5173";
+
+pub struct TestApp {
+    pub address: SocketAddr,
+    client: reqwest::Client,
+    base_options: PgConnectOptions,
+    database: String,
+}
+
+/// Tokens of a logged in user
+pub struct Login {
+    pub access_token: String,
+    pub refresh_token: String,
+}
+
+fn configure_env() {
+    static ONCE: Once = Once::new();
+
+    ONCE.call_once(|| {
+        dotenv::dotenv().ok();
+
+        let fake = format!("http://{}", fake_provider::address());
+        for (name, value) in [
+            ("DISCORD_CLIENT_ID", "money-tracker".to_string()),
+            ("DISCORD_CLIENT_SECRET", "secret".to_string()),
+            ("DISCORD_AUTH_URL", format!("{fake}/authorize")),
+            ("DISCORD_TOKEN_URL", format!("{fake}/token")),
+            ("DISCORD_USERINFO_URL", format!("{fake}/userinfo")),
+            ("ALLOWED_ORIGINS", ORIGIN.to_string()),
+            ("LOCAL_LOGIN", "true".to_string()),
+        ] {
+            std::env::set_var(name, value);
+        }
+
+        for name in ["GITHUB_CLIENT_ID", "OIDC_CLIENT_ID"] {
+            std::env::remove_var(name);
+        }
+    });
+}
+
+/// Start the api on a random port with a fresh database, `None` if there is no database to use
+pub async fn spawn() -> Option<TestApp> {
+    configure_env();
+
+    let Ok(url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping integration test");
+        return None;
+    };
+
+    let base_options = PgConnectOptions::from_str(&url)
+        .expect("DATABASE_URL is valid")
+        .disable_statement_logging()
+        .clone();
+    let database = format!("money_tracker_test_{}", Uuid::new_v4().simple());
+
+    let admin_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .connect_with(base_options.clone())
+        .await
+        .expect("can connect to DATABASE_URL");
+    sqlx::query(&format!(r#"CREATE DATABASE "{database}""#))
+        .execute(&admin_pool)
+        .await
+        .expect("can create test database");
+    admin_pool.close().await;
+
+    let pool = PgPoolOptions::new()
+        .connect_with(base_options.clone().database(&database))
+        .await
+        .expect("can connect to test database");
+    money_tracker::migrate(&pool)
+        .await
+        .expect("can run migrations");
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("api can bind");
+    listener.set_nonblocking(true).expect("api is non blocking");
+    let address = listener.local_addr().expect("api has an address");
+    let app = money_tracker::app(pool).await;
+
+    tokio::spawn(async move {
+        axum::Server::from_tcp(listener)
+            .expect("api can listen")
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("api can serve");
+    });
+
+    Some(TestApp {
+        address,
+        client: reqwest::Client::builder()
+            .redirect(redirect::Policy::none())
+            .build()
+            .expect("client can be built"),
+        base_options,
+        database,
+    })
+}
+
+fn location(response: &Response) -> Url {
+    let location = response
+        .headers()
+        .get("location")
+        .expect("response is a redirect")
+        .to_str()
+        .expect("location is valid");
+
+    Url::parse(location).expect("location is an absolute url")
+}
+
+fn query_param(url: &Url, name: &str) -> String {
+    url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_else(|| panic!("{url} has no {name}"))
+}
+
+impl TestApp {
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    /// The database of the test, e.g. to change data without the api
+    pub async fn pool(&self) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(self.base_options.clone().database(&self.database))
+            .await
+            .expect("can connect to test database")
+    }
+
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> Response {
+        let mut request = self.client.request(method, self.url(path));
+
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        request.send().await.expect("api can be called")
+    }
+
+    /// Call the api and return the json body, panics on another status
+    pub async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        token: &str,
+        body: Option<Value>,
+        expected: StatusCode,
+    ) -> Value {
+        let response = self.request(method.clone(), path, Some(token), body).await;
+        let status = response.status();
+        let text = response.text().await.expect("body can be read");
+
+        assert_eq!(expected, status, "{method} {path}: {text}");
+
+        serde_json::from_str(&text).unwrap_or(Value::Null)
+    }
+
+    pub async fn get(&self, path: &str, token: &str) -> Value {
+        self.call(Method::GET, path, token, None, StatusCode::OK)
+            .await
+    }
+
+    pub async fn post(&self, path: &str, token: &str, body: Value) -> Value {
+        self.call(Method::POST, path, token, Some(body), StatusCode::OK)
+            .await
+    }
+
+    /// Login via the fake provider like a browser, up to the redirect to the frontend
+    pub async fn authorize(&self, subject: &str) -> Response {
+        let response = self
+            .request(
+                Method::GET,
+                &format!("/auth/discord?origin_uri={ORIGIN}/done"),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(StatusCode::SEE_OTHER, response.status());
+
+        let mut authorize_url = location(&response);
+        authorize_url
+            .query_pairs_mut()
+            .append_pair("login_as", subject);
+
+        let response = self
+            .client
+            .get(authorize_url)
+            .send()
+            .await
+            .expect("fake provider can be called");
+        let redirect = location(&response);
+
+        self.request(
+            Method::GET,
+            &format!(
+                "/auth/authorized?code={}&state={}",
+                query_param(&redirect, "code"),
+                query_param(&redirect, "state"),
+            ),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// The one time login code of the redirect after a successful login
+    pub async fn login_code(&self, subject: &str) -> String {
+        let response = self.authorize(subject).await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, response.status());
+
+        let redirect = location(&response);
+        assert!(redirect.as_str().starts_with(ORIGIN));
+
+        query_param(&redirect, "login_code")
+    }
+
+    pub async fn login(&self, subject: &str) -> Login {
+        let login_code = self.login_code(subject).await;
+
+        let response = self
+            .request(
+                Method::POST,
+                "/auth/token",
+                None,
+                Some(serde_json::json!({ "login_code": login_code })),
+            )
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let tokens: Value = response.json().await.expect("tokens are json");
+
+        Login {
+            access_token: tokens["access_token"].as_str().unwrap().to_string(),
+            refresh_token: tokens["refresh_token"].as_str().unwrap().to_string(),
+        }
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let options = self.base_options.clone();
+        let database = self.database.clone();
+
+        // the runtime of the test is shutting down, so the database is dropped on another one
+        std::thread::spawn(move || {
+            tokio::runtime::Runtime::new()
+                .expect("runtime can be created")
+                .block_on(async {
+                    let pool = PgPoolOptions::new()
+                        .max_connections(1)
+                        .connect_with(options)
+                        .await
+                        .expect("can connect to DATABASE_URL");
+
+                    sqlx::query(&format!(r#"DROP DATABASE "{database}" WITH (FORCE)"#))
+                        .execute(&pool)
+                        .await
+                        .expect("can drop test database");
+                });
+        })
+        .join()
+        .ok();
+    }
+}
@@ -0,0 +1,820 @@
+mod common;
+
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::{TestApp, ADMIN};
+
+/// Ledger with the account of the admin and one of bob, who is not a user
+struct Ledger {
+    token: String,
+    id: String,
+    admin_account: String,
+    bob_account: String,
+}
+
+async fn setup(app: &TestApp) -> Ledger {
+    let token = app.login(ADMIN).await.access_token;
+
+    // the creator of the ledger gets an account in it
+    let ledger = app.post("/ledger", &token, json!({ "name": "flat" })).await;
+    let id = ledger["id"].as_str().unwrap().to_string();
+
+    let accounts = app.get(&format!("/ledger/{id}/account"), &token).await;
+    let admin_account = &accounts[0];
+    let bob_account = app
+        .post(
+            &format!("/ledger/{id}/account"),
+            &token,
+            json!({ "name": "bob" }),
+        )
+        .await;
+
+    Ledger {
+        token,
+        id,
+        admin_account: admin_account["id"].as_str().unwrap().to_string(),
+        bob_account: bob_account["id"].as_str().unwrap().to_string(),
+    }
+}
+
+fn debts_of(debtors: &Value) -> Vec<(String, String)> {
+    let mut debts: Vec<(String, String)> = debtors
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|debt| {
+            (
+                debt["account_id"].as_str().unwrap().to_string(),
+                debt["amount"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    debts.sort();
+    debts
+}
+
+/// Amount of the snapshot row between the two accounts
+fn amount(snapshot: &Value, payer_account: &str, lender_account: &str) -> String {
+    snapshot
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|debt| {
+            debt["payer_account"]["id"] == payer_account
+                && debt["lender_account"]["id"] == lender_account
+        })
+        .map(|debt| debt["amount"].as_str().unwrap().to_string())
+        .unwrap_or_else(|| panic!("snapshot has no debt of {payer_account} to {lender_account}"))
+}
+
+#[tokio::test]
+async fn ledgers_of_others_are_not_accessible() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    app.post(
+        "/admin/user",
+        token,
+        json!({ "provider": "discord", "subject": "43", "role": "member" }),
+    )
+    .await;
+    let other = app.login("43").await.access_token;
+
+    let own = app.post("/ledger", &other, json!({ "name": "trip" })).await;
+    let ledgers = app.get("/ledger", &other).await;
+    assert_eq!(json!([own]), ledgers);
+
+    for (method, path) in [
+        (Method::GET, format!("/ledger/{id}")),
+        (Method::GET, format!("/ledger/{id}/account")),
+        (Method::GET, format!("/ledger/{id}/cost")),
+        (Method::GET, format!("/ledger/{id}/payment")),
+        (Method::GET, format!("/ledger/{id}/snapshot")),
+        (Method::DELETE, format!("/ledger/{id}")),
+    ] {
+        app.call(method, &path, &other, None, StatusCode::NOT_FOUND)
+            .await;
+    }
+
+    // admins can access every ledger, e.g. to link the account of a new member
+    let own_id = own["id"].as_str().unwrap();
+    app.get(&format!("/ledger/{own_id}/account"), token).await;
+    let me = app.get("/me", &other).await;
+    app.post(
+        &format!("/ledger/{id}/account"),
+        token,
+        json!({ "name": "other", "user_id": me["user"]["id"] }),
+    )
+    .await;
+    app.get(&format!("/ledger/{id}/cost"), &other).await;
+    assert_eq!(
+        2,
+        app.get("/ledger", &other).await.as_array().unwrap().len()
+    );
+}
+
+#[tokio::test]
+async fn only_admins_link_accounts_to_other_users() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let token = app.login(ADMIN).await.access_token;
+    app.post(
+        "/admin/user",
+        &token,
+        json!({ "provider": "discord", "subject": "43", "role": "member" }),
+    )
+    .await;
+    let other = app.login("43").await.access_token;
+    let admin_id = app.get("/me", &token).await["user"]["id"].clone();
+    let other_id = app.get("/me", &other).await["user"]["id"].clone();
+
+    let ledger = app.post("/ledger", &other, json!({ "name": "trip" })).await;
+    let id = ledger["id"].as_str().unwrap();
+    let own = app.get(&format!("/ledger/{id}/account"), &other).await[0].clone();
+    let own_id = own["id"].as_str().unwrap();
+
+    // a rename keeps the link to the user
+    let renamed = app
+        .call(
+            Method::PUT,
+            &format!("/ledger/{id}/account/{own_id}"),
+            &other,
+            Some(json!({ "name": "renamed" })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!(other_id, renamed["user_id"]);
+
+    app.call(
+        Method::POST,
+        &format!("/ledger/{id}/account"),
+        &other,
+        Some(json!({ "name": "carol", "user_id": admin_id })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    let carol = app
+        .post(
+            &format!("/ledger/{id}/account"),
+            &other,
+            json!({ "name": "carol" }),
+        )
+        .await;
+    let carol_path = format!("/ledger/{id}/account/{}", carol["id"].as_str().unwrap());
+    app.call(
+        Method::PUT,
+        &carol_path,
+        &other,
+        Some(json!({ "name": "carol", "user_id": admin_id })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    app.call(
+        Method::PUT,
+        &format!("/ledger/{id}/account/{own_id}"),
+        &other,
+        Some(json!({ "name": "renamed", "user_id": admin_id })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    // only admins can link other users
+    let carol = app
+        .call(
+            Method::PUT,
+            &carol_path,
+            &token,
+            Some(json!({ "name": "carol", "user_id": admin_id })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!(admin_id, carol["user_id"]);
+}
+
+#[tokio::test]
+async fn create_update_and_delete_cost() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    let cost = app
+        .post(
+            &format!("/ledger/{id}/cost"),
+            token,
+            json!({
+                "amount": "30.00",
+                "split": "equal",
+                "event_date": "2026-10-01",
+                "description": "groceries",
+                "tags": ["food"],
+                "debtors": [
+                    { "account_id": ledger.admin_account },
+                    { "account_id": ledger.bob_account },
+                ],
+            }),
+        )
+        .await;
+    let cost_id = cost["id"].as_str().unwrap();
+    assert_eq!(ledger.admin_account, cost["account_id"]);
+    assert_eq!("EUR", cost["currency"]);
+    let mut expected = vec![
+        (ledger.admin_account.clone(), "15.00".to_string()),
+        (ledger.bob_account.clone(), "15.00".to_string()),
+    ];
+    expected.sort();
+    assert_eq!(expected, debts_of(&cost["debtors"]));
+
+    let costs = &app.get(&format!("/ledger/{id}/cost"), token).await["costs"];
+    assert_eq!(1, costs.as_array().unwrap().len());
+    assert_eq!(expected, debts_of(&costs[0]["debtors"]));
+
+    let path = format!(
+        "/ledger/{id}/account/{}/cost/{cost_id}",
+        ledger.admin_account
+    );
+    assert_eq!(cost, app.get(&path, token).await);
+    let cost = app
+        .call(
+            Method::PATCH,
+            &path,
+            token,
+            Some(json!({ "amount": "40.00", "split": "equal" })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!("40.00", cost["amount"]);
+    assert_eq!("groceries", cost["description"]);
+    let cleared = app
+        .call(
+            Method::PATCH,
+            &path,
+            token,
+            Some(json!({ "description": null })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!(Value::Null, cleared["description"]);
+    assert_eq!("40.00", cleared["amount"]);
+    let amounts: Vec<String> = debts_of(&cost["debtors"])
+        .into_iter()
+        .map(|(_, amount)| amount)
+        .collect();
+    assert_eq!(vec!["20.00", "20.00"], amounts);
+
+    let costs = &app.get(&format!("/ledger/{id}/cost"), token).await["costs"];
+    assert_eq!(debts_of(&cost["debtors"]), debts_of(&costs[0]["debtors"]));
+
+    app.call(Method::DELETE, &path, token, None, StatusCode::OK)
+        .await;
+    app.call(Method::GET, &path, token, None, StatusCode::NOT_FOUND)
+        .await;
+    let costs = &app.get(&format!("/ledger/{id}/cost"), token).await["costs"];
+    assert!(costs.as_array().unwrap().is_empty());
+
+    app.call(Method::DELETE, &path, token, None, StatusCode::NOT_FOUND)
+        .await;
+}
+
+#[tokio::test]
+async fn only_owners_change_costs_and_payments() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    app.post(
+        "/admin/user",
+        token,
+        json!({ "provider": "discord", "subject": "43", "role": "member" }),
+    )
+    .await;
+    let other = app.login("43").await.access_token;
+    let me = app.get("/me", &other).await;
+    let other_account = app
+        .post(
+            &format!("/ledger/{id}/account"),
+            token,
+            json!({ "name": "other", "user_id": me["user"]["id"] }),
+        )
+        .await;
+    let other_account = other_account["id"].as_str().unwrap();
+
+    let cost = app
+        .post(
+            &format!("/ledger/{id}/cost"),
+            token,
+            json!({
+                "amount": "30.00",
+                "split": "equal",
+                "event_date": "2026-10-01",
+                "debtors": [{ "account_id": other_account }],
+            }),
+        )
+        .await;
+    let payment = app
+        .post(
+            &format!("/ledger/{id}/payment"),
+            token,
+            json!({
+                "amount": "10.00",
+                "event_date": "2026-10-02",
+                "lender_account_id": other_account,
+            }),
+        )
+        .await;
+    let cost_path = format!(
+        "/ledger/{id}/account/{}/cost/{}",
+        ledger.admin_account,
+        cost["id"].as_str().unwrap()
+    );
+    let payment_path = format!(
+        "/ledger/{id}/account/{}/payment/{}",
+        ledger.admin_account,
+        payment["id"].as_str().unwrap()
+    );
+
+    for (method, path, body) in [
+        (Method::PATCH, &cost_path, Some(json!({ "amount": "1.00" }))),
+        (
+            Method::PUT,
+            &cost_path,
+            Some(json!({
+                "amount": "1.00",
+                "event_date": "2026-10-01",
+                "debtors": [{ "account_id": other_account, "amount": "1.00" }],
+            })),
+        ),
+        (Method::DELETE, &cost_path, None),
+        (
+            Method::PUT,
+            &payment_path,
+            Some(json!({
+                "amount": "1.00",
+                "event_date": "2026-10-02",
+                "lender_account_id": other_account,
+            })),
+        ),
+        (Method::DELETE, &payment_path, None),
+    ] {
+        app.call(
+            method.clone(),
+            path,
+            &other,
+            body.clone(),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+
+        // entries are only found under the path of the account that payed them
+        let path = path.replace(&ledger.admin_account, other_account);
+        app.call(method, &path, token, body, StatusCode::NOT_FOUND)
+            .await;
+    }
+
+    // payments are also found under the path of the account that received them
+    app.get(&cost_path, &other).await;
+    app.get(
+        &payment_path.replace(&ledger.admin_account, other_account),
+        &other,
+    )
+    .await;
+    for path in [
+        cost_path.replace(&ledger.admin_account, other_account),
+        payment_path.replace(&ledger.admin_account, &ledger.bob_account),
+    ] {
+        app.call(Method::GET, &path, &other, None, StatusCode::NOT_FOUND)
+            .await;
+    }
+
+    app.call(Method::DELETE, &cost_path, token, None, StatusCode::OK)
+        .await;
+    app.call(Method::DELETE, &payment_path, token, None, StatusCode::OK)
+        .await;
+
+    // accounts are deleted with their history, only by their user
+    app.call(
+        Method::DELETE,
+        &format!("/ledger/{id}/account/{}", ledger.bob_account),
+        &other,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    // the settlement pays for every account of the ledger
+    app.call(
+        Method::POST,
+        &format!("/ledger/{id}/settlement/apply"),
+        &other,
+        Some(json!({ "event_date": "2026-10-03" })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+
+    // the ledger is shared by all members, only admins change it
+    let ledger_path = format!("/ledger/{id}");
+    app.call(
+        Method::PUT,
+        &ledger_path,
+        &other,
+        Some(json!({ "name": "mine" })),
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    app.call(
+        Method::DELETE,
+        &ledger_path,
+        &other,
+        None,
+        StatusCode::FORBIDDEN,
+    )
+    .await;
+    app.get(&ledger_path, &other).await;
+}
+
+#[tokio::test]
+async fn reject_cost_with_debts_not_adding_up() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+
+    app.call(
+        Method::POST,
+        &format!("/ledger/{}/cost", ledger.id),
+        &ledger.token,
+        Some(json!({
+            "amount": "30.00",
+            "event_date": "2026-10-01",
+            "debtors": [{ "account_id": ledger.bob_account, "amount": "10.00" }],
+        })),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn list_costs_filtered_sorted_and_paged() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    for (amount, day, description, tags) in [
+        ("10.00", 1, "Groceries", json!(["food"])),
+        ("25.00", 2, "Train tickets", json!(["travel"])),
+        ("7.50", 3, "Bakery and groceries", json!(["food", "weekly"])),
+        ("40.00", 3, "Dinner", json!(["food"])),
+    ] {
+        app.post(
+            &format!("/ledger/{id}/cost"),
+            token,
+            json!({
+                "amount": amount,
+                "split": "equal",
+                "event_date": format!("2026-10-0{day}"),
+                "description": description,
+                "tags": tags,
+                "debtors": [
+                    { "account_id": ledger.admin_account },
+                    { "account_id": ledger.bob_account },
+                ],
+            }),
+        )
+        .await;
+    }
+    let amounts = |page: &Value| -> Vec<String> {
+        page["costs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|cost| cost["amount"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let page = app
+        .get(
+            &format!("/ledger/{id}/cost?tags=food&description=GROCER"),
+            token,
+        )
+        .await;
+    assert_eq!(vec!["7.50", "10.00"], amounts(&page));
+    assert_eq!(2, page["costs"][0]["debtors"].as_array().unwrap().len());
+    assert_eq!(Value::Null, page["next_cursor"]);
+
+    let page = app
+        .get(
+            &format!("/ledger/{id}/cost?min_amount=10.00&max_amount=30.00&end_date=2026-10-02"),
+            token,
+        )
+        .await;
+    assert_eq!(vec!["25.00", "10.00"], amounts(&page));
+
+    let path = format!("/ledger/{id}/cost?sort=amount&order=asc&limit=3");
+    let first = app.get(&path, token).await;
+    assert_eq!(vec!["7.50", "10.00", "25.00"], amounts(&first));
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = app.get(&format!("{path}&cursor={cursor}"), token).await;
+    assert_eq!(vec!["40.00"], amounts(&second));
+    assert_eq!(Value::Null, second["next_cursor"]);
+
+    app.call(
+        Method::GET,
+        &format!("/ledger/{id}/cost?sort=date&limit=3&cursor={cursor}"),
+        token,
+        None,
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    app.call(
+        Method::GET,
+        &format!("/ledger/{id}/cost?limit=201"),
+        token,
+        None,
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn create_update_and_delete_payment() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    // admins can record payments for accounts of others
+    let payment = app
+        .post(
+            &format!("/ledger/{id}/account/{}/payment", ledger.bob_account),
+            token,
+            json!({
+                "lender_account_id": ledger.admin_account,
+                "amount": "12.50",
+                "event_date": "2026-10-02",
+            }),
+        )
+        .await;
+    let payment_id = payment["id"].as_str().unwrap();
+    assert_eq!(ledger.bob_account, payment["payer_account_id"]);
+
+    let path = format!(
+        "/ledger/{id}/account/{}/payment/{payment_id}",
+        ledger.bob_account
+    );
+    let payment = app
+        .call(
+            Method::PUT,
+            &path,
+            token,
+            Some(json!({
+                "lender_account_id": ledger.admin_account,
+                "amount": "13.00",
+                "event_date": "2026-10-03",
+                "description": "rent",
+            })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!("13.00", payment["amount"]);
+    assert_eq!(payment, app.get(&path, token).await);
+
+    let payments = &app.get(&format!("/ledger/{id}/payment"), token).await["payments"];
+    assert_eq!(1, payments.as_array().unwrap().len());
+    assert_eq!("rent", payments[0]["description"]);
+
+    app.call(Method::DELETE, &path, token, None, StatusCode::OK)
+        .await;
+    let payments = &app.get(&format!("/ledger/{id}/payment"), token).await["payments"];
+    assert!(payments.as_array().unwrap().is_empty());
+    app.call(Method::GET, &path, token, None, StatusCode::NOT_FOUND)
+        .await;
+}
+
+#[tokio::test]
+async fn list_payments_filtered_paged_and_per_account() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    for (payer, lender, amount, day) in [
+        (&ledger.bob_account, &ledger.admin_account, "10.00", 1),
+        (&ledger.bob_account, &ledger.admin_account, "20.00", 2),
+        (&ledger.admin_account, &ledger.bob_account, "5.00", 3),
+    ] {
+        app.post(
+            &format!("/ledger/{id}/account/{payer}/payment"),
+            token,
+            json!({
+                "lender_account_id": lender,
+                "amount": amount,
+                "event_date": format!("2026-10-0{day}"),
+            }),
+        )
+        .await;
+    }
+    let amounts = |payments: &Value| -> Vec<String> {
+        payments
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|payment| payment["amount"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let page = app
+        .get(
+            &format!(
+                "/ledger/{id}/payment?payer_account_id={}&max_amount=15.00",
+                ledger.bob_account
+            ),
+            token,
+        )
+        .await;
+    assert_eq!(vec!["10.00"], amounts(&page["payments"]));
+
+    let path = format!("/ledger/{id}/payment?start_date=2026-10-02&limit=1");
+    let first = app.get(&path, token).await;
+    assert_eq!(vec!["5.00"], amounts(&first["payments"]));
+    let cursor = first["next_cursor"].as_str().unwrap();
+    let second = app.get(&format!("{path}&cursor={cursor}"), token).await;
+    assert_eq!(vec!["20.00"], amounts(&second["payments"]));
+    assert_eq!(Value::Null, second["next_cursor"]);
+
+    let payments = app
+        .get(
+            &format!("/ledger/{id}/account/{}/payment", ledger.bob_account),
+            token,
+        )
+        .await;
+    assert_eq!(vec!["20.00", "10.00"], amounts(&payments["outgoing"]));
+    assert_eq!(vec!["5.00"], amounts(&payments["incoming"]));
+
+    let payments = app
+        .get(
+            &format!(
+                "/ledger/{id}/account/{}/payment?end_date=2026-10-01",
+                ledger.bob_account
+            ),
+            token,
+        )
+        .await;
+    assert_eq!(vec!["10.00"], amounts(&payments["outgoing"]));
+    assert!(payments["incoming"].as_array().unwrap().is_empty());
+
+    // accounts of other ledgers are not found
+    let other = app.post("/ledger", token, json!({ "name": "trip" })).await;
+    app.call(
+        Method::GET,
+        &format!(
+            "/ledger/{}/account/{}/payment",
+            other["id"].as_str().unwrap(),
+            ledger.bob_account
+        ),
+        token,
+        None,
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn snapshot_of_costs_and_payments() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    app.post(
+        &format!("/ledger/{id}/cost"),
+        token,
+        json!({
+            "amount": "30.00",
+            "split": "equal",
+            "event_date": "2026-10-01",
+            "debtors": [
+                { "account_id": ledger.admin_account },
+                { "account_id": ledger.bob_account },
+            ],
+        }),
+    )
+    .await;
+
+    let snapshot = app.get(&format!("/ledger/{id}/snapshot"), token).await;
+    assert_eq!(
+        "15.00",
+        amount(&snapshot, &ledger.admin_account, &ledger.bob_account)
+    );
+    assert_eq!(
+        "-15.00",
+        amount(&snapshot, &ledger.bob_account, &ledger.admin_account)
+    );
+
+    app.post(
+        &format!("/ledger/{id}/account/{}/payment", ledger.bob_account),
+        token,
+        json!({
+            "lender_account_id": ledger.admin_account,
+            "amount": "15.00",
+            "event_date": "2026-10-05",
+        }),
+    )
+    .await;
+
+    let snapshot = app.get(&format!("/ledger/{id}/snapshot"), token).await;
+    assert_eq!(
+        "0.00",
+        amount(&snapshot, &ledger.admin_account, &ledger.bob_account)
+    );
+
+    // the payment was made afterwards
+    let snapshot = app
+        .get(&format!("/ledger/{id}/snapshot?as_of=2026-10-03"), token)
+        .await;
+    assert_eq!(
+        "15.00",
+        amount(&snapshot, &ledger.admin_account, &ledger.bob_account)
+    );
+
+    let settlement = app.get(&format!("/ledger/{id}/settlement"), token).await;
+    assert!(settlement.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rebuild_balances_reports_drift() {
+    let Some(app) = common::spawn().await else {
+        return;
+    };
+    let ledger = setup(&app).await;
+    let Ledger { token, id, .. } = &ledger;
+
+    let cost = app
+        .post(
+            &format!("/ledger/{id}/cost"),
+            token,
+            json!({
+                "amount": "30.00",
+                "split": "equal",
+                "event_date": "2026-10-01",
+                "debtors": [
+                    { "account_id": ledger.admin_account },
+                    { "account_id": ledger.bob_account },
+                ],
+            }),
+        )
+        .await;
+    app.call(
+        Method::PATCH,
+        &format!(
+            "/ledger/{id}/account/{}/cost/{}",
+            ledger.admin_account,
+            cost["id"].as_str().unwrap()
+        ),
+        token,
+        Some(json!({ "amount": "40.00", "split": "equal" })),
+        StatusCode::OK,
+    )
+    .await;
+
+    // every write keeps the stored balances up to date
+    let drifts = app.post("/admin/balances/rebuild", token, json!({})).await;
+    assert_eq!(json!([]), drifts);
+
+    let pool = app.pool().await;
+    sqlx::query("UPDATE balance SET amount = 0")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+
+    let drifts = app.post("/admin/balances/rebuild", token, json!({})).await;
+    let drift = drifts
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|drift| drift["payer_account_id"] == ledger.admin_account.as_str())
+        .expect("drift of the admin account is reported");
+    assert_eq!("0.00", drift["stored"]);
+    assert_eq!("20.00", drift["calculated"]);
+    assert_eq!(2, drifts.as_array().unwrap().len());
+
+    let snapshot = app.get(&format!("/ledger/{id}/snapshot"), token).await;
+    assert_eq!(
+        "20.00",
+        amount(&snapshot, &ledger.admin_account, &ledger.bob_account)
+    );
+}
@@ -0,0 +1,280 @@
+//! The single user api against an in-memory sqlite database, runs without a postgres server
+
+#![allow(clippy::expect_used, clippy::unwrap_used)]
+
+use std::net::{SocketAddr, TcpListener};
+
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use sqlx::sqlite::SqlitePoolOptions;
+
+struct TestApp {
+    address: SocketAddr,
+    client: reqwest::Client,
+}
+
+async fn spawn() -> TestApp {
+    // every connection to `:memory:` would get its own database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("can open in-memory database");
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&pool)
+        .await
+        .expect("can enable foreign keys");
+    money_tracker::migrate_sqlite(&pool)
+        .await
+        .expect("can run migrations");
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("api can bind");
+    listener.set_nonblocking(true).expect("api is non blocking");
+    let address = listener.local_addr().expect("api has an address");
+    let app = money_tracker::single_user_app(pool).await;
+
+    tokio::spawn(async move {
+        axum::Server::from_tcp(listener)
+            .expect("api can listen")
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("api can serve");
+    });
+
+    TestApp {
+        address,
+        client: reqwest::Client::new(),
+    }
+}
+
+impl TestApp {
+    /// Call the api without a login and return the json body, panics on another status
+    async fn call(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+        expected: StatusCode,
+    ) -> Value {
+        let mut request = self
+            .client
+            .request(method.clone(), format!("http://{}{path}", self.address));
+
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        let response = request.send().await.expect("api can be called");
+        let status = response.status();
+        let text = response.text().await.expect("body can be read");
+
+        assert_eq!(expected, status, "{method} {path}: {text}");
+
+        serde_json::from_str(&text).unwrap_or(Value::Null)
+    }
+
+    async fn get(&self, path: &str) -> Value {
+        self.call(Method::GET, path, None, StatusCode::OK).await
+    }
+
+    async fn post(&self, path: &str, body: Value) -> Value {
+        self.call(Method::POST, path, Some(body), StatusCode::OK)
+            .await
+    }
+}
+
+fn id(value: &Value) -> String {
+    value["id"].as_str().unwrap().to_string()
+}
+
+/// Tags are unique, but not in order
+fn sorted(tags: &Value) -> Vec<&str> {
+    let mut tags: Vec<&str> = tags
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| tag.as_str().unwrap())
+        .collect();
+    tags.sort_unstable();
+    tags
+}
+
+#[tokio::test]
+async fn costs_payments_and_snapshot_without_login() {
+    let app = spawn().await;
+
+    let me = app.get("/me").await;
+    assert_eq!("admin", me["user"]["role"]);
+
+    let ledger = id(&app.post("/ledger", json!({ "name": "flat" })).await);
+    let accounts = app.get(&format!("/ledger/{ledger}/account")).await;
+    assert_eq!(me["user"]["id"], accounts[0]["user_id"]);
+    let owner = id(&accounts[0]);
+    let bob = id(&app
+        .post(
+            &format!("/ledger/{ledger}/account"),
+            json!({ "name": "bob" }),
+        )
+        .await);
+
+    let cost = app
+        .post(
+            &format!("/ledger/{ledger}/cost"),
+            json!({
+                "amount": "30.00",
+                "split": "equal",
+                "event_date": "2026-10-01",
+                "description": "groceries",
+                "tags": ["food", "weekly"],
+                "debtors": [{ "account_id": owner }, { "account_id": bob }],
+            }),
+        )
+        .await;
+    assert_eq!(owner, cost["account_id"]);
+
+    let cost = app
+        .call(
+            Method::PATCH,
+            &format!("/ledger/{ledger}/account/{owner}/cost/{}", id(&cost)),
+            Some(json!({ "amount": "40.00", "split": "equal" })),
+            StatusCode::OK,
+        )
+        .await;
+    assert_eq!("40.00", cost["amount"]);
+    assert_eq!(vec!["food", "weekly"], sorted(&cost["tags"]));
+    assert_eq!(2, cost["debtors"].as_array().unwrap().len());
+    let path = format!("/ledger/{ledger}/account/{owner}/cost/{}", id(&cost));
+    assert_eq!(cost, app.get(&path).await);
+
+    let tags = app.get(&format!("/ledger/{ledger}/tags")).await;
+    assert_eq!(vec!["food", "weekly"], sorted(&tags));
+
+    let costs = app
+        .get(&format!(
+            "/ledger/{ledger}/cost?tags=weekly,food&description=GROC&min_amount=40.00&sort=amount&limit=1"
+        ))
+        .await;
+    assert_eq!("40.00", costs["costs"][0]["amount"]);
+    assert_eq!(2, costs["costs"][0]["debtors"].as_array().unwrap().len());
+    assert_eq!(Value::Null, costs["next_cursor"]);
+    let costs = app
+        .get(&format!("/ledger/{ledger}/cost?tags=food,travel"))
+        .await;
+    assert_eq!(json!([]), costs["costs"]);
+
+    let payment = app
+        .post(
+            &format!("/ledger/{ledger}/account/{bob}/payment"),
+            json!({
+                "lender_account_id": owner,
+                "amount": "5.00",
+                "event_date": "2026-10-05",
+            }),
+        )
+        .await;
+    let payments = app.get(&format!("/ledger/{ledger}/payment")).await;
+    assert_eq!(json!([payment]), payments["payments"]);
+    let path = format!("/ledger/{ledger}/account/{bob}/payment/{}", id(&payment));
+    assert_eq!(payment, app.get(&path).await);
+    let payments = app
+        .get(&format!(
+            "/ledger/{ledger}/payment?payer_account_id={bob}&min_amount=5.00&sort=amount&order=asc"
+        ))
+        .await;
+    assert_eq!(json!([payment]), payments["payments"]);
+    let payments = app
+        .get(&format!("/ledger/{ledger}/payment?lender_account_id={bob}"))
+        .await;
+    assert_eq!(json!([]), payments["payments"]);
+
+    let payments = app
+        .get(&format!("/ledger/{ledger}/account/{owner}/payment"))
+        .await;
+    assert_eq!(json!([payment]), payments["incoming"]);
+    assert_eq!(json!([]), payments["outgoing"]);
+
+    let snapshot = app.get(&format!("/ledger/{ledger}/snapshot")).await;
+    let debt = snapshot
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|debt| debt["payer_account"]["id"] == owner && debt["lender_account"]["id"] == bob)
+        .expect("snapshot has the debt of bob");
+    assert_eq!("15.00", debt["amount"]);
+
+    // the payment was made afterwards
+    let snapshot = app
+        .get(&format!("/ledger/{ledger}/snapshot?as_of=2026-10-03"))
+        .await;
+    assert!(snapshot
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|debt| debt["payer_account"]["id"] == owner && debt["amount"] == "20.00"));
+
+    // every write kept the stored balances up to date
+    let drifts = app.post("/admin/balances/rebuild", json!({})).await;
+    assert_eq!(json!([]), drifts);
+
+    // the account is removed together with its costs and payments
+    app.call(
+        Method::DELETE,
+        &format!("/ledger/{ledger}/account/{bob}"),
+        None,
+        StatusCode::OK,
+    )
+    .await;
+    let payments = app.get(&format!("/ledger/{ledger}/payment")).await;
+    assert!(payments["payments"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn constraints_are_enforced() {
+    let app = spawn().await;
+
+    let rate = json!({
+        "date": "2026-10-01",
+        "from_currency": "USD",
+        "to_currency": "EUR",
+        "rate": 0.5,
+    });
+    app.post("/exchange-rate", rate.clone()).await;
+    app.call(
+        Method::POST,
+        "/exchange-rate",
+        Some(rate),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+
+    let ledger = id(&app.post("/ledger", json!({ "name": "flat" })).await);
+    let other = id(&app.post("/ledger", json!({ "name": "other" })).await);
+    let stranger = id(&app
+        .post(
+            &format!("/ledger/{other}/account"),
+            json!({ "name": "stranger" }),
+        )
+        .await);
+    let owner = id(&app
+        .post(
+            &format!("/ledger/{ledger}/account"),
+            json!({ "name": "owner" }),
+        )
+        .await);
+
+    app.call(
+        Method::POST,
+        &format!("/ledger/{ledger}/account/{owner}/payment"),
+        Some(json!({
+            "lender_account_id": stranger,
+            "amount": "5.00",
+            "event_date": "2026-10-05",
+        })),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+
+    // logins need postgres
+    app.call(Method::GET, "/tokens", None, StatusCode::NOT_FOUND)
+        .await;
+}